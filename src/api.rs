@@ -0,0 +1,232 @@
+use rkyv::{
+    api::serialize_using,
+    rancor::Strategy,
+    ser::{allocator::ArenaHandle, sharing::Share, Serializer},
+    util::{with_arena, AlignedVec},
+    Serialize,
+};
+
+use crate::{InterningAdapter, MultiInterner};
+
+/// Statistics about the values interned while serializing.
+///
+/// These are returned by [`serialize_interned_with_stats`].
+#[cfg(feature = "statistics")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct InternerStats {
+    /// The number of distinct interned values.
+    pub values: usize,
+    /// The number of references to interned values.
+    ///
+    /// Each reference is one interned field which was serialized.
+    pub references: usize,
+    /// The number of values which were referenced exactly once.
+    pub singletons: usize,
+    /// The number of bytes written while serializing interned values.
+    pub interned_bytes: usize,
+}
+
+/// The serializer used by [`to_bytes`] and [`to_bytes_with`].
+pub type InterningSerializer<'a, I, E> = Strategy<
+    InterningAdapter<Serializer<AlignedVec, ArenaHandle<'a>, Share>, I>,
+    E,
+>;
+
+/// Serializes a value to bytes, interning values along the way.
+///
+/// This works like `rkyv::to_bytes`, but interns values using a new
+/// [`MultiInterner`]. Any mix of interning wrappers can be used.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{access_unchecked, rancor::Error, Archive, Archived, Serialize};
+/// use rkyv_intern::Intern;
+///
+/// #[derive(Archive, Serialize)]
+/// struct Log {
+///     #[rkyv(with = Intern)]
+///     user: String,
+/// }
+///
+/// let value = vec![
+///     Log { user: "alice".to_string() },
+///     Log { user: "alice".to_string() },
+/// ];
+/// let bytes = rkyv_intern::to_bytes::<Error>(&value).unwrap();
+///
+/// let archived = unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+/// assert!(std::ptr::eq(&*archived[0].user, &*archived[1].user));
+/// ```
+pub fn to_bytes<E>(
+    value: &impl for<'a> Serialize<InterningSerializer<'a, MultiInterner, E>>,
+) -> Result<AlignedVec, E> {
+    to_bytes_with(value, MultiInterner::new()).map(|(bytes, _)| bytes)
+}
+
+/// Serializes a value to bytes with the given interning.
+///
+/// Returns the serialized bytes and the interning, which can be used to
+/// inspect the interned values or to serialize more values later.
+pub fn to_bytes_with<I, E>(
+    value: &impl for<'a> Serialize<InterningSerializer<'a, I, E>>,
+    interning: I,
+) -> Result<(AlignedVec, I), E> {
+    with_arena(|arena| {
+        let mut serializer = InterningAdapter::new(
+            Serializer::new(AlignedVec::new(), arena.acquire(), Share::new()),
+            interning,
+        );
+        serialize_using::<_, E>(value, &mut serializer)?;
+        let (serializer, interning) = serializer.into_components();
+        Ok((serializer.into_writer(), interning))
+    })
+}
+
+/// Serializes a value to bytes and returns statistics about its interned
+/// values.
+///
+/// This works like [`to_bytes`], but also returns the [`InternerStats`] of
+/// the interner and adapter after serializing.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{rancor::Error, Archive, Serialize};
+/// use rkyv_intern::Intern;
+///
+/// #[derive(Archive, Serialize)]
+/// struct Log {
+///     #[rkyv(with = Intern)]
+///     user: String,
+/// }
+///
+/// let value = ["alice", "alice", "bob"]
+///     .iter()
+///     .map(|user| Log { user: user.to_string() })
+///     .collect::<Vec<_>>();
+/// let (_, stats) =
+///     rkyv_intern::serialize_interned_with_stats::<Error>(&value).unwrap();
+///
+/// assert_eq!(stats.values, 2);
+/// assert_eq!(stats.references, 3);
+/// assert_eq!(stats.singletons, 1);
+/// ```
+#[cfg(feature = "statistics")]
+pub fn serialize_interned_with_stats<E>(
+    value: &impl for<'a> Serialize<InterningSerializer<'a, MultiInterner, E>>,
+) -> Result<(AlignedVec, InternerStats), E> {
+    with_arena(|arena| {
+        let mut serializer = InterningAdapter::new(
+            Serializer::new(AlignedVec::new(), arena.acquire(), Share::new()),
+            MultiInterner::new(),
+        );
+        serialize_using::<_, E>(value, &mut serializer)?;
+
+        let mut stats = InternerStats {
+            interned_bytes: serializer.interned_bytes(),
+            ..InternerStats::default()
+        };
+        let (serializer, interner) = serializer.into_components();
+        for (ref_cnt, count) in interner.ref_count_histogram() {
+            stats.values += count;
+            stats.references =
+                stats.references.saturating_add(ref_cnt.saturating_mul(count));
+            if ref_cnt == 1 {
+                stats.singletons = count;
+            }
+        }
+        Ok((serializer.into_writer(), stats))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        rc::Rc,
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{tests::USERS, to_bytes, to_bytes_with, Intern, Interner};
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Log {
+        #[rkyv(with = Intern)]
+        user: String,
+        tags: Rc<[u8]>,
+    }
+
+    fn logs() -> Vec<Log> {
+        let tags = Rc::<[u8]>::from([1, 2, 3].as_slice());
+        (0..100)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+                tags: tags.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn to_bytes_interned() {
+        let value = logs();
+        let bytes = to_bytes::<Panic>(&value).unwrap();
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        assert!(core::ptr::eq(&*archived[0].user, &*archived[4].user));
+        assert!(core::ptr::eq(&*archived[0].tags, &*archived[1].tags));
+
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn to_bytes_with_interner() {
+        let (bytes, interner) =
+            to_bytes_with::<_, Panic>(&logs(), Interner::<String>::new())
+                .unwrap();
+        assert_eq!(interner.len(), USERS.len());
+        assert_eq!(bytes[..], to_bytes::<Panic>(&logs()).unwrap()[..]);
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn serialize_with_stats() {
+        use crate::{serialize_interned_with_stats, DerefIntern};
+
+        #[derive(Archive, Serialize)]
+        struct Message {
+            #[rkyv(with = Intern)]
+            user: String,
+            #[rkyv(with = DerefIntern)]
+            text: String,
+        }
+
+        let value = (0..100)
+            .map(|i| Message {
+                user: USERS[i % USERS.len()].to_string(),
+                text: i.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, stats) =
+            serialize_interned_with_stats::<Panic>(&value).unwrap();
+        assert_eq!(stats.values, USERS.len() + 100);
+        assert_eq!(stats.references, 200);
+        assert_eq!(stats.singletons, 100);
+        assert!(stats.interned_bytes > 0);
+        assert!(stats.interned_bytes < bytes.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Message>>>(&bytes) };
+        assert!(core::ptr::eq(&*archived[0].user, &*archived[4].user));
+        assert_eq!(&*archived[42].text, "42");
+    }
+}
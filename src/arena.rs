@@ -0,0 +1,222 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{cmp, ptr, ptr::NonNull, slice, str};
+
+use hashbrown::HashMap;
+
+use crate::{
+    error::NotStarted, Entry, Interning, InterningError, InterningState,
+};
+
+/// The minimum size of each chunk of the arena.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// A string interner which copies its values into an arena.
+///
+/// This works like [`Interner<String>`](crate::Interner), but instead of
+/// allocating a `String` for each value, it copies values into large chunks of
+/// memory which are only freed when the interner is dropped. This avoids
+/// fragmenting the heap when interning many short strings.
+pub struct ArenaInterner {
+    // SAFETY: The keys of `value_to_pos` borrow from `chunks`, and are only
+    // valid as long as the chunk they were copied into is alive. The `'static`
+    // lifetime is never exposed outside of this type: keys are only returned
+    // with the lifetime of a borrow of the interner.
+    value_to_pos: HashMap<&'static str, Entry>,
+    // SAFETY: Each chunk is a leaked `Box<[u8]>` which is freed when the
+    // interner is dropped. Chunks are accessed through raw pointers so that
+    // copying a value into the unused part of a chunk doesn't invalidate keys
+    // borrowing from the rest of it. Moving the interner or growing this `Vec`
+    // doesn't move the chunks themselves.
+    chunks: Vec<NonNull<[u8]>>,
+    // The number of bytes used in the last chunk.
+    used: usize,
+}
+
+// SAFETY: `ArenaInterner` owns its chunks, which are plain bytes.
+unsafe impl Send for ArenaInterner {}
+// SAFETY: `ArenaInterner` only mutates its chunks through `&mut self`.
+unsafe impl Sync for ArenaInterner {}
+
+impl ArenaInterner {
+    /// Returns a new, empty interner.
+    pub fn new() -> Self {
+        Self {
+            value_to_pos: HashMap::new(),
+            chunks: Vec::new(),
+            used: 0,
+        }
+    }
+
+    /// The number of interned values.
+    pub fn len(&self) -> usize {
+        self.value_to_pos.len()
+    }
+
+    /// Returns `true` if the interner contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_pos.is_empty()
+    }
+
+    /// The interned values.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Entry)> + '_ {
+        self.value_to_pos.iter().map(|(value, entry)| (*value, entry))
+    }
+
+    /// The number of bytes allocated for the arena.
+    pub fn arena_size(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.len()).sum()
+    }
+
+    /// Copies a value into the arena.
+    fn alloc(&mut self, value: &str) -> &'static str {
+        // Empty values don't need any bytes, and there may not be a chunk to
+        // point into yet.
+        if value.is_empty() {
+            return "";
+        }
+
+        let remaining = self.chunks.last().map_or(0, |c| c.len() - self.used);
+        if value.len() > remaining {
+            let size = cmp::max(CHUNK_SIZE, value.len());
+            let chunk = Box::into_raw(vec![0; size].into_boxed_slice());
+            // SAFETY: `Box::into_raw` never returns a null pointer.
+            self.chunks.push(unsafe { NonNull::new_unchecked(chunk) });
+            self.used = 0;
+        }
+
+        let chunk = self.chunks.last().unwrap();
+        // SAFETY: The last chunk has at least `value.len()` bytes after
+        // `used`, and no keys borrow from them yet.
+        let bytes = unsafe {
+            let start = chunk.as_ptr().cast::<u8>().add(self.used);
+            ptr::copy_nonoverlapping(value.as_ptr(), start, value.len());
+            slice::from_raw_parts(start, value.len())
+        };
+        self.used += value.len();
+        // SAFETY: The bytes were copied from a `str`, and are never modified
+        // or freed while the interner is alive.
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+}
+
+impl Default for ArenaInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ArenaInterner {
+    fn drop(&mut self) {
+        // Remove the keys before freeing the chunks they borrow from.
+        self.value_to_pos.clear();
+        for chunk in self.chunks.drain(..) {
+            // SAFETY: Each chunk was leaked from a `Box` in `alloc`.
+            drop(unsafe { Box::from_raw(chunk.as_ptr()) });
+        }
+    }
+}
+
+impl<E: InterningError> Interning<str, E> for ArenaInterner {
+    type State<'a> = &'a str;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a str,
+    ) -> InterningState<Self::State<'a>> {
+        match self.value_to_pos.get_mut(value) {
+            Some(entry) => entry.restart(value),
+            None => {
+                let key = self.alloc(value);
+                self.value_to_pos.insert(key, Entry::pending());
+                InterningState::Started(value)
+            }
+        }
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        match self.value_to_pos.get_mut(state) {
+            Some(entry) => entry.finish(pos, || None),
+            None => Err(E::new(NotStarted(None))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        testing::check_interning_contract,
+        tests::{serialize_with, USERS},
+        ArenaInterner, DerefIntern,
+    };
+
+    #[test]
+    fn arena_intern_strings() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = DerefIntern)]
+            user: String,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].repeat(i % 10 * 100 + 1),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, ArenaInterner::new())
+                .unwrap();
+        assert_eq!(interner.len(), 20);
+        for (user, _) in interner.iter() {
+            assert!(value.iter().any(|log| log.user == user));
+        }
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn arena_intern_empty_first() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = DerefIntern)]
+            user: String,
+        }
+
+        let value = ["", USERS[0], "", USERS[0]]
+            .iter()
+            .map(|user| Log {
+                user: String::from(*user),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, ArenaInterner::new())
+                .unwrap();
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.arena_size(), 16 * 1024);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn arena_interner_follows_contract() {
+        check_interning_contract::<_, Panic>(&mut ArenaInterner::new());
+    }
+}
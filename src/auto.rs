@@ -0,0 +1,435 @@
+use alloc::{borrow::ToOwned, boxed::Box};
+use core::{borrow::Borrow, hash::Hash, ops::Deref};
+
+use hashbrown::HashMap;
+use rkyv::{
+    rancor::{Fallible, Source, Strategy},
+    ser::Writer,
+    traits::LayoutRaw,
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    Archive, ArchiveUnsized, Deserialize, DeserializeUnsized, Place, Serialize,
+    SerializeUnsized,
+};
+
+use crate::{
+    deserialize_boxed, ArchivedMaybeInterned, Interner, Interning,
+    InterningAdapter, InterningError, InterningExt as _, InterningState,
+    MaybeInternedResolver,
+};
+
+/// A serializer which knows how many times values of type `T` occur in the
+/// data being serialized.
+///
+/// This is used by [`AutoIntern`] to decide whether a value should be interned.
+pub trait OccurrenceCounting<T: ?Sized> {
+    /// Returns the number of times `value` occurs in the data being
+    /// serialized.
+    ///
+    /// While counting, this records an occurrence of `value` and returns
+    /// `None`.
+    fn occurrences(&mut self, value: &T) -> Option<usize>;
+}
+
+impl<S, I, T> OccurrenceCounting<T> for InterningAdapter<S, I>
+where
+    I: OccurrenceCounting<T>,
+    T: ?Sized,
+{
+    fn occurrences(&mut self, value: &T) -> Option<usize> {
+        self.interning_mut().occurrences(value)
+    }
+}
+
+impl<S, T, E> OccurrenceCounting<T> for Strategy<S, E>
+where
+    S: OccurrenceCounting<T> + ?Sized,
+    T: ?Sized,
+{
+    fn occurrences(&mut self, value: &T) -> Option<usize> {
+        S::occurrences(self, value)
+    }
+}
+
+/// An interning which counts occurrences of values before interning them.
+///
+/// An `AutoInterner` is used to serialize the same value twice. It starts out
+/// counting: every value passed to [`AutoIntern`] is counted, and nothing is
+/// interned, so the bytes from the counting pass can be thrown away. After
+/// [`finish_counting`](Self::finish_counting), the second pass interns values
+/// which occurred more than once with the underlying interning `I`.
+///
+/// If the occurrences are already known, the counting pass can be skipped by
+/// creating the interner with [`from_occurrences`](Self::from_occurrences).
+#[derive(Debug)]
+pub struct AutoInterner<T, I = Interner<T>> {
+    occurrences: HashMap<T, usize>,
+    counting: bool,
+    interning: I,
+}
+
+impl<T, I: Default> Default for AutoInterner<T, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: Default> AutoInterner<T, I> {
+    /// Returns a new interner which is counting occurrences.
+    pub fn new() -> Self {
+        Self::with_interning(I::default())
+    }
+}
+
+impl<T, I> AutoInterner<T, I> {
+    /// Returns a new interner which is counting occurrences, and which will
+    /// intern values with the given interning.
+    pub fn with_interning(interning: I) -> Self {
+        Self {
+            occurrences: HashMap::new(),
+            counting: true,
+            interning,
+        }
+    }
+
+    /// Returns a new interner which uses the given occurrences instead of
+    /// counting them.
+    ///
+    /// Values which aren't in `occurrences` are stored inline.
+    pub fn from_occurrences(
+        occurrences: HashMap<T, usize>,
+        interning: I,
+    ) -> Self {
+        Self {
+            occurrences,
+            counting: false,
+            interning,
+        }
+    }
+
+    /// Returns whether the interner is still counting occurrences.
+    pub fn is_counting(&self) -> bool {
+        self.counting
+    }
+
+    /// Stops counting occurrences, so that the next serialization interns
+    /// values which occurred more than once.
+    pub fn finish_counting(&mut self) {
+        self.counting = false;
+    }
+
+    /// Returns the number of occurrences of `value` that have been counted.
+    pub fn occurrences_of<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.occurrences.get(value).copied().unwrap_or(0)
+    }
+
+    /// Returns a reference to the underlying interning.
+    pub fn interning(&self) -> &I {
+        &self.interning
+    }
+
+    /// Consumes the interner, returning the underlying interning.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+
+    /// Consumes the interner, returning the counted occurrences.
+    pub fn into_occurrences(self) -> HashMap<T, usize> {
+        self.occurrences
+    }
+}
+
+impl<T, I> OccurrenceCounting<T> for AutoInterner<T::Owned, I>
+where
+    T::Owned: Hash + Eq + Borrow<T>,
+    T: Hash + Eq + ToOwned + ?Sized,
+{
+    fn occurrences(&mut self, value: &T) -> Option<usize> {
+        if !self.counting {
+            return Some(self.occurrences_of(value));
+        }
+
+        match self.occurrences.get_mut(value) {
+            Some(count) => *count += 1,
+            None => {
+                self.occurrences.insert(value.to_owned(), 1);
+            }
+        }
+        None
+    }
+}
+
+impl<T, I, E> Interning<T, E> for AutoInterner<T::Owned, I>
+where
+    I: Interning<T, E>,
+    T: ToOwned + ?Sized,
+    E: InterningError,
+{
+    type State<'a> = Option<I::State<'a>>
+    where
+        T: 'a;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a T,
+    ) -> InterningState<Self::State<'a>> {
+        // The bytes from the counting pass are thrown away, so nothing may be
+        // recorded at their positions.
+        if self.counting {
+            return InterningState::Started(None);
+        }
+
+        match self.interning.start_interning(value) {
+            InterningState::Started(state) => {
+                InterningState::Started(Some(state))
+            }
+            InterningState::Pending => InterningState::Pending,
+            InterningState::Finished(pos) => InterningState::Finished(pos),
+        }
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        match state {
+            Some(state) => self.interning.finish_interning(state, pos),
+            None => Ok(()),
+        }
+    }
+
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        if !self.counting {
+            self.interning.record_hit(value, pos);
+        }
+    }
+}
+
+/// A wrapper that interns values only if they occur more than once.
+///
+/// Interning a value which only occurs once doesn't save any space, and
+/// interning a short value can cost more than storing copies of it. Instead of
+/// picking a threshold like [`MinLenIntern`](crate::MinLenIntern),
+/// `AutoIntern` stores values inline if they occur once and interns them if
+/// they occur multiple times.
+///
+/// The serializer must implement [`OccurrenceCounting`] for the dereferenced
+/// value, usually with an [`AutoInterner`]. Since the occurrences have to be
+/// known before the first value is written, serialization takes two passes:
+///
+/// 1. Serialize the value with a counting `AutoInterner`, and throw away the
+///    bytes. Every value is stored inline.
+/// 2. Call [`AutoInterner::finish_counting`] and serialize the value again
+///    with the same interner.
+///
+/// The first pass can be skipped by creating the interner with
+/// [`AutoInterner::from_occurrences`].
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{access_unchecked, rancor::Error, Archive, Archived, Serialize};
+/// use rkyv_intern::{AutoIntern, AutoInterner};
+///
+/// #[derive(Archive, Serialize)]
+/// struct Log {
+///     #[rkyv(with = AutoIntern)]
+///     user: String,
+/// }
+///
+/// let value = vec![
+///     Log { user: "alice".to_string() },
+///     Log { user: "alice".to_string() },
+///     Log { user: "bob".to_string() },
+/// ];
+///
+/// let interner = AutoInterner::<String>::new();
+/// let (_, mut interner) =
+///     rkyv_intern::to_bytes_with::<_, Error>(&value, interner).unwrap();
+/// interner.finish_counting();
+/// let (bytes, _) =
+///     rkyv_intern::to_bytes_with::<_, Error>(&value, interner).unwrap();
+///
+/// let archived = unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+/// assert!(archived[0].user.is_interned());
+/// assert!(archived[2].user.is_inline());
+/// ```
+#[derive(Debug)]
+pub struct AutoIntern;
+
+impl<T> ArchiveWith<T> for AutoIntern
+where
+    T: Archive + Deref,
+    T::Target: ArchiveUnsized,
+{
+    type Archived = ArchivedMaybeInterned<
+        <T::Target as ArchiveUnsized>::Archived,
+        T::Archived,
+    >;
+    type Resolver = MaybeInternedResolver<T::Resolver>;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedMaybeInterned::resolve_from_parts(
+            field,
+            field.deref(),
+            resolver,
+            out,
+        );
+    }
+}
+
+impl<T, S> SerializeWith<T, S> for AutoIntern
+where
+    T: Serialize<S> + Deref,
+    T::Target: SerializeUnsized<S>,
+    S: Interning<T::Target>
+        + OccurrenceCounting<T::Target>
+        + Writer
+        + Fallible
+        + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        match serializer.occurrences(field.deref()) {
+            Some(count) if count > 1 => {
+                let resolver = serializer.resolve_interned(field.deref())?;
+                Ok(MaybeInternedResolver::Interned(resolver))
+            }
+            _ => {
+                let resolver = field.serialize(serializer)?;
+                Ok(MaybeInternedResolver::Inline(resolver))
+            }
+        }
+    }
+}
+
+impl<T, D>
+    DeserializeWith<
+        ArchivedMaybeInterned<
+            <T::Target as ArchiveUnsized>::Archived,
+            T::Archived,
+        >,
+        T,
+        D,
+    > for AutoIntern
+where
+    T: Archive + Deref + From<Box<T::Target>>,
+    T::Archived: Deserialize<T, D>,
+    T::Target: ArchiveUnsized + LayoutRaw,
+    <T::Target as ArchiveUnsized>::Archived: DeserializeUnsized<T::Target, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedMaybeInterned<
+            <T::Target as ArchiveUnsized>::Archived,
+            T::Archived,
+        >,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        match field {
+            ArchivedMaybeInterned::Inline(value) => {
+                value.deserialize(deserializer)
+            }
+            ArchivedMaybeInterned::Interned(value) => {
+                Ok(T::from(deserialize_boxed(value.get(), deserializer)?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        format,
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_interned, serialize_with, USERS},
+        AutoIntern, AutoInterner, MinLenIntern,
+    };
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Auto {
+        #[rkyv(with = AutoIntern)]
+        name: String,
+    }
+
+    #[derive(Archive, Serialize)]
+    struct AlwaysInline {
+        #[rkyv(with = MinLenIntern<{ usize::MAX }>)]
+        name: String,
+    }
+
+    #[derive(Archive, Serialize)]
+    struct AlwaysIntern {
+        #[rkyv(with = MinLenIntern<0>)]
+        name: String,
+    }
+
+    #[test]
+    fn auto_intern_size() {
+        // Repeated user names, unique short ids, and unique long messages.
+        let names = (0..300)
+            .map(|i| match i % 3 {
+                0 => USERS[i / 3 % USERS.len()].to_string(),
+                1 => format!("#{}", i),
+                _ => format!("a unique message, number {}", i),
+            })
+            .collect::<Vec<_>>();
+
+        let value = names
+            .iter()
+            .map(|name| Auto { name: name.clone() })
+            .collect::<Vec<_>>();
+        let (_, mut interner) =
+            serialize_with::<_, _, Panic>(&value, AutoInterner::<String>::new())
+                .unwrap();
+        assert!(interner.interning().is_empty());
+        assert_eq!(interner.occurrences_of(USERS[0]), 25);
+        interner.finish_counting();
+        let (auto, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.interning().len(), USERS.len());
+
+        let inline = names
+            .iter()
+            .map(|name| AlwaysInline { name: name.clone() })
+            .collect::<Vec<_>>();
+        let inline = serialize_interned::<_, Panic>(&inline).unwrap();
+        let interned = names
+            .iter()
+            .map(|name| AlwaysIntern { name: name.clone() })
+            .collect::<Vec<_>>();
+        let interned = serialize_interned::<_, Panic>(&interned).unwrap();
+        assert!(auto.len() <= inline.len());
+        assert!(auto.len() <= interned.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Auto>>>(&auto) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.name.is_interned(), USERS.contains(&&*b.name));
+            assert_eq!(&*a.name, b.name);
+        }
+        let deserialized = deserialize::<Vec<Auto>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+}
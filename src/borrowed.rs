@@ -0,0 +1,135 @@
+use core::hash::Hash;
+
+use hashbrown::{hash_map, HashMap};
+
+use crate::{
+    error::NotStarted, Entry, Interning, InterningError, InterningState,
+};
+
+/// A value interner which borrows its values instead of cloning them.
+///
+/// This works like [`Interner`](crate::Interner), but uses references to the
+/// values being serialized as its keys instead of owned copies. This avoids
+/// cloning each value when it is first interned, which can halve the memory
+/// used for large values that are all kept alive during serialization anyway.
+///
+/// Because [`Interning`] accepts values with any lifetime, the interner can't
+/// check that the values it borrows live long enough. Constructing one is
+/// unsafe, and callers must guarantee that every value it interns outlives
+/// `'a`. This holds when the interner is only used to serialize values which
+/// are borrowed for `'a`, such as a root value which outlives the interner.
+pub struct BorrowedInterner<'a, T: ?Sized> {
+    value_to_pos: HashMap<&'a T, Entry>,
+}
+
+impl<'a, T: ?Sized> BorrowedInterner<'a, T> {
+    /// Returns a new, empty interner.
+    ///
+    /// # Safety
+    ///
+    /// Every value interned with the returned interner must remain valid and
+    /// unchanged for `'a`.
+    pub unsafe fn new() -> Self {
+        Self {
+            value_to_pos: HashMap::new(),
+        }
+    }
+
+    /// The number of interned values.
+    pub fn len(&self) -> usize {
+        self.value_to_pos.len()
+    }
+
+    /// Returns `true` if the interner contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_pos.is_empty()
+    }
+
+    /// The interned values.
+    pub fn iter(&self) -> hash_map::Iter<'_, &'a T, Entry> {
+        self.value_to_pos.iter()
+    }
+}
+
+impl<T, E> Interning<T, E> for BorrowedInterner<'_, T>
+where
+    T: Hash + Eq + ?Sized,
+    E: InterningError,
+{
+    type State<'b> = &'b T where T: 'b;
+
+    fn start_interning<'b>(
+        &mut self,
+        value: &'b T,
+    ) -> InterningState<Self::State<'b>> {
+        match self.value_to_pos.get_mut(value) {
+            Some(entry) => entry.restart(value),
+            None => {
+                // SAFETY: The caller of `new` guaranteed that every value
+                // interned outlives the interner's lifetime.
+                let key = unsafe { &*(value as *const T) };
+                self.value_to_pos.insert(key, Entry::pending());
+                InterningState::Started(value)
+            }
+        }
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        match self.value_to_pos.get_mut(state) {
+            Some(entry) => entry.finish(pos, || None),
+            None => Err(E::new(NotStarted(None))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::ptr;
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_with, USERS},
+        BorrowedInterner, Intern,
+    };
+
+    #[test]
+    fn borrow_intern_strings() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        // SAFETY: `value` outlives the interner.
+        let interner = unsafe { BorrowedInterner::<String>::new() };
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), USERS.len());
+        for (key, _) in interner.iter() {
+            assert!(value.iter().any(|log| ptr::eq(*key, &log.user)));
+        }
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+}
@@ -0,0 +1,273 @@
+use alloc::{borrow::ToOwned, collections::BTreeMap};
+use core::{borrow::Borrow, hash::Hash};
+
+use crate::{Entry, Interner, Interning, InterningError, InterningState};
+
+/// The entries a [`BoundedInterner`] evicts when it is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the value which was written first.
+    Oldest,
+    /// Evict the value which has been interned the fewest times.
+    #[cfg(feature = "statistics")]
+    LeastReferenced,
+}
+
+impl EvictionPolicy {
+    /// Returns the key which orders an entry for eviction, smallest first, or
+    /// `None` if its value hasn't been written.
+    fn key(self, entry: &Entry) -> Option<(usize, usize)> {
+        let pos = entry.pos()?;
+        match self {
+            Self::Oldest => Some((0, pos)),
+            #[cfg(feature = "statistics")]
+            Self::LeastReferenced => Some((entry.ref_cnt, pos)),
+        }
+    }
+}
+
+/// An interner which holds at most a fixed number of values.
+///
+/// When the interner is full, interning a new value evicts an existing value
+/// according to its [`EvictionPolicy`]. Evicted values are written again if
+/// they are interned later, so archives may contain duplicates of them.
+///
+/// Pending values are never evicted because their position isn't known yet. If
+/// every value in the interner is pending, new values are written without being
+/// added to the interner.
+///
+/// Written values are kept in eviction order, so evicting a value takes
+/// `O(log n)` time.
+pub struct BoundedInterner<T> {
+    interner: Interner<T>,
+    max_len: usize,
+    policy: EvictionPolicy,
+    /// The hashes of the written values, ordered by their eviction keys.
+    order: BTreeMap<(usize, usize), u64>,
+}
+
+impl<T> BoundedInterner<T> {
+    /// Returns a new, empty interner which holds at most `max_len` values.
+    pub fn new(max_len: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            interner: Interner::new(),
+            max_len,
+            policy,
+            order: BTreeMap::new(),
+        }
+    }
+
+    /// The maximum number of values the interner holds.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// The eviction policy of the interner.
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    /// Returns a reference to the underlying interner.
+    pub fn interner(&self) -> &Interner<T> {
+        &self.interner
+    }
+
+    /// Consumes the bounded interner, returning the underlying interner.
+    pub fn into_inner(self) -> Interner<T> {
+        self.interner
+    }
+
+    fn evict(&mut self) -> bool
+    where
+        T: Hash,
+    {
+        match self.order.pop_first() {
+            Some(((_, pos), hash)) => self.interner.remove_written(hash, pos),
+            None => false,
+        }
+    }
+
+    fn eviction_key<Q>(&self, value: &Q) -> Option<(usize, usize)>
+    where
+        T: Hash + Eq + Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entry = self.interner.as_map().get(value)?;
+        self.policy.key(entry)
+    }
+
+    /// Returns the eviction key of `value` if interning it again can change
+    /// the key.
+    fn hit_key<Q>(&self, value: &Q) -> Option<(usize, usize)>
+    where
+        T: Hash + Eq + Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.policy == EvictionPolicy::Oldest {
+            return None;
+        }
+        self.eviction_key(value)
+    }
+
+    /// Moves a written value to its new place in the eviction order after it
+    /// was interned again.
+    fn reorder<Q>(&mut self, value: &Q, old_key: Option<(usize, usize)>)
+    where
+        T: Hash + Eq + Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let new_key = match (old_key, self.eviction_key(value)) {
+            (Some(old_key), Some(new_key)) if old_key != new_key => new_key,
+            _ => return,
+        };
+        if let Some(hash) = old_key.and_then(|key| self.order.remove(&key)) {
+            self.order.insert(new_key, hash);
+        }
+    }
+}
+
+impl<T, E> Interning<T, E> for BoundedInterner<T::Owned>
+where
+    T::Owned: Hash + Eq + Borrow<T>,
+    T: Hash + Eq + ToOwned + ?Sized,
+    E: InterningError,
+{
+    type State<'a> = Option<<Interner<T::Owned> as Interning<T, E>>::State<'a>>
+    where
+        T: 'a;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a T,
+    ) -> InterningState<Self::State<'a>> {
+        if self.interner.len() >= self.max_len
+            && !self.interner.contains(value)
+            && !self.evict()
+        {
+            return InterningState::Started(None);
+        }
+
+        let old_key = self.hit_key(value);
+        match Interning::<T, E>::start_interning(&mut self.interner, value) {
+            InterningState::Started(state) => {
+                InterningState::Started(Some(state))
+            }
+            InterningState::Pending => InterningState::Pending,
+            InterningState::Finished(pos) => {
+                self.reorder(value, old_key);
+                InterningState::Finished(pos)
+            }
+        }
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        let state = match state {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        self.interner.finish_interning(state, pos)?;
+        if let Some((value, hash)) = state {
+            if let Some(key) = self.eviction_key(value) {
+                self.order.insert(key, hash);
+            }
+        }
+        Ok(())
+    }
+
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        let old_key = self.hit_key(value);
+        Interning::<T, E>::record_hit(&mut self.interner, value, pos);
+        self.reorder(value, old_key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::serialize_with, BoundedInterner, EvictionPolicy, Intern,
+    };
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Event {
+        #[rkyv(with = Intern)]
+        source: String,
+    }
+
+    fn bounded_workload(policy: EvictionPolicy) {
+        let value = (0..1000)
+            .map(|i| Event {
+                source: (i % 20 * (i % 3)).to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) = serialize_with::<_, _, Panic>(
+            &value,
+            BoundedInterner::new(8, policy),
+        )
+        .unwrap();
+        assert_eq!(interner.interner().len(), 8);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Event>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Event>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn bounded_oldest() {
+        bounded_workload(EvictionPolicy::Oldest);
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn bounded_least_referenced() {
+        bounded_workload(EvictionPolicy::LeastReferenced);
+    }
+
+    fn bounded_survivors(policy: EvictionPolicy) -> [bool; 3] {
+        let value = ["a", "b", "a", "c"]
+            .iter()
+            .map(|source| Event {
+                source: source.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (_, interner) = serialize_with::<_, _, Panic>(
+            &value,
+            BoundedInterner::new(2, policy),
+        )
+        .unwrap();
+        ["a", "b", "c"].map(|source| interner.interner().contains(source))
+    }
+
+    #[test]
+    fn bounded_evicts_oldest() {
+        assert_eq!(
+            bounded_survivors(EvictionPolicy::Oldest),
+            [false, true, true],
+        );
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn bounded_evicts_least_referenced() {
+        assert_eq!(
+            bounded_survivors(EvictionPolicy::LeastReferenced),
+            [true, false, true],
+        );
+    }
+}
@@ -0,0 +1,409 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::ops::Deref;
+#[cfg(feature = "bytecheck")]
+use core::{marker::PhantomData, ops::Range};
+
+#[cfg(all(feature = "bytecheck", feature = "alloc"))]
+use rkyv::{
+    api::access_with_context,
+    bytecheck::CheckBytes,
+    rancor::Strategy,
+    validation::{shared::SharedValidator, Validator},
+    Portable,
+};
+use rkyv::{
+    boxed::{ArchivedBox, BoxResolver},
+    rancor::Fallible,
+    ser::Writer,
+    with::{ArchiveWith, SerializeWith},
+    ArchiveUnsized, Place, SerializeUnsized,
+};
+#[cfg(feature = "alloc")]
+use rkyv::{
+    rancor::Source, traits::LayoutRaw, with::DeserializeWith,
+    DeserializeUnsized,
+};
+
+#[cfg(feature = "alloc")]
+use crate::deserialize_boxed;
+use crate::{Interning, InterningError, InterningExt as _};
+
+/// A wrapper that interns the value behind a pointer and archives it as an
+/// `ArchivedBox`.
+///
+/// This interns values like [`DerefIntern`](crate::DerefIntern), but archives
+/// each reference as a plain `ArchivedBox` instead of an
+/// `ArchivedRc<_, InternFlavor>`. Consumers which reject shared pointers can
+/// read the archive as if every field owned its value, and equal values still
+/// share a single archived copy.
+///
+/// # Validation
+///
+/// rkyv's own validation requires every box to own a separate range of the
+/// archive, so `rkyv::access` rejects archives where two boxes point to the
+/// same value. With the `bytecheck` feature, [`InternBox::access`] validates
+/// them with an [`InternBoxValidator`] instead. Otherwise, deduplicated
+/// archives can only be read with `access_unchecked`. Use `DerefIntern` if the
+/// archive has to be validated with rkyv's `access`.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::InternBox;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = InternBox)]
+///     name: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InternBox;
+
+impl<T: Deref> ArchiveWith<T> for InternBox
+where
+    T::Target: ArchiveUnsized,
+{
+    type Archived = ArchivedBox<<T::Target as ArchiveUnsized>::Archived>;
+    type Resolver = BoxResolver;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedBox::resolve_from_ref(field.deref(), resolver, out);
+    }
+}
+
+impl<T, S> SerializeWith<T, S> for InternBox
+where
+    T: Deref,
+    T::Target: SerializeUnsized<S>,
+    S: Interning<T::Target> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        let pos = serializer.serialize_interned(field.deref())?;
+        Ok(BoxResolver::from_pos(pos))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, D>
+    DeserializeWith<ArchivedBox<<T::Target as ArchiveUnsized>::Archived>, T, D>
+    for InternBox
+where
+    T: Deref + From<Box<T::Target>>,
+    T::Target: ArchiveUnsized + LayoutRaw,
+    <T::Target as ArchiveUnsized>::Archived: DeserializeUnsized<T::Target, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedBox<<T::Target as ArchiveUnsized>::Archived>,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        Ok(T::from(deserialize_boxed(field.get(), deserializer)?))
+    }
+}
+
+/// A validator for archives where several [`InternBox`]es point to the same
+/// value.
+///
+/// This checks pointers like rkyv's `ArchiveValidator`: each target has to be
+/// aligned, lie in the archive and end before the value that points to it.
+/// Unlike `ArchiveValidator`, it doesn't require the targets of sibling
+/// pointers to be disjoint, so a shared value is checked again for each box
+/// that points to it. Since targets always lie before their pointer,
+/// validation can't loop, but archives where shared values point to other
+/// shared values can take a long time to check.
+///
+/// Only shared access is checked. Archives accepted by this validator must not
+/// be accessed mutably, since two boxes may point to the same bytes.
+#[cfg(feature = "bytecheck")]
+#[derive(Debug)]
+pub struct InternBoxValidator<'a> {
+    subtree_range: Range<usize>,
+    _phantom: PhantomData<&'a [u8]>,
+}
+
+#[cfg(feature = "bytecheck")]
+impl<'a> InternBoxValidator<'a> {
+    /// Creates a new validator for the given bytes.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        let Range { start, end } = bytes.as_ptr_range();
+        Self {
+            subtree_range: start as usize..end as usize,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "bytecheck")]
+const _: () = {
+    use core::{alloc::Layout, error::Error, fmt};
+
+    use rkyv::{rancor::Source, validation::ArchiveContext};
+
+    #[derive(Debug)]
+    struct InvalidBoxPointer {
+        address: usize,
+        size: usize,
+        subtree_range: Range<usize>,
+    }
+
+    impl fmt::Display for InvalidBoxPointer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "pointer to {:#x} with size {} overran range {:#x}..{:#x}",
+                self.address,
+                self.size,
+                self.subtree_range.start,
+                self.subtree_range.end,
+            )
+        }
+    }
+
+    impl Error for InvalidBoxPointer {}
+
+    #[derive(Debug)]
+    struct UnalignedBoxPointer {
+        address: usize,
+        align: usize,
+    }
+
+    impl fmt::Display for UnalignedBoxPointer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "pointer to {:#x} is unaligned for alignment {}",
+                self.address, self.align,
+            )
+        }
+    }
+
+    impl Error for UnalignedBoxPointer {}
+
+    #[derive(Debug)]
+    struct RangePoppedOutOfOrder;
+
+    impl fmt::Display for RangePoppedOutOfOrder {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "subtree range popped out of order")
+        }
+    }
+
+    impl Error for RangePoppedOutOfOrder {}
+
+    // SAFETY: `check_subtree_ptr` only accepts pointers which lie entirely in
+    // the current subtree range, which never grows past the archive.
+    unsafe impl<E: Source> ArchiveContext<E> for InternBoxValidator<'_> {
+        fn check_subtree_ptr(
+            &mut self,
+            ptr: *const u8,
+            layout: &Layout,
+        ) -> Result<(), E> {
+            let start = ptr as usize;
+            let end = ptr.wrapping_add(layout.size()) as usize;
+            if end < start
+                || start < self.subtree_range.start
+                || end > self.subtree_range.end
+            {
+                Err(E::new(InvalidBoxPointer {
+                    address: start,
+                    size: layout.size(),
+                    subtree_range: self.subtree_range.clone(),
+                }))
+            } else if start & (layout.align() - 1) != 0 {
+                Err(E::new(UnalignedBoxPointer {
+                    address: start,
+                    align: layout.align(),
+                }))
+            } else {
+                Ok(())
+            }
+        }
+
+        unsafe fn push_subtree_range(
+            &mut self,
+            root: *const u8,
+            _: *const u8,
+        ) -> Result<Range<usize>, E> {
+            // Values nested in this one have to lie before it, but may overlap
+            // values nested in its siblings. The whole range is restored when
+            // this one is popped.
+            let range = self.subtree_range.clone();
+            self.subtree_range.end = root as usize;
+            Ok(range)
+        }
+
+        unsafe fn pop_subtree_range(
+            &mut self,
+            range: Range<usize>,
+        ) -> Result<(), E> {
+            if range.end < self.subtree_range.end {
+                return Err(E::new(RangePoppedOutOfOrder));
+            }
+            self.subtree_range = range;
+            Ok(())
+        }
+    }
+};
+
+#[cfg(all(feature = "bytecheck", feature = "alloc"))]
+impl InternBox {
+    /// Accesses an archive containing interned boxes, validating it with an
+    /// [`InternBoxValidator`].
+    ///
+    /// This is a version of `rkyv::access` which accepts boxes that share
+    /// their value. Shared pointers such as those from
+    /// [`DerefIntern`](crate::DerefIntern) are validated as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rkyv::{rancor::Error, Archive, Archived, Serialize};
+    /// use rkyv_intern::InternBox;
+    ///
+    /// #[derive(Archive, Serialize)]
+    /// struct Example {
+    ///     #[rkyv(with = InternBox)]
+    ///     name: String,
+    /// }
+    ///
+    /// let value = vec![
+    ///     Example { name: "pi".to_string() },
+    ///     Example { name: "pi".to_string() },
+    /// ];
+    /// let bytes = rkyv_intern::to_bytes::<Error>(&value).unwrap();
+    ///
+    /// let archived =
+    ///     InternBox::access::<Archived<Vec<Example>>, Error>(&bytes).unwrap();
+    /// assert_eq!(archived[0].name.as_ptr(), archived[1].name.as_ptr());
+    /// ```
+    pub fn access<T, E>(bytes: &[u8]) -> Result<&T, E>
+    where
+        T: Portable + for<'a> CheckBytes<InternBoxContext<'a, E>>,
+        E: Source,
+    {
+        access_with_context::<T, _, E>(
+            bytes,
+            &mut Validator::new(
+                InternBoxValidator::new(bytes),
+                SharedValidator::new(),
+            ),
+        )
+    }
+}
+
+/// The validation context used by [`InternBox::access`].
+#[cfg(all(feature = "bytecheck", feature = "alloc"))]
+pub type InternBoxContext<'a, E> =
+    Strategy<Validator<InternBoxValidator<'a>, SharedValidator>, E>;
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_with_interner, USERS},
+        InternBox,
+    };
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Log {
+        #[rkyv(with = InternBox)]
+        user: String,
+    }
+
+    fn logs(count: usize) -> Vec<Log> {
+        (0..count)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn intern_box_strings() {
+        let value = logs(20);
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        assert_eq!(interner.len(), USERS.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(&*a.user, b.user);
+        }
+        assert!(core::ptr::eq(
+            archived[0].user.as_ptr(),
+            archived[USERS.len()].user.as_ptr(),
+        ));
+
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "bytecheck")]
+    #[test]
+    fn intern_box_access_checked() {
+        use rkyv::{access, rancor::Error};
+
+        // Boxes which don't share values validate like any other box.
+        let value = logs(USERS.len());
+        let (bytes, _) = serialize_with_interner::<_, Error>(&value).unwrap();
+        let archived = access::<Archived<Vec<Log>>, Error>(&bytes).unwrap();
+        assert_eq!(&*archived[1].user, USERS[1]);
+
+        // Boxes which share a value claim the same range twice.
+        let value = logs(USERS.len() + 1);
+        let (bytes, _) = serialize_with_interner::<_, Error>(&value).unwrap();
+        assert!(access::<Archived<Vec<Log>>, Error>(&bytes).is_err());
+
+        let archived =
+            InternBox::access::<Archived<Vec<Log>>, Error>(&bytes).unwrap();
+        assert!(core::ptr::eq(
+            archived[0].user.as_ptr(),
+            archived[USERS.len()].user.as_ptr(),
+        ));
+        let deserialized = deserialize::<Vec<Log>, Error>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "bytecheck")]
+    #[test]
+    fn intern_box_access_corrupted() {
+        use rkyv::rancor::Error;
+
+        let value = logs(USERS.len() + 1);
+        let (mut bytes, _) =
+            serialize_with_interner::<_, Error>(&value).unwrap();
+
+        // Point the last box past the end of the archive.
+        let field = {
+            let archived =
+                unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+            let field = &archived[USERS.len()].user as *const _ as usize;
+            field - bytes.as_ptr() as usize
+        };
+        let offset = (bytes.len() - field) as i32;
+        bytes[field..field + 4].copy_from_slice(&offset.to_le_bytes());
+        assert!(InternBox::access::<Archived<Vec<Log>>, Error>(&bytes).is_err());
+    }
+}
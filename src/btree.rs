@@ -0,0 +1,173 @@
+use alloc::{
+    borrow::ToOwned,
+    collections::{btree_map, BTreeMap},
+};
+use core::{borrow::Borrow, iter::FromIterator};
+
+use crate::{
+    error::NotStarted, Entry, Interning, InterningError, InterningState,
+};
+
+/// A value interner backed by a `BTreeMap`.
+///
+/// This works like [`Interner`](crate::Interner), but requires values to be
+/// `Ord` instead of `Hash + Eq`. It doesn't use a hasher, and iterates over its
+/// values in sorted order.
+pub struct BTreeInterner<T> {
+    value_to_pos: BTreeMap<T, Entry>,
+}
+
+impl<T> BTreeInterner<T> {
+    /// Returns a new, empty interner.
+    pub fn new() -> Self {
+        Self {
+            value_to_pos: BTreeMap::new(),
+        }
+    }
+
+    /// The number of interned values.
+    pub fn len(&self) -> usize {
+        self.value_to_pos.len()
+    }
+
+    /// Returns `true` if the interner contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_pos.is_empty()
+    }
+
+    /// The interned values, in sorted order.
+    pub fn iter(&self) -> btree_map::Iter<'_, T, Entry> {
+        self.value_to_pos.iter()
+    }
+
+    /// Preseeds the interner with a value.
+    ///
+    /// The value will be written the first time it is interned. Returns
+    /// `false` if the interner already contained the value.
+    pub fn preseed(&mut self, value: T) -> bool
+    where
+        T: Ord,
+    {
+        match self.value_to_pos.entry(value) {
+            btree_map::Entry::Occupied(_) => false,
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(Entry::preseeded());
+                true
+            }
+        }
+    }
+}
+
+impl<T> Default for BTreeInterner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> Extend<T> for BTreeInterner<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.preseed(value);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for BTreeInterner<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = Self::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<T, E> Interning<T, E> for BTreeInterner<T::Owned>
+where
+    T::Owned: Ord + Borrow<T>,
+    T: Ord + ToOwned + ?Sized,
+    E: InterningError,
+{
+    type State<'a> = &'a T where T: 'a;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a T,
+    ) -> InterningState<Self::State<'a>> {
+        match self.value_to_pos.get_mut(value) {
+            Some(entry) => entry.restart(value),
+            None => {
+                self.value_to_pos.insert(value.to_owned(), Entry::pending());
+                InterningState::Started(value)
+            }
+        }
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        match self.value_to_pos.get_mut(state) {
+            Some(entry) => entry.finish(pos, || None),
+            None => Err(E::new(NotStarted(None))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        testing::check_interning_contract,
+        tests::{serialize_with, USERS},
+        BTreeInterner, Intern,
+    };
+
+    #[test]
+    fn btree_intern_strings() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+            code: u16,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: USERS[i * 3 % USERS.len()].to_string(),
+                code: (i % u16::MAX as usize) as u16,
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) = serialize_with::<_, _, Panic>(
+            &value,
+            BTreeInterner::<String>::new(),
+        )
+        .unwrap();
+        assert!(bytes.len() < 20_000);
+        assert!(interner.iter().map(|(value, _)| value).eq(USERS.iter()));
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(*a.user, b.user);
+            assert_eq!(a.code, b.code);
+        }
+
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn btree_interner_follows_contract() {
+        check_interning_contract::<_, Panic>(&mut BTreeInterner::new());
+    }
+}
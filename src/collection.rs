@@ -0,0 +1,362 @@
+use rkyv::with::{Identity, Map, MapKV};
+
+use crate::{DerefIntern, Intern};
+
+/// A wrapper that interns every element of a collection.
+///
+/// This works with `Vec`s, arrays and `Option`s, and archives each element as
+/// it would be archived by [`Intern`].
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::VecIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = VecIntern)]
+///     tags: Vec<String>,
+/// }
+/// ```
+pub type VecIntern = Map<Intern>;
+
+/// A wrapper that interns the target of every element of a collection.
+///
+/// This works with `Vec`s, arrays and `Option`s, and archives each element as
+/// it would be archived by [`DerefIntern`]. Unlike [`VecIntern`], a
+/// `Vec<String>` is archived as a vector of `ArchivedRc<str, InternFlavor>`,
+/// so its elements share archived strings with other fields interned as `str`.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::InternEach;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = InternEach)]
+///     tags: Vec<String>,
+/// }
+/// ```
+pub type InternEach = Map<DerefIntern>;
+
+/// A wrapper that interns every key of a map.
+///
+/// This works with `BTreeMap`s and `HashMap`s, and archives each key as it
+/// would be archived by [`Intern`]. Values are archived normally.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::KeyIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = KeyIntern)]
+///     counts: BTreeMap<String, u32>,
+/// }
+/// ```
+pub type KeyIntern = MapKV<Intern, Identity>;
+
+/// A wrapper that interns every key of a map.
+///
+/// This is another name for [`KeyIntern`], and archives maps the same way. It
+/// pairs with [`InternValues`].
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::InternKeys;
+///
+/// # #[cfg(feature = "std")]
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = InternKeys)]
+///     fields: HashMap<String, u32>,
+/// }
+/// ```
+pub type InternKeys = KeyIntern;
+
+/// A wrapper that interns every value of a map.
+///
+/// This works with `BTreeMap`s and `HashMap`s, and archives each value as it
+/// would be archived by [`Intern`]. Keys are archived normally.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::ValueIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = ValueIntern)]
+///     owners: BTreeMap<u32, String>,
+/// }
+/// ```
+pub type ValueIntern = MapKV<Identity, Intern>;
+
+/// A wrapper that interns every value of a map.
+///
+/// This is another name for [`ValueIntern`], and archives maps the same way.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::InternValues;
+///
+/// # #[cfg(feature = "std")]
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = InternValues)]
+///     owners: HashMap<u32, String>,
+/// }
+/// ```
+pub type InternValues = ValueIntern;
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        collections::BTreeMap,
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{
+            serialize_interned, serialize_with, serialize_with_interner, USERS,
+        },
+        DerefIntern, InternEach, Interner, KeyIntern, ValueIntern, VecIntern,
+    };
+
+    #[test]
+    fn intern_vec_elements() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Team {
+            #[rkyv(with = VecIntern)]
+            members: Vec<String>,
+        }
+
+        let value = Team {
+            members: (0..1000)
+                .map(|i| USERS[i % USERS.len()].to_string())
+                .collect(),
+        };
+
+        let bytes = serialize_interned::<_, Panic>(&value).unwrap();
+        assert!(bytes.len() < 10_000);
+
+        let archived = unsafe { access_unchecked::<Archived<Team>>(&bytes) };
+        for (a, b) in archived.members.iter().zip(value.members.iter()) {
+            assert_eq!(**a, *b);
+        }
+
+        let deserialized = deserialize::<Team, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_each_element() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Team {
+            #[rkyv(with = DerefIntern)]
+            lead: String,
+            #[rkyv(with = InternEach)]
+            members: Vec<String>,
+        }
+
+        let value = (0..10)
+            .map(|i| Team {
+                lead: USERS[i % USERS.len()].to_string(),
+                members: USERS[..i % USERS.len()]
+                    .iter()
+                    .map(|user| user.to_string())
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, Interner::<String>::new())
+                .unwrap();
+        assert_eq!(interner.len(), USERS.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Team>>>(&bytes) };
+        let member: &str = &archived[2].members[1];
+        assert!(core::ptr::eq(member, &*archived[1].lead));
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert!(a.members.iter().map(|m| &**m).eq(b.members.iter()));
+        }
+
+        let deserialized = deserialize::<Vec<Team>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_map_keys() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Scores {
+            #[rkyv(with = KeyIntern)]
+            by_user: BTreeMap<String, u32>,
+        }
+
+        let value = (0..100)
+            .map(|i| Scores {
+                by_user: USERS
+                    .iter()
+                    .map(|user| (user.to_string(), i))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        assert_eq!(interner.len(), USERS.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Scores>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Scores>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_map_values() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Owners {
+            #[rkyv(with = ValueIntern)]
+            by_id: BTreeMap<u32, String>,
+        }
+
+        let value = (0..100)
+            .map(|i| Owners {
+                by_id: (0..10)
+                    .map(|id| (id, USERS[(i + id as usize) % 3].to_string()))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        assert_eq!(interner.len(), 3);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Owners>>>(&bytes) };
+        let owner = |i: usize, id: u32| {
+            let (_, owner) = archived[i]
+                .by_id
+                .iter()
+                .find(|(key, _)| **key == id)
+                .unwrap();
+            &**owner as *const _
+        };
+        assert!(core::ptr::eq(owner(0, 0), owner(1, 2)));
+        assert!(core::ptr::eq(owner(0, 0), owner(0, 3)));
+
+        let deserialized = deserialize::<Vec<Owners>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn intern_hash_map_keys() {
+        use std::collections::HashMap;
+
+        use crate::InternKeys;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Payload {
+            #[rkyv(with = InternKeys)]
+            fields: HashMap<String, u32>,
+        }
+
+        let value = (0..100)
+            .map(|i| Payload {
+                fields: USERS[..i % USERS.len() + 1]
+                    .iter()
+                    .map(|user| (user.to_string(), i as u32))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        assert_eq!(interner.len(), USERS.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Payload>>>(&bytes) };
+        let key = |i: usize| {
+            let (key, _) = archived[i]
+                .fields
+                .iter()
+                .find(|(key, _)| ***key == USERS[0])
+                .unwrap();
+            &**key as *const _
+        };
+        assert!(core::ptr::eq(key(0), key(1)));
+        assert_eq!(archived[5].fields.len(), 2);
+
+        let deserialized =
+            deserialize::<Vec<Payload>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn intern_hash_map_values() {
+        use std::collections::HashMap;
+
+        use crate::InternValues;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Owners {
+            #[rkyv(with = InternValues)]
+            by_id: HashMap<u32, String>,
+        }
+
+        let value = (0..100)
+            .map(|i| Owners {
+                by_id: (0..10)
+                    .map(|id| (id, USERS[(i + id as usize) % 3].to_string()))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        assert_eq!(interner.len(), 3);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Owners>>>(&bytes) };
+        let owner = |i: usize, id: u32| {
+            let (_, owner) = archived[i]
+                .by_id
+                .iter()
+                .find(|(key, _)| **key == id)
+                .unwrap();
+            &**owner as *const _
+        };
+        assert!(core::ptr::eq(owner(0, 0), owner(1, 2)));
+        assert!(core::ptr::eq(owner(0, 0), owner(0, 3)));
+
+        let deserialized = deserialize::<Vec<Owners>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+}
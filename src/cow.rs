@@ -0,0 +1,306 @@
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+};
+use core::hash::Hash;
+
+use hashbrown::{hash_map, HashMap};
+use rkyv::{
+    rancor::{Fallible, Source},
+    rc::{ArchivedRc, RcResolver},
+    ser::Writer,
+    traits::LayoutRaw,
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    ArchiveUnsized, DeserializeUnsized, Place, SerializeUnsized,
+};
+
+use crate::{
+    deserialize_boxed, error::NotStarted, Entry, InternFlavor, Interning,
+    InterningError, InterningExt as _, InterningState,
+};
+
+/// A value interner which can borrow values instead of cloning them.
+///
+/// This works like [`Interner`](crate::Interner), but its keys may borrow
+/// values for `'a`. Values added with
+/// [`preseed_borrowed`](Self::preseed_borrowed) are stored as borrowed keys,
+/// while values first seen during serialization are cloned as usual, since
+/// [`Interning`] can't prove that they live for `'a`.
+///
+/// Preseeding the values from the data being serialized lets a single pass of
+/// serialization intern them without cloning any of them. Unlike
+/// [`BorrowedInterner`](crate::BorrowedInterner), this is entirely safe.
+///
+/// # Example
+///
+/// ```
+/// use rkyv_intern::CowInterner;
+///
+/// let users = vec!["Alice".to_string(), "Bob".to_string()];
+/// let mut interner = CowInterner::<str>::new();
+/// for user in users.iter() {
+///     interner.preseed_borrowed(user);
+/// }
+/// assert_eq!(interner.len(), 2);
+/// ```
+pub struct CowInterner<'a, T: ToOwned + ?Sized> {
+    value_to_pos: HashMap<Cow<'a, T>, Entry>,
+}
+
+impl<'a, T: ToOwned + ?Sized> CowInterner<'a, T> {
+    /// Returns a new, empty interner.
+    pub fn new() -> Self {
+        Self {
+            value_to_pos: HashMap::new(),
+        }
+    }
+
+    /// The number of interned values.
+    pub fn len(&self) -> usize {
+        self.value_to_pos.len()
+    }
+
+    /// Returns `true` if the interner contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_pos.is_empty()
+    }
+
+    /// The interned values.
+    pub fn iter(&self) -> hash_map::Iter<'_, Cow<'a, T>, Entry> {
+        self.value_to_pos.iter()
+    }
+
+    /// Preseeds the interner with a borrowed value.
+    ///
+    /// The value will be written the first time it is interned, without
+    /// being cloned. Returns `false` if the interner already contained the
+    /// value.
+    pub fn preseed_borrowed(&mut self, value: &'a T) -> bool
+    where
+        T: Hash + Eq,
+    {
+        match self.value_to_pos.entry(Cow::Borrowed(value)) {
+            hash_map::Entry::Occupied(_) => false,
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(Entry::preseeded());
+                true
+            }
+        }
+    }
+}
+
+impl<T: ToOwned + ?Sized> Default for CowInterner<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E> Interning<T, E> for CowInterner<'_, T>
+where
+    T: Hash + Eq + ToOwned + ?Sized,
+    E: InterningError,
+{
+    type State<'b> = &'b T where T: 'b;
+
+    fn start_interning<'b>(
+        &mut self,
+        value: &'b T,
+    ) -> InterningState<Self::State<'b>> {
+        match self.value_to_pos.get_mut(value) {
+            Some(entry) => entry.restart(value),
+            None => {
+                let key = Cow::Owned(value.to_owned());
+                self.value_to_pos.insert(key, Entry::pending());
+                InterningState::Started(value)
+            }
+        }
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        match self.value_to_pos.get_mut(state) {
+            Some(entry) => entry.finish(pos, || None),
+            None => Err(E::new(NotStarted(None))),
+        }
+    }
+}
+
+/// A wrapper that interns the value behind a `Cow`.
+///
+/// This archives a `Cow<'a, T>` the same way as
+/// [`BorrowIntern<T>`](crate::BorrowIntern), whether it's borrowed or owned,
+/// and deserializes it into `Cow::Owned`. The value is interned as a `T`
+/// directly, so a `CowInterner<T>` preseeded with the borrowed values can
+/// intern them without cloning any of them.
+///
+/// # Example
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::CowIntern;
+///
+/// #[derive(Archive)]
+/// struct Example<'a> {
+///     #[rkyv(with = CowIntern)]
+///     name: Cow<'a, str>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CowIntern;
+
+impl<T> ArchiveWith<Cow<'_, T>> for CowIntern
+where
+    T: ArchiveUnsized + ToOwned + ?Sized,
+{
+    type Archived = ArchivedRc<T::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &Cow<'_, T>,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedRc::resolve_from_ref(&**field, resolver, out);
+    }
+}
+
+impl<T, S> SerializeWith<Cow<'_, T>, S> for CowIntern
+where
+    T: SerializeUnsized<S> + ToOwned + ?Sized,
+    S: Interning<T> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &Cow<'_, T>,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        serializer.resolve_interned(&**field)
+    }
+}
+
+impl<'a, T, D>
+    DeserializeWith<ArchivedRc<T::Archived, InternFlavor>, Cow<'a, T>, D>
+    for CowIntern
+where
+    T: ArchiveUnsized + LayoutRaw + ToOwned + ?Sized,
+    T::Archived: DeserializeUnsized<T, D>,
+    T::Owned: From<Box<T>>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<T::Archived, InternFlavor>,
+        deserializer: &mut D,
+    ) -> Result<Cow<'a, T>, <D as Fallible>::Error> {
+        let value = deserialize_boxed(field.get(), deserializer)?;
+        Ok(Cow::Owned(T::Owned::from(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        borrow::Cow,
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        testing::check_interning_contract,
+        tests::{serialize_with, USERS},
+        CowIntern, CowInterner, DerefIntern,
+    };
+
+    #[test]
+    fn cow_intern_strings() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = DerefIntern)]
+            user: String,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut interner = CowInterner::<str>::new();
+        for log in value.iter() {
+            interner.preseed_borrowed(&log.user);
+        }
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), USERS.len());
+        for (user, entry) in interner.iter() {
+            assert!(matches!(user, Cow::Borrowed(_)));
+            assert!(entry.pos().is_some());
+        }
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn cow_intern_fields() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log<'a> {
+            #[rkyv(with = CowIntern)]
+            user: Cow<'a, str>,
+            #[rkyv(with = CowIntern)]
+            team: Cow<'a, str>,
+        }
+
+        let value = (0..100)
+            .map(|i| Log {
+                user: match i % 2 {
+                    0 => Cow::Borrowed(USERS[i % USERS.len()]),
+                    _ => Cow::Owned(USERS[i % USERS.len()].to_string()),
+                },
+                team: Cow::Borrowed(USERS[0]),
+            })
+            .collect::<Vec<_>>();
+
+        let mut interner = CowInterner::<str>::new();
+        for user in USERS.iter() {
+            interner.preseed_borrowed(user);
+        }
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), USERS.len());
+        for (user, _) in interner.iter() {
+            assert!(matches!(user, Cow::Borrowed(_)));
+        }
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log<'_>>>>(&bytes) };
+        assert!(core::ptr::eq(
+            archived[0].user.as_ptr(),
+            archived[USERS.len() + 1].team.as_ptr(),
+        ));
+
+        let deserialized =
+            deserialize::<Vec<Log<'_>>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+        for log in deserialized.iter() {
+            assert!(matches!(log.user, Cow::Owned(_)));
+        }
+    }
+
+    #[test]
+    fn cow_interner_follows_contract() {
+        check_interning_contract::<_, Panic>(&mut CowInterner::new());
+    }
+}
@@ -0,0 +1,403 @@
+use alloc::{borrow::ToOwned, vec::Vec};
+use core::{
+    borrow::Borrow, convert::TryFrom, error::Error, fmt, hash::Hash,
+    marker::PhantomData,
+};
+
+use rkyv::{
+    rancor::{Fallible, Strategy},
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    Archive, Archived, Place,
+};
+
+use crate::{
+    InternHasher, Interner, InterningAdapter, InterningError, PositionInt,
+    SymbolInterner,
+};
+
+/// The default flavor of dictionaries used by [`DictIntern`].
+///
+/// Each flavor names a separate dictionary. Archives which refer to more than
+/// one shared dictionary can use a custom flavor type for each of them, so
+/// that the serializer and deserializer can tell which dictionary a field's
+/// index belongs to.
+#[derive(Debug)]
+pub struct DictFlavor;
+
+/// A serializer which can assign dictionary indices to values of type `T`.
+///
+/// This is used by [`DictIntern`] to replace values with their index in the
+/// shared dictionary of flavor `F`.
+pub trait DictionaryIndexing<T: ?Sized, F = DictFlavor> {
+    /// Returns the index of the value in the dictionary, adding it if it
+    /// isn't in the dictionary yet.
+    fn dictionary_index(&mut self, value: &T) -> usize;
+}
+
+impl<S, I, T, F> DictionaryIndexing<T, F> for InterningAdapter<S, I>
+where
+    I: DictionaryIndexing<T, F>,
+    T: ?Sized,
+{
+    fn dictionary_index(&mut self, value: &T) -> usize {
+        DictionaryIndexing::<T, F>::dictionary_index(
+            self.interning_mut(),
+            value,
+        )
+    }
+}
+
+impl<S, T, E, F> DictionaryIndexing<T, F> for Strategy<S, E>
+where
+    S: DictionaryIndexing<T, F> + ?Sized,
+    T: ?Sized,
+{
+    fn dictionary_index(&mut self, value: &T) -> usize {
+        <S as DictionaryIndexing<T, F>>::dictionary_index(self, value)
+    }
+}
+
+impl<T, S, N, Q, F> DictionaryIndexing<Q, F> for Interner<T, S, N>
+where
+    T: Hash + Borrow<Q>,
+    S: InternHasher<Q>,
+    N: PositionInt,
+    Q: ToOwned<Owned = T> + ?Sized,
+{
+    fn dictionary_index(&mut self, value: &Q) -> usize {
+        Interner::dictionary_index(self, value)
+    }
+}
+
+impl<T, Q, F> DictionaryIndexing<Q, F> for SymbolInterner<T>
+where
+    T: Borrow<Q>,
+    Q: Hash + Eq + ToOwned<Owned = T> + ?Sized,
+{
+    fn dictionary_index(&mut self, value: &Q) -> usize {
+        self.intern(value).index()
+    }
+}
+
+/// A deserializer which can look up values of type `T` in a dictionary.
+///
+/// This is used by [`DictIntern`] to replace indices with the values they
+/// refer to in the shared dictionary of flavor `F`.
+pub trait DictionaryLookup<T, F = DictFlavor> {
+    /// Returns the value at `index` in the dictionary, if there is one.
+    fn dictionary_value(&self, index: usize) -> Option<&T>;
+}
+
+impl<D, T, E, F> DictionaryLookup<T, F> for Strategy<D, E>
+where
+    D: DictionaryLookup<T, F> + ?Sized,
+{
+    fn dictionary_value(&self, index: usize) -> Option<&T> {
+        <D as DictionaryLookup<T, F>>::dictionary_value(self, index)
+    }
+}
+
+impl<T, F> DictionaryLookup<T, F> for Vec<T> {
+    fn dictionary_value(&self, index: usize) -> Option<&T> {
+        self.get(index)
+    }
+}
+
+impl<T, F> DictionaryLookup<T, F> for [T] {
+    fn dictionary_value(&self, index: usize) -> Option<&T> {
+        self.get(index)
+    }
+}
+
+#[derive(Debug)]
+struct DictionaryIndexTooLarge(usize);
+
+impl fmt::Display for DictionaryIndexTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dictionary index {} is too large to be archived as a `u32`",
+            self.0,
+        )
+    }
+}
+
+impl Error for DictionaryIndexTooLarge {}
+
+#[derive(Debug)]
+struct MissingDictionaryValue(usize);
+
+impl fmt::Display for MissingDictionaryValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the dictionary has no value at index {}", self.0)
+    }
+}
+
+impl Error for MissingDictionaryValue {}
+
+/// A wrapper that replaces values with their index in a shared dictionary.
+///
+/// Unlike [`Intern`](crate::Intern), values aren't written to the archive at
+/// all. Each value is archived as a `u32` index into a dictionary which is
+/// stored separately, so many small archives can share one copy of their
+/// common values. The serializer must implement [`DictionaryIndexing`], for
+/// example with an [`Interner`] or [`SymbolInterner`] which is used for every
+/// archive that shares the dictionary. Once they have all been serialized,
+/// [`Interner::into_dictionary`] returns the dictionary, which can be archived
+/// on its own. See the [`Interner`
+/// documentation](Interner#dictionaries) for details.
+///
+/// The flavor `F` selects which dictionary the field's values belong to, and
+/// defaults to [`DictFlavor`]. Fields with different flavors are indexed and
+/// looked up separately, so one archive can refer to several dictionaries.
+///
+/// An archived index can be resolved by indexing into the archived dictionary.
+/// To deserialize, the deserializer must implement [`DictionaryLookup`], for
+/// example with the deserialized dictionary.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{
+///     access_unchecked, rancor::{Error, Strategy}, Archive, Archived,
+///     Deserialize, Serialize,
+/// };
+/// use rkyv_intern::{DictIntern, Interner};
+///
+/// #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+/// struct Log {
+///     #[rkyv(with = DictIntern)]
+///     user: String,
+/// }
+///
+/// let value = Log { user: "alice".to_string() };
+/// let interner = Interner::<String>::new();
+/// let (bytes, interner) =
+///     rkyv_intern::to_bytes_with::<_, Error>(&value, interner).unwrap();
+/// let mut dictionary = interner.into_dictionary();
+/// let dictionary_bytes = rkyv::to_bytes::<Error>(&dictionary).unwrap();
+///
+/// let archived = unsafe { access_unchecked::<Archived<Log>>(&bytes) };
+/// let archived_dictionary = unsafe {
+///     access_unchecked::<Archived<Vec<String>>>(&dictionary_bytes)
+/// };
+/// let index = archived.user.to_native() as usize;
+/// assert_eq!(archived_dictionary[index], "alice");
+///
+/// let deserialized: Log = archived
+///     .deserialize(Strategy::<_, Error>::wrap(&mut dictionary))
+///     .unwrap();
+/// assert_eq!(deserialized, value);
+/// ```
+#[derive(Debug)]
+pub struct DictIntern<F = DictFlavor> {
+    _phantom: PhantomData<F>,
+}
+
+impl<T, F> ArchiveWith<T> for DictIntern<F> {
+    type Archived = Archived<u32>;
+    type Resolver = u32;
+
+    fn resolve_with(
+        _: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        resolver.resolve((), out);
+    }
+}
+
+impl<T, S, F> SerializeWith<T, S> for DictIntern<F>
+where
+    S: DictionaryIndexing<T, F> + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, S::Error> {
+        let index =
+            DictionaryIndexing::<T, F>::dictionary_index(serializer, field);
+        u32::try_from(index)
+            .map_err(|_| S::Error::new(DictionaryIndexTooLarge(index)))
+    }
+}
+
+impl<T, D, F> DeserializeWith<Archived<u32>, T, D> for DictIntern<F>
+where
+    T: Clone,
+    D: DictionaryLookup<T, F> + Fallible + ?Sized,
+    D::Error: InterningError,
+{
+    fn deserialize_with(
+        field: &Archived<u32>,
+        deserializer: &mut D,
+    ) -> Result<T, D::Error> {
+        let index = field.to_native() as usize;
+        DictionaryLookup::<T, F>::dictionary_value(deserializer, index)
+            .cloned()
+            .ok_or_else(|| D::Error::new(MissingDictionaryValue(index)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked,
+        rancor::{Error, Panic, Strategy},
+        Archive, Archived, Deserialize, Serialize,
+    };
+
+    use crate::{
+        to_bytes_with, tests::USERS, DictIntern, DictionaryIndexing,
+        DictionaryLookup, Interner, SymbolInterner,
+    };
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Message {
+        #[rkyv(with = DictIntern)]
+        user: String,
+        text: String,
+    }
+
+    fn messages(offset: usize) -> Vec<Message> {
+        (0..10)
+            .map(|i| Message {
+                user: USERS[(i + offset) % USERS.len()].to_string(),
+                text: i.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shared_dictionary() {
+        let (first, symbols) =
+            to_bytes_with::<_, Panic>(&messages(0), SymbolInterner::new())
+                .unwrap();
+        let (second, symbols) =
+            to_bytes_with::<_, Panic>(&messages(1), symbols).unwrap();
+        let mut dictionary = symbols.into_dictionary();
+        assert_eq!(dictionary, USERS);
+        let dictionary_bytes = rkyv::to_bytes::<Panic>(&dictionary).unwrap();
+
+        let archived_dictionary = unsafe {
+            access_unchecked::<Archived<Vec<String>>>(&dictionary_bytes)
+        };
+        for (bytes, offset) in [(&first, 0), (&second, 1)] {
+            let value = messages(offset);
+            let archived =
+                unsafe { access_unchecked::<Archived<Vec<Message>>>(bytes) };
+            for (a, b) in archived.iter().zip(value.iter()) {
+                let index = a.user.to_native() as usize;
+                assert_eq!(archived_dictionary[index], b.user);
+                assert_eq!(a.text, b.text);
+            }
+
+            let deserialized: Vec<Message> = archived
+                .deserialize(Strategy::<_, Panic>::wrap(&mut dictionary))
+                .unwrap();
+            assert_eq!(deserialized, value);
+        }
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Message>>>(&second) };
+        let mut truncated = dictionary[..1].to_vec();
+        let result = archived.deserialize(Strategy::<_, Error>::wrap(
+            &mut truncated,
+        ));
+        let error: Error = result.map(|_: Vec<Message>| ()).unwrap_err();
+        assert!(error.to_string().contains("no value at index 1"));
+    }
+
+    struct TeamFlavor;
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct TeamMessage {
+        #[rkyv(with = DictIntern)]
+        user: String,
+        #[rkyv(with = DictIntern<TeamFlavor>)]
+        team: String,
+    }
+
+    fn team_messages(offset: usize) -> Vec<TeamMessage> {
+        (0..10)
+            .map(|i| TeamMessage {
+                user: USERS[(i + offset) % USERS.len()].to_string(),
+                team: ["red", "blue"][(i + offset) % 2].to_string(),
+            })
+            .collect()
+    }
+
+    #[derive(Default)]
+    struct Dictionaries<T> {
+        users: T,
+        teams: T,
+    }
+
+    impl DictionaryIndexing<String> for Dictionaries<Interner<String>> {
+        fn dictionary_index(&mut self, value: &String) -> usize {
+            self.users.dictionary_index(value)
+        }
+    }
+
+    impl DictionaryIndexing<String, TeamFlavor>
+        for Dictionaries<Interner<String>>
+    {
+        fn dictionary_index(&mut self, value: &String) -> usize {
+            self.teams.dictionary_index(value)
+        }
+    }
+
+    impl DictionaryLookup<String> for Dictionaries<Vec<String>> {
+        fn dictionary_value(&self, index: usize) -> Option<&String> {
+            self.users.get(index)
+        }
+    }
+
+    impl DictionaryLookup<String, TeamFlavor> for Dictionaries<Vec<String>> {
+        fn dictionary_value(&self, index: usize) -> Option<&String> {
+            self.teams.get(index)
+        }
+    }
+
+    #[test]
+    fn interner_dictionary_flavors() {
+        let (first, dictionaries) = to_bytes_with::<_, Panic>(
+            &team_messages(0),
+            Dictionaries::<Interner<String>>::default(),
+        )
+        .unwrap();
+        let (second, dictionaries) =
+            to_bytes_with::<_, Panic>(&team_messages(1), dictionaries)
+                .unwrap();
+        let mut dictionaries = Dictionaries {
+            users: dictionaries.users.into_dictionary(),
+            teams: dictionaries.teams.into_dictionary(),
+        };
+        assert_eq!(dictionaries.users, USERS);
+        assert_eq!(dictionaries.teams, ["red", "blue"]);
+
+        for (bytes, offset) in [(&first, 0), (&second, 1)] {
+            let value = team_messages(offset);
+            let archived = unsafe {
+                access_unchecked::<Archived<Vec<TeamMessage>>>(bytes)
+            };
+            for (a, b) in archived.iter().zip(value.iter()) {
+                let user = a.user.to_native() as usize;
+                let team = a.team.to_native() as usize;
+                assert_eq!(dictionaries.users[user], b.user);
+                assert_eq!(dictionaries.teams[team], b.team);
+            }
+
+            let deserialized: Vec<TeamMessage> = archived
+                .deserialize(Strategy::<_, Panic>::wrap(&mut dictionaries))
+                .unwrap();
+            assert_eq!(deserialized, value);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Errors returned while interning.
+//!
+//! Interning errors are returned through [`InterningError`], so serializers
+//! usually receive them as boxed sources. Error types which keep their
+//! sources, like `rancor::BoxedError`, can be downcast to these types to
+//! handle specific failures.
+//!
+//! [`InterningError`]: crate::InterningError
+
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String};
+use core::{any::type_name, error::Error, fmt};
+
+/// A value was interned again while it was still being serialized.
+///
+/// This is returned by
+/// [`serialize_interned`](crate::InterningExt::serialize_interned) when
+/// starting a value returns [`InterningState::Pending`]. It usually means that
+/// the value contains an interned copy of itself. With a
+/// [`SyncInterner`](crate::SyncInterner), it can also mean that another thread
+/// is still serializing the value.
+///
+/// [`InterningState::Pending`]: crate::InterningState::Pending
+#[derive(Debug)]
+pub struct CyclicInternedValueError {
+    type_name: &'static str,
+    #[cfg(feature = "alloc")]
+    value: Option<String>,
+}
+
+impl CyclicInternedValueError {
+    pub(crate) fn new<T: ?Sized>(describe: Option<&dyn fmt::Debug>) -> Self {
+        #[cfg(not(feature = "alloc"))]
+        let _ = describe;
+        Self {
+            type_name: type_name::<T>(),
+            #[cfg(feature = "alloc")]
+            value: describe.map(|value| format!("{:?}", value)),
+        }
+    }
+}
+
+impl fmt::Display for CyclicInternedValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "encountered cyclic shared pointers while interning a value of \
+             type `{}`",
+            self.type_name,
+        )?;
+        #[cfg(feature = "alloc")]
+        if let Some(value) = &self.value {
+            write!(f, ": {}", value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for CyclicInternedValueError {}
+
+/// A value was finished without being started.
+///
+/// This is returned by `finish_interning` when the value isn't pending in the
+/// interner. This happens if the value was never started, if it was preseeded
+/// but not started, or if it was removed from the interner while it was being
+/// serialized.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct NotStarted(pub(crate) Option<String>);
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for NotStarted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => {
+                write!(f, "value {} was not started interning", value)
+            }
+            None => write!(f, "value was not started interning"),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Error for NotStarted {}
+
+/// A value was finished at a different position than it was already finished
+/// at.
+///
+/// This is returned by `finish_interning` when the value was already finished.
+/// Finishing it again at the same position succeeds.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct AlreadyFinished(pub(crate) Option<String>);
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for AlreadyFinished {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => write!(
+                f,
+                "value {} was already finished interning at another position",
+                value,
+            ),
+            None => write!(
+                f,
+                "value was already finished interning at another position",
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Error for AlreadyFinished {}
+
+/// A new value was interned by a sealed interner.
+///
+/// This is returned by `finish_interning` when an interner sealed with
+/// [`SealPolicy::Fail`](crate::SealPolicy::Fail) is asked to intern a value it
+/// doesn't already contain.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct SealedInterner(pub(crate) Option<String>);
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for SealedInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => write!(
+                f,
+                "value {} is not in the sealed interner",
+                value,
+            ),
+            None => write!(f, "value is not in the sealed interner"),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Error for SealedInterner {}
+
+/// A value which isn't valid UTF-8 was serialized with
+/// [`AsStringIntern`](crate::AsStringIntern).
+///
+/// `AsStringIntern` interns `OsStr`s and `Path`s as `str`s, so values which
+/// can't be converted to a `str` fail to serialize with this error. The value
+/// is included in the message after a lossy conversion.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct InvalidUtf8(pub(crate) String);
+
+#[cfg(feature = "std")]
+impl fmt::Display for InvalidUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "value {:?} is not valid UTF-8", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for InvalidUtf8 {}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::string::{String, ToString};
+    use core::hash::BuildHasher;
+
+    use rkyv::{rancor::BoxedError, util::AlignedVec};
+
+    use crate::{
+        error::{AlreadyFinished, CyclicInternedValueError, NotStarted},
+        tests::USERS,
+        Interner, Interning, InterningAdapter, InterningState,
+    };
+
+    #[test]
+    fn downcast_errors() {
+        fn finish(
+            interner: &mut Interner<String>,
+            value: &str,
+            pos: usize,
+        ) -> BoxedError {
+            let hash = interner.hasher().hash_one(value);
+            Interning::<str, BoxedError>::finish_interning(
+                interner,
+                Some((value, hash)),
+                pos,
+            )
+            .unwrap_err()
+        }
+
+        let mut interner = Interner::<String>::new();
+        interner.preseed(USERS[0].to_string());
+        let error = finish(&mut interner, USERS[0], 0);
+        let source = BoxedError::inner(&error);
+        assert!(source.downcast_ref::<NotStarted>().is_some());
+
+        let state = Interning::<str, BoxedError>::start_interning(
+            &mut interner,
+            USERS[0],
+        );
+        let state = match state {
+            InterningState::Started(state) => state,
+            _ => panic!("preseeded value was already started"),
+        };
+        Interning::<str, BoxedError>::finish_interning(&mut interner, state, 0)
+            .unwrap();
+        let error = finish(&mut interner, USERS[0], 8);
+        let source = BoxedError::inner(&error);
+        assert!(source.downcast_ref::<AlreadyFinished>().is_some());
+        assert!(source.downcast_ref::<NotStarted>().is_none());
+
+        // Leave a value pending so that serializing it looks like a cycle.
+        let _ = Interning::<str, BoxedError>::start_interning(
+            &mut interner,
+            USERS[1],
+        );
+        let mut serializer =
+            InterningAdapter::new(AlignedVec::<8>::new(), interner);
+        let error = serializer
+            .serialize_interned::<str, BoxedError>(USERS[1])
+            .unwrap_err();
+        let source = BoxedError::inner(&error);
+        assert!(source.downcast_ref::<CyclicInternedValueError>().is_some());
+    }
+}
@@ -0,0 +1,317 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{borrow::Borrow, fmt, ops::Deref, str};
+
+use rkyv::{
+    munge::munge,
+    rancor::Fallible,
+    ser::Writer,
+    with::{ArchiveWith, SerializeWith},
+    Archive, Place, Portable,
+};
+#[cfg(feature = "alloc")]
+use rkyv::{rancor::Source, with::DeserializeWith};
+
+#[cfg(feature = "alloc")]
+use crate::deserialize_boxed;
+use crate::{
+    ArchivedMaybeInterned, Interning, InterningError, InterningExt as _,
+    MaybeInternedResolver,
+};
+
+/// A string of at most `N` bytes stored in a fixed-size buffer.
+///
+/// This is the archived type of strings which [`InlineOrIntern<N>`] stores
+/// inline.
+#[derive(Portable)]
+#[rkyv(crate = rkyv)]
+#[cfg_attr(
+    feature = "bytecheck",
+    derive(rkyv::bytecheck::CheckBytes),
+    bytecheck(crate = rkyv::bytecheck, verify)
+)]
+#[repr(C)]
+pub struct ArchivedInlineStr<const N: usize> {
+    len: u8,
+    bytes: [u8; N],
+}
+
+impl<const N: usize> ArchivedInlineStr<N> {
+    /// Gets the archived string.
+    pub fn as_str(&self) -> &str {
+        let bytes = &self.bytes[..self.len as usize];
+        // SAFETY: The buffer always starts with `len` bytes of valid UTF-8.
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+}
+
+impl<const N: usize> Borrow<str> for ArchivedInlineStr<N> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> Deref for ArchivedInlineStr<N> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Debug for ArchivedInlineStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<const N: usize> PartialEq for ArchivedInlineStr<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const N: usize> Eq for ArchivedInlineStr<N> {}
+
+#[cfg(feature = "bytecheck")]
+const _: () = {
+    use core::error::Error;
+
+    use rkyv::{bytecheck::Verify, rancor::Source};
+
+    #[derive(Debug)]
+    struct InlineStrTooLong {
+        len: usize,
+        capacity: usize,
+    }
+
+    impl fmt::Display for InlineStrTooLong {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "inline string has length {} but only fits {} bytes",
+                self.len, self.capacity,
+            )
+        }
+    }
+
+    impl Error for InlineStrTooLong {}
+
+    unsafe impl<C, const N: usize> Verify<C> for ArchivedInlineStr<N>
+    where
+        C: Fallible + ?Sized,
+        C::Error: Source,
+    {
+        fn verify(&self, _: &mut C) -> Result<(), C::Error> {
+            let len = self.len as usize;
+            let bytes = self.bytes.get(..len).ok_or_else(|| {
+                Source::new(InlineStrTooLong { len, capacity: N })
+            })?;
+            str::from_utf8(bytes).map_err(Source::new)?;
+            Ok(())
+        }
+    }
+};
+
+/// A borrowed string which archives as an [`ArchivedInlineStr<N>`].
+struct InlineStr<'a, const N: usize>(&'a str);
+
+impl<const N: usize> Archive for InlineStr<'_, N> {
+    type Archived = ArchivedInlineStr<N>;
+    type Resolver = ();
+
+    fn resolve(&self, _: Self::Resolver, out: Place<Self::Archived>) {
+        let mut buffer = [0; N];
+        buffer[..self.0.len()].copy_from_slice(self.0.as_bytes());
+
+        munge!(let ArchivedInlineStr { len, bytes } = out);
+        len.write(self.0.len() as u8);
+        bytes.write(buffer);
+    }
+}
+
+/// A wrapper that stores strings of at most `N` bytes inline, and interns
+/// longer strings.
+///
+/// Interning a short string can cost more than it saves, since every interned
+/// value is accessed through a relative pointer. Unlike
+/// [`MinLenIntern`](crate::MinLenIntern), which archives short strings as an
+/// `ArchivedString` that only fits a few bytes without writing them out of
+/// line, this reserves an `N`-byte buffer next to the pointer. Strings which
+/// fit in the buffer are never written anywhere else or added to the interner,
+/// and the archived value derefs to `str` either way.
+///
+/// `N` must be at most 255.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::InlineOrIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     // Codes like "en" and "fr-CA" are stored inline.
+///     #[rkyv(with = InlineOrIntern<7>)]
+///     locale: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InlineOrIntern<const N: usize>;
+
+impl<const N: usize> InlineOrIntern<N> {
+    const FITS_LEN: () = assert!(
+        N <= u8::MAX as usize,
+        "`InlineOrIntern<N>` requires N <= 255",
+    );
+
+    /// Returns `true` if `value` is stored inline.
+    pub const fn inlines(value: &str) -> bool {
+        value.len() <= N
+    }
+}
+
+impl<T, const N: usize> ArchiveWith<T> for InlineOrIntern<N>
+where
+    T: Deref<Target = str>,
+{
+    type Archived = ArchivedMaybeInterned<str, ArchivedInlineStr<N>>;
+    type Resolver = MaybeInternedResolver<()>;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedMaybeInterned::resolve_from_parts(
+            &InlineStr::<N>(field),
+            field.deref(),
+            resolver,
+            out,
+        );
+    }
+}
+
+impl<T, S, const N: usize> SerializeWith<T, S> for InlineOrIntern<N>
+where
+    T: Deref<Target = str>,
+    S: Interning<str> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_LEN;
+
+        if Self::inlines(field) {
+            Ok(MaybeInternedResolver::Inline(()))
+        } else {
+            let resolver = serializer.resolve_interned(field.deref())?;
+            Ok(MaybeInternedResolver::Interned(resolver))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, D, const N: usize>
+    DeserializeWith<ArchivedMaybeInterned<str, ArchivedInlineStr<N>>, T, D>
+    for InlineOrIntern<N>
+where
+    T: From<Box<str>>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedMaybeInterned<str, ArchivedInlineStr<N>>,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        match field {
+            ArchivedMaybeInterned::Inline(value) => {
+                Ok(T::from(Box::from(value.as_str())))
+            }
+            ArchivedMaybeInterned::Interned(value) => {
+                Ok(T::from(deserialize_boxed(value.get(), deserializer)?))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+    use core::mem::size_of;
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::serialize_with_interner, ArchivedInlineStr, InlineOrIntern,
+    };
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Tag {
+        #[rkyv(with = InlineOrIntern<12>)]
+        name: String,
+    }
+
+    const NAMES: [&str; 5] =
+        ["", "abc", "twelve bytes", "thirteen byte", "a much longer name"];
+
+    fn tags() -> Vec<Tag> {
+        (0..100)
+            .map(|i| Tag {
+                name: NAMES[i % NAMES.len()].into(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inline_or_intern_strings() {
+        assert_eq!(size_of::<ArchivedInlineStr<12>>(), 13);
+
+        let value = tags();
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        let mut interned = interner.iter().map(|(k, _)| k).collect::<Vec<_>>();
+        interned.sort();
+        assert_eq!(interned, [NAMES[4], NAMES[3]]);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Tag>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.name.is_inline(), b.name.len() <= 12);
+            assert_eq!(&*a.name, b.name);
+        }
+        assert!(core::ptr::eq(
+            archived[3].name.as_ptr(),
+            archived[8].name.as_ptr(),
+        ));
+
+        let deserialized = deserialize::<Vec<Tag>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "bytecheck")]
+    #[test]
+    fn inline_or_intern_access_checked() {
+        use rkyv::{access, rancor::Error};
+
+        let value = tags();
+        let (mut bytes, _) =
+            serialize_with_interner::<_, Error>(&value).unwrap();
+        let archived = access::<Archived<Vec<Tag>>, Error>(&bytes).unwrap();
+        assert_eq!(&*archived[2].name, NAMES[2]);
+
+        // Overwrite the length of the first inline string.
+        let pos = archived[1].name.as_ptr() as usize - bytes.as_ptr() as usize;
+        bytes[pos - 1] = 13;
+        assert!(access::<Archived<Vec<Tag>>, Error>(&bytes).is_err());
+        bytes[pos - 1] = 3;
+        bytes[pos] = 0xff;
+        assert!(access::<Archived<Vec<Tag>>, Error>(&bytes).is_err());
+    }
+}
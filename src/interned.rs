@@ -0,0 +1,208 @@
+use core::{
+    borrow::{Borrow, BorrowMut},
+    ops::{Deref, DerefMut},
+};
+
+use rkyv::{
+    rancor::Fallible,
+    rc::{ArchivedRc, RcResolver},
+    ser::Writer,
+    Archive, Deserialize, Place, Serialize,
+};
+
+use crate::{InternFlavor, Interning, InterningError, InterningExt as _};
+
+/// A value which is always interned when it's serialized.
+///
+/// This archives and serializes its value the same way as a field wrapped with
+/// [`Intern`](crate::Intern), but implements `Archive`, `Serialize`, and
+/// `Deserialize` itself. It can be used where a with-wrapper can't be named,
+/// like inside generic types and nested containers, and archives to the same
+/// `ArchivedRc` so the two are interchangeable.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::Interned;
+///
+/// # #[cfg(feature = "alloc")]
+/// #[derive(Archive)]
+/// struct Example {
+///     tags: Vec<Interned<String>>,
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Interned<T>(pub T);
+
+impl<T> Interned<T> {
+    /// Returns a new `Interned` containing `value`.
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Returns the contained value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Interned<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Deref for Interned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Interned<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> Borrow<T> for Interned<T> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> BorrowMut<T> for Interned<T> {
+    fn borrow_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Archive> Archive for Interned<T> {
+    type Archived = ArchivedRc<T::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedRc::resolve_from_ref(&self.0, resolver, out);
+    }
+}
+
+impl<T, S> Serialize<S> for Interned<T>
+where
+    T: Serialize<S>,
+    S: Interning<T> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize(
+        &self,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        serializer.resolve_interned(&self.0)
+    }
+}
+
+impl<T, D> Deserialize<Interned<T>, D> for ArchivedRc<T::Archived, InternFlavor>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(
+        &self,
+        deserializer: &mut D,
+    ) -> Result<Interned<T>, <D as Fallible>::Error> {
+        Ok(Interned(self.get().deserialize(deserializer)?))
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_interned, USERS},
+        Intern, Interned,
+    };
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Team {
+        members: Vec<Interned<String>>,
+        lead: Option<Interned<String>>,
+    }
+
+    #[test]
+    fn interned_field_type() {
+        let value = (0..20)
+            .map(|i| Team {
+                members: (0..i % 5)
+                    .map(|j| USERS[j % USERS.len()].to_string().into())
+                    .collect(),
+                lead: (i % 2 == 0)
+                    .then(|| Interned(USERS[i % USERS.len()].to_string())),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = serialize_interned::<_, Panic>(&value).unwrap();
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Team>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.members.len(), b.members.len());
+            for (x, y) in a.members.iter().zip(b.members.iter()) {
+                assert_eq!(**x, **y);
+            }
+        }
+        assert!(core::ptr::eq(
+            &*archived[2].members[1],
+            &*archived[9].members[1],
+        ));
+        assert!(core::ptr::eq(
+            &**archived[0].lead.as_ref().unwrap(),
+            &*archived[4].members[0],
+        ));
+
+        let deserialized = deserialize::<Vec<Team>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn interned_matches_intern() {
+        #[derive(Archive, Serialize)]
+        struct Wrapped {
+            #[rkyv(with = Intern)]
+            name: String,
+        }
+
+        #[derive(Archive, Serialize)]
+        struct Owned {
+            name: Interned<String>,
+        }
+
+        let wrapped = USERS
+            .iter()
+            .map(|user| Wrapped {
+                name: user.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let owned = USERS
+            .iter()
+            .map(|user| Owned {
+                name: Interned::new(user.to_string()),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            &*serialize_interned::<_, Panic>(&wrapped).unwrap(),
+            &*serialize_interned::<_, Panic>(&owned).unwrap(),
+        );
+    }
+}
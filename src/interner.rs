@@ -1,117 +1,3197 @@
-use alloc::borrow::ToOwned;
-use core::{borrow::Borrow, error::Error, fmt, hash::{BuildHasher, Hash}, num::NonZeroUsize};
+#[cfg(feature = "statistics")]
+use alloc::collections::BTreeMap;
+use alloc::{
+    borrow::ToOwned, boxed::Box, format, string::String, sync::Arc, vec::Vec,
+};
+#[cfg(feature = "statistics")]
+use core::cmp::Reverse;
+use core::{
+    borrow::Borrow,
+    convert::TryFrom,
+    error::Error,
+    fmt,
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    marker::PhantomData,
+    mem::{size_of, size_of_val},
+    num::{NonZeroU32, NonZeroUsize},
+    ptr::{self, NonNull},
+};
 
-use hashbrown::HashMap;
-use rkyv::rancor::{fail, Source};
+#[cfg(feature = "debug-checks")]
+use hashbrown::HashSet;
+use hashbrown::{
+    hash_map::{RawEntryMut, RawVacantEntryMut},
+    DefaultHashBuilder, HashMap,
+};
+#[cfg(feature = "statistics")]
+use rkyv::{Archive, Deserialize, Serialize};
+use crate::{
+    error::{AlreadyFinished, NotStarted, SealedInterner},
+    Interning, InterningError, InterningPolicy, InterningState,
+};
 
-use crate::{Interning, InterningState};
+/// An integer type which can store the positions of interned values.
+///
+/// Positions are stored plus one in a non-zero integer, so an unwritten
+/// position doesn't take any extra space. A position can be stored if one more
+/// than it fits in the integer, so `NonZeroUsize` can store any position but
+/// `usize::MAX`, and `NonZeroU32` can store positions below `u32::MAX`.
+///
+/// Interners never store a position which doesn't fit. Finishing a value at
+/// such a position fails, and [`Interner::import_table`] returns the entries
+/// it couldn't store instead of adding them.
+pub trait PositionInt: Copy {
+    /// Encodes a position, or returns `None` if it can't be stored.
+    fn encode(pos: usize) -> Option<Self>;
+
+    /// Decodes a stored position.
+    fn decode(self) -> usize;
+}
+
+impl PositionInt for NonZeroUsize {
+    fn encode(pos: usize) -> Option<Self> {
+        NonZeroUsize::new(pos.checked_add(1)?)
+    }
+
+    fn decode(self) -> usize {
+        // Subtracting one from a stored position can't underflow.
+        self.get() - 1
+    }
+}
+
+impl PositionInt for NonZeroU32 {
+    fn encode(pos: usize) -> Option<Self> {
+        NonZeroU32::new(u32::try_from(pos.checked_add(1)?).ok()?)
+    }
+
+    fn decode(self) -> usize {
+        self.get() as usize - 1
+    }
+}
 
 /// An entry in the interner.
-pub struct Entry {
-    pos: Option<NonZeroUsize>,
+///
+/// Positions are stored as `N`, which is `NonZeroUsize` by default. See
+/// [`PositionInt`] for details.
+pub struct Entry<N = NonZeroUsize> {
+    /// The position of the value, if it has been written.
+    pos: Option<N>,
+    started: bool,
     /// The number of references to the value.
+    ///
+    /// This is zero for preseeded values which have not been interned yet, and
+    /// saturates at `usize::MAX`.
     #[cfg(feature = "statistics")]
-    pub ref_cnt: NonZeroUsize,
+    pub ref_cnt: usize,
+}
+
+impl<N: PositionInt> Entry<N> {
+    /// Returns an entry for a value which has not been interned yet.
+    ///
+    /// The value will be written the first time it is interned.
+    pub fn preseeded() -> Self {
+        Self {
+            pos: None,
+            started: false,
+            #[cfg(feature = "statistics")]
+            ref_cnt: 0,
+        }
+    }
+
+    /// Returns an entry for a value which was started interning but not
+    /// finished.
+    ///
+    /// Interning the value again returns [`InterningState::Pending`] until it
+    /// is finished with `finish_interning`. Its reference count starts at one,
+    /// like a value which was just started.
+    pub fn pending() -> Self {
+        Self {
+            pos: None,
+            started: true,
+            #[cfg(feature = "statistics")]
+            ref_cnt: 1,
+        }
+    }
+
+    /// Returns an entry for a value which was already written at `pos`, or
+    /// `None` if the position can't be stored.
+    pub fn finished(pos: usize) -> Option<Self> {
+        Some(Self {
+            pos: Some(N::encode(pos)?),
+            started: true,
+            #[cfg(feature = "statistics")]
+            ref_cnt: 0,
+        })
+    }
+
+    /// Starts interning the entry's value again.
+    pub(crate) fn restart<S>(&mut self, state: S) -> InterningState<S> {
+        #[cfg(feature = "statistics")]
+        {
+            self.ref_cnt = self.ref_cnt.saturating_add(1);
+        }
+        match self.pos {
+            None if self.started => InterningState::Pending,
+            None => {
+                self.started = true;
+                InterningState::Started(state)
+            }
+            Some(pos) => InterningState::Finished(pos.decode()),
+        }
+    }
+
+    /// Finishes interning the entry's value at the given position.
+    ///
+    /// `describe` is called to describe the value if it fails to finish.
+    pub(crate) fn finish<E: InterningError>(
+        &mut self,
+        pos: usize,
+        describe: impl FnOnce() -> Option<String>,
+    ) -> Result<(), E> {
+        match self {
+            Entry { started: false, .. } => Err(E::new(NotStarted(describe()))),
+            Entry { pos: Some(existing), .. } => {
+                if existing.decode() == pos {
+                    Ok(())
+                } else {
+                    Err(E::new(AlreadyFinished(describe())))
+                }
+            }
+            Entry { pos: x, .. } => match N::encode(pos) {
+                Some(pos) => {
+                    *x = Some(pos);
+                    Ok(())
+                }
+                None => Err(E::new(PositionTooLarge(describe(), pos))),
+            },
+        }
+    }
+
+    /// The position of the value, if it has been written.
+    pub fn pos(&self) -> Option<usize> {
+        self.pos.map(N::decode)
+    }
+
+    /// Returns `true` if the value was started interning but not finished.
+    pub fn is_pending(&self) -> bool {
+        self.started && self.pos.is_none()
+    }
+}
+
+/// How an [`Interner`] hashes and compares values of type `Q`.
+///
+/// This is implemented for every `BuildHasher`, which hashes values with their
+/// `Hash` implementations and compares them with `Eq`, and for [`WithEq`],
+/// which hashes and compares them with custom functions.
+pub trait InternHasher<Q: ?Sized>: Sized {
+    /// A handle which can compare values while the table is borrowed.
+    #[doc(hidden)]
+    type Handle;
+
+    /// Hashes a value.
+    fn hash_value(&self, value: &Q) -> u64;
+
+    /// Hashes a value stored in the table.
+    #[doc(hidden)]
+    fn hash_key<K: Hash + Borrow<Q>>(&self, key: &K) -> u64;
+
+    #[doc(hidden)]
+    fn handle(&self) -> Self::Handle;
+
+    /// Returns `true` if two values are equal.
+    #[doc(hidden)]
+    fn values_eq(handle: &Self::Handle, a: &Q, b: &Q) -> bool;
+
+    /// Inserts a new value into the table.
+    #[doc(hidden)]
+    fn insert<'a, K: Hash + Borrow<Q>, V>(
+        handle: &Self::Handle,
+        entry: RawVacantEntryMut<'a, K, V, Self>,
+        hash: u64,
+        key: K,
+        value: V,
+    ) -> (&'a mut K, &'a mut V);
+}
+
+impl<Q: Hash + Eq + ?Sized, S: BuildHasher> InternHasher<Q> for S {
+    type Handle = ();
+
+    fn hash_value(&self, value: &Q) -> u64 {
+        self.hash_one(value)
+    }
+
+    fn hash_key<K: Hash + Borrow<Q>>(&self, key: &K) -> u64 {
+        self.hash_one(key)
+    }
+
+    fn handle(&self) {}
+
+    fn values_eq(_: &(), a: &Q, b: &Q) -> bool {
+        a == b
+    }
+
+    fn insert<'a, K: Hash + Borrow<Q>, V>(
+        _: &(),
+        entry: RawVacantEntryMut<'a, K, V, Self>,
+        hash: u64,
+        key: K,
+        value: V,
+    ) -> (&'a mut K, &'a mut V) {
+        entry.insert_hashed_nocheck(hash, key, value)
+    }
+}
+
+/// Custom functions which an [`Interner`] uses to hash and compare values.
+///
+/// This is the hasher of an interner created with
+/// [`Interner::with_eq`](Interner::with_eq). The functions take values as
+/// `Q`, and values of any type which borrows as `Q` are hashed and compared
+/// through it.
+pub struct WithEq<Q: ?Sized, H, E> {
+    fns: Arc<(H, E)>,
+    _phantom: PhantomData<fn(&Q)>,
+}
+
+impl<Q: ?Sized, H, E> fmt::Debug for WithEq<Q, H, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithEq").finish_non_exhaustive()
+    }
+}
+
+impl<K, Q, H, E> InternHasher<K> for WithEq<Q, H, E>
+where
+    K: Borrow<Q> + ?Sized,
+    Q: ?Sized,
+    H: Fn(&Q) -> u64,
+    E: Fn(&Q, &Q) -> bool,
+{
+    type Handle = Arc<(H, E)>;
+
+    fn hash_value(&self, value: &K) -> u64 {
+        (self.fns.0)(value.borrow())
+    }
+
+    fn hash_key<T: Hash + Borrow<K>>(&self, key: &T) -> u64 {
+        (self.fns.0)(key.borrow().borrow())
+    }
+
+    fn handle(&self) -> Arc<(H, E)> {
+        Arc::clone(&self.fns)
+    }
+
+    fn values_eq(handle: &Arc<(H, E)>, a: &K, b: &K) -> bool {
+        let (a, b) = (a.borrow(), b.borrow());
+        size_of_val(a) == size_of_val(b) && (handle.1)(a, b)
+    }
+
+    fn insert<'a, T: Hash + Borrow<K>, V>(
+        handle: &Arc<(H, E)>,
+        entry: RawVacantEntryMut<'a, T, V, Self>,
+        hash: u64,
+        key: T,
+        value: V,
+    ) -> (&'a mut T, &'a mut V) {
+        entry.insert_with_hasher(hash, key, value, |key| {
+            (handle.0)(key.borrow().borrow())
+        })
+    }
 }
 
 /// A general-purpose value interner.
-pub struct Interner<T> {
-    value_to_pos: HashMap<T, Entry>,
+///
+/// Values are compared with their `Hash` and `Eq` implementations. To
+/// deduplicate values under a different equivalence, create the interner with
+/// [`with_eq`](Interner::with_eq).
+///
+/// Values can be preseeded into an interner with [`preseed`](Self::preseed),
+/// or by collecting an iterator of values into an interner. Preseeded values
+/// are not written until they are first interned, which starts and finishes
+/// them like any other new value. Until then, finishing a preseeded value
+/// fails as if it had never been started.
+///
+/// Finishing a value which was already finished succeeds if it is finished
+/// with the same position, and fails otherwise. Call
+/// [`with_debug_errors`](Self::with_debug_errors) to include the value in these
+/// errors.
+///
+/// # Sized and unsized values
+///
+/// An `Interner<T>` can intern any value which it can store as a `T`, which
+/// means values of types `U` where `U: ToOwned<Owned = T>` and `T: Borrow<U>`.
+/// There are two common cases:
+///
+/// - Unsized values are borrowed from their owned form. An `Interner<String>`
+///   interns `str`s for fields with [`DerefIntern`](crate::DerefIntern), and
+///   an `Interner<Vec<u8>>` interns `[u8]`s.
+/// - Sized values which are `Clone` are their own owned form, so an
+///   `Interner<T>` interns `T`s for fields with [`Intern`](crate::Intern).
+///   New values are cloned into the interner.
+///
+/// An `Interner<String>` also interns `String`s, but they are archived
+/// differently from `str`s. Use separate interners or a
+/// [`MultiInterner`](crate::MultiInterner) to intern both.
+///
+/// # Determinism
+///
+/// Each value is written the first time it is encountered during
+/// serialization, and later copies refer back to that position. The interner
+/// never iterates over its table while serializing, so its hasher and the
+/// order of its entries can't affect the output: serializing the same value
+/// with a new interner always produces the same bytes. Preseeding doesn't
+/// change this either, since preseeded values are only written when they are
+/// first encountered.
+///
+/// # Hashing
+///
+/// Values are hashed with the interner's hasher, which can be set with
+/// [`with_hasher`](Self::with_hasher). Callers which already know the hash of
+/// a value can pass it to
+/// [`start_interning_with_hash`](Interning::start_interning_with_hash) to avoid
+/// hashing the value again, as long as it was computed with
+/// [`hasher`](Self::hasher).
+///
+/// # Custom equality
+///
+/// An interner created with [`with_eq`](Interner::with_eq) hashes values with
+/// a custom `hash` function and compares them with a custom `eq` function, as
+/// its [`WithEq`] hasher. Values which are equal under `eq` share a single
+/// interned value, which is the first of them to be interned. The functions
+/// take the borrowed form of the values, like `str` for an
+/// `Interner<String>`.
+///
+/// `hash` and `eq` must be consistent with each other: values which are equal
+/// under `eq` must have the same hash. Otherwise, equal values may not be
+/// deduplicated.
+///
+/// Each pointer to a shared value is archived with the metadata of the value
+/// it replaced, like the length of a string or slice, so `eq` should only
+/// return `true` for values with the same metadata and archived layout. To keep
+/// pointers from claiming the wrong length, values with different sizes are
+/// never treated as equal, even if `eq` returns `true`. For example, comparing
+/// strings with `a.trim_end() == b.trim_end()` only deduplicates strings with
+/// the same amount of trailing whitespace.
+///
+/// Methods which hash values with a `BuildHasher`, like
+/// [`reserve`](Self::reserve) and [`import_table`](Self::import_table), aren't
+/// available for custom equality.
+///
+/// # Incremental archives
+///
+/// The positions of finished values can be saved with
+/// [`export_table`](Self::export_table) and loaded into a new interner with
+/// [`import_table`](Self::import_table). This lets a later serialization
+/// session append to a previous archive and refer to the values it already
+/// contains. The positions are only valid if the new session continues
+/// writing to the same buffer, so the previous archive must be kept intact at
+/// the start of it.
+///
+/// # Consistency checks
+///
+/// Values must have `Hash` and `Eq` implementations which agree with each
+/// other, and with those of their borrowed forms. Otherwise, a value may be
+/// deduplicated with a different value and the archive will silently refer to
+/// the wrong data. Enabling the `debug-checks` feature rehashes values to
+/// check for this, and fails to finish interning them if they are
+/// inconsistent.
+///
+/// Each value must also be written at its own position. A serializer which
+/// reports the same position for two different values would make them alias
+/// each other in the archive. With `debug-checks` enabled, the interner
+/// remembers the positions it has finished values at, and fails to finish a
+/// new value at a position which was already used.
+///
+/// A hasher which maps many values to the same hash doesn't cause errors, but
+/// makes interning slow. With `debug-checks` enabled,
+/// [`collision_count`](Self::collision_count) reports how often lookups had to
+/// compare values with the same hash.
+///
+/// # Last hit cache
+///
+/// Inputs often repeat the same value many times in a row. An interner created
+/// with [`with_last_hit_cache`](Self::with_last_hit_cache) remembers the hash
+/// and entry of the last value it found already written, and returns its
+/// position without looking it up in the table if the next value has the same
+/// hash and is equal to the interned value. The cache is cleared whenever
+/// values are added, removed, or replaced. Hits on the cache still count as
+/// references when the `statistics` feature is enabled.
+///
+/// # Policies
+///
+/// By default, every value is interned. An [`InterningPolicy`] set with
+/// [`with_policy`](Self::with_policy) can exclude some values from interning,
+/// as can [`set_bypass`](Self::set_bypass). Fields serialized with
+/// [`Intern`](crate::Intern) are still archived as shared pointers, so a
+/// bypassed value isn't inlined into the field. Instead, a new copy of it is
+/// written every time it is serialized, and it is never added to the interner.
+///
+/// # Position storage
+///
+/// Positions are stored as `N`, which is `NonZeroUsize` by default. Archives
+/// which are known to be smaller than 4 GiB can store them as `NonZeroU32`
+/// instead, which makes each entry smaller on 64-bit targets. Finishing a
+/// value at a position which doesn't fit in `N` fails, and importing one with
+/// [`import_table`](Self::import_table) returns it instead of adding it. See
+/// [`PositionInt`] for details.
+///
+/// # Dictionaries
+///
+/// Instead of interning values into a buffer, an interner can assign them
+/// indices in a dictionary which is shared by many archives. Fields serialized
+/// with [`DictIntern`](crate::DictIntern) are archived as the index returned by
+/// [`dictionary_index`](Self::dictionary_index), which gives each new value
+/// the next index and finishes it at that position. Once every archive has
+/// been serialized, [`into_dictionary`](Self::into_dictionary) returns the
+/// values ordered by index, so the dictionary can be archived on its own.
+///
+/// Policies, size limits, and sealing don't apply to dictionary indices. An
+/// interner used as a dictionary shouldn't also be used to intern values into
+/// a buffer, and values shouldn't be removed from it, since the dictionary
+/// would no longer line up with the indices.
+///
+/// # Observers
+///
+/// An observer set with [`set_observer`](Self::set_observer) is called with
+/// an [`InternEvent`] whenever a new value is started or finished, and
+/// whenever a value is found already written. This can be used to report
+/// interning activity as it happens instead of inspecting the interner
+/// afterwards. Bypassed values don't produce any events.
+///
+/// # Maximum value size
+///
+/// [`with_max_value_size`](Self::with_max_value_size) limits the size of new
+/// values, as measured by [`MeasureSize`]. Depending on its
+/// [`OversizePolicy`], oversize values either bypass the interner like values
+/// excluded by a policy, or fail to finish interning. Either way, oversize
+/// values are never added to the interner. Like bypassed values, oversize
+/// values are still archived behind a shared pointer.
+///
+/// # Sealing
+///
+/// An interner can be warmed with a fixed set of values, for example with
+/// [`preseed`](Self::preseed), and then sealed with [`seal`](Self::seal).
+/// Values which a sealed interner already contains are deduplicated as usual,
+/// but new values are never added to it. Depending on its [`SealPolicy`], new
+/// values either bypass the interner or fail to finish interning with a
+/// [`SealedInterner`] error, which catches values missing from a closed
+/// dictionary.
+pub struct Interner<T, S = DefaultHashBuilder, N = NonZeroUsize> {
+    value_to_pos: HashMap<T, Entry<N>, S>,
+    describe: Option<fn(&T) -> String>,
+    policy: Option<Box<dyn InterningPolicy<T> + Send + Sync>>,
+    cache_last_hit: bool,
+    last_hit: Option<LastHit<T, N>>,
+    max_value_size: Option<MaxValueSize<T>>,
+    positions: Option<Vec<usize>>,
+    observer: Option<Observer<T>>,
+    sealed: Option<SealPolicy>,
+    next_index: usize,
+    #[cfg(feature = "debug-checks")]
+    capacity: Option<usize>,
+    #[cfg(feature = "debug-checks")]
+    used_positions: HashSet<usize>,
+    #[cfg(feature = "debug-checks")]
+    collisions: usize,
+}
+
+/// The last value an [`Interner`] found already written.
+struct LastHit<T, N> {
+    hash: u64,
+    // SAFETY: `key` and `entry` point to a value and its entry in the table of
+    // the interner which owns the cache. The table only moves its entries when
+    // it grows or rehashes, so the cache is cleared before anything is
+    // inserted into the table. It is also cleared before any other mutable
+    // access to the table, so that no other reference to the entry is live
+    // when it is accessed through the cache.
+    key: NonNull<T>,
+    entry: NonNull<Entry<N>>,
+}
+
+// SAFETY: The cache only points into the table of the interner which owns it,
+// so it can be sent along with the interner.
+unsafe impl<T: Send, N: Send> Send for LastHit<T, N> {}
+// SAFETY: Shared references to the cache are never used to access the key or
+// entry it points to.
+unsafe impl<T: Sync, N: Sync> Sync for LastHit<T, N> {}
+
+/// Returns one past the largest position in `map`, which is the next index
+/// given to a value by [`Interner::dictionary_index`].
+fn next_index<T, N: PositionInt, S>(map: &HashMap<T, Entry<N>, S>) -> usize {
+    map.values()
+        .filter_map(Entry::pos)
+        .max()
+        .map_or(0, |pos| pos + 1)
+}
+
+/// Returns the handle of the map's hasher and the map's entry for `value`.
+fn entry_mut<'a, T, Q, N, S>(
+    map: &'a mut HashMap<T, Entry<N>, S>,
+    hash: u64,
+    value: &Q,
+) -> (S::Handle, RawEntryMut<'a, T, Entry<N>, S>)
+where
+    T: Borrow<Q>,
+    S: InternHasher<Q>,
+    Q: ?Sized,
+{
+    let handle = map.hasher().handle();
+    let entry = map
+        .raw_entry_mut()
+        .from_hash(hash, |key| S::values_eq(&handle, key.borrow(), value));
+    (handle, entry)
+}
+
+type MaxValueSize<T> = (usize, fn(&T) -> usize, OversizePolicy);
+
+type Observer<T> = Box<dyn FnMut(InternEvent<&T>) + Send + Sync>;
+
+/// Calls the observer with an event, if there is one.
+fn observe<T>(observer: &mut Option<Observer<T>>, event: InternEvent<&T>) {
+    if let Some(observer) = observer {
+        observer(event);
+    }
+}
+
+/// An event reported to the observer of an [`Interner`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InternEvent<T> {
+    /// A new value was started and will be written.
+    Started(T),
+    /// A value was found already written at the given position.
+    Hit(T, usize),
+    /// A new value was finished at the given position.
+    Finished(T, usize),
+}
+
+/// The reference counts of an interner's values, which can be archived.
+///
+/// A snapshot is taken with [`Interner::stats_snapshot`], and can be used to
+/// warm up a new interner with [`Interner::warm_from_stats`], for example after
+/// a process restarts. Positions aren't included, since they are only valid in
+/// the buffer the values were written to.
+#[cfg(feature = "statistics")]
+#[derive(Archive, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct InternerSnapshot<T> {
+    /// Each value and its reference count, from most to least referenced.
+    pub ref_counts: Vec<(T, usize)>,
+}
+
+/// Returns the oversize policy for the value, if it is oversize.
+fn oversize_policy<T>(
+    max_value_size: &Option<MaxValueSize<T>>,
+    value: &T,
+) -> Option<OversizePolicy> {
+    match max_value_size {
+        Some((limit, measure, policy)) if measure(value) > *limit => {
+            Some(*policy)
+        }
+        _ => None,
+    }
+}
+
+/// What an [`Interner`] does with values over its maximum value size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OversizePolicy {
+    /// Write the value every time it is serialized without interning it.
+    Bypass,
+    /// Fail to finish interning the value.
+    Fail,
+}
+
+/// What a sealed [`Interner`] does with values it doesn't contain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SealPolicy {
+    /// Write the value every time it is serialized without interning it.
+    Bypass,
+    /// Fail to finish interning the value with a [`SealedInterner`] error.
+    Fail,
 }
 
 impl<T> Interner<T> {
     /// Returns a new, empty interner.
     pub fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::default())
+    }
+
+    /// Returns a new, empty interner with space for `capacity` values.
+    ///
+    /// When the `debug-checks` feature is enabled, interning more than
+    /// `capacity` distinct values fails. Otherwise, the interner grows as
+    /// usual once it is full.
+    pub fn with_fixed_capacity(capacity: usize) -> Self {
+        let mut result = Self::new();
+        result.value_to_pos = HashMap::with_capacity_and_hasher(
+            capacity,
+            DefaultHashBuilder::default(),
+        );
+        #[cfg(feature = "debug-checks")]
+        {
+            result.capacity = Some(capacity);
+        }
+        result
+    }
+}
+
+impl<T, Q: ?Sized, H, E> Interner<T, WithEq<Q, H, E>> {
+    /// Returns a new, empty interner which hashes values with `hash` and
+    /// compares them with `eq`.
+    ///
+    /// See the [type-level documentation](Self#custom-equality) for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rkyv_intern::Interner;
+    ///
+    /// fn hash(value: &str) -> u64 {
+    ///     value.bytes().fold(0, |hash, byte| {
+    ///         hash.rotate_left(5) ^ u64::from(byte.to_ascii_lowercase())
+    ///     })
+    /// }
+    ///
+    /// let mut interner = Interner::<String, _>::with_eq(hash, |a, b| {
+    ///     str::eq_ignore_ascii_case(a, b)
+    /// });
+    /// interner.preseed("Alice".to_string());
+    /// assert!(interner.contains("ALICE"));
+    /// ```
+    pub fn with_eq(hash: H, eq: E) -> Self
+    where
+        T: Borrow<Q>,
+        H: Fn(&Q) -> u64,
+        E: Fn(&Q, &Q) -> bool,
+    {
+        Self::with_hasher(WithEq {
+            fns: Arc::new((hash, eq)),
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T, S, N: PositionInt> Interner<T, S, N> {
+    /// Returns a new, empty interner which uses the given hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::from_map(HashMap::with_hasher(hasher))
+    }
+
+    /// Returns an interner which uses `map` to store its values.
+    ///
+    /// This lets an existing table of values be used as an interner without
+    /// copying it. Each entry must either be [`Entry::preseeded`],
+    /// [`Entry::pending`], or [`Entry::finished`] with the position the value
+    /// was actually written at in the buffer being serialized to. A finished
+    /// entry with any other position makes every reference to that value point
+    /// to the wrong data.
+    pub fn from_map(map: HashMap<T, Entry<N>, S>) -> Self {
+        #[cfg(feature = "debug-checks")]
+        let used_positions = map.values().filter_map(Entry::pos).collect();
+        let next_index = next_index(&map);
         Self {
-            value_to_pos: HashMap::new(),
+            value_to_pos: map,
+            describe: None,
+            policy: None,
+            cache_last_hit: false,
+            last_hit: None,
+            max_value_size: None,
+            positions: None,
+            observer: None,
+            sealed: None,
+            next_index,
+            #[cfg(feature = "debug-checks")]
+            capacity: None,
+            #[cfg(feature = "debug-checks")]
+            used_positions,
+            #[cfg(feature = "debug-checks")]
+            collisions: 0,
+        }
+    }
+
+    /// Returns `true` if the interner has a fixed capacity and is full.
+    fn is_full(&self) -> bool {
+        #[cfg(feature = "debug-checks")]
+        if let Some(capacity) = self.capacity {
+            return self.value_to_pos.len() >= capacity;
+        }
+        false
+    }
+
+    /// Returns the hasher used to hash values.
+    pub fn hasher(&self) -> &S {
+        self.value_to_pos.hasher()
+    }
+
+    /// Includes the `Debug` representation of values in errors.
+    pub fn with_debug_errors(mut self) -> Self
+    where
+        T: fmt::Debug,
+    {
+        self.describe = Some(|value| format!("{:?}", value));
+        self
+    }
+
+    /// Enables the last hit cache.
+    ///
+    /// See the [type-level documentation](Self#last-hit-cache) for details.
+    pub fn with_last_hit_cache(mut self) -> Self {
+        self.cache_last_hit = true;
+        self
+    }
+
+    /// Sets the policy which decides which values are interned.
+    ///
+    /// Values which the policy rejects are written every time they are
+    /// serialized instead of being deduplicated. This only applies to values
+    /// which are not already in the interner. See the
+    /// [type-level documentation](Self#policies) for details.
+    pub fn with_policy<P>(mut self, policy: P) -> Self
+    where
+        P: InterningPolicy<T> + Send + Sync + 'static,
+    {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Sets an observer which is called as values are interned.
+    ///
+    /// This replaces any previous observer. See the
+    /// [type-level documentation](Self#observers) for details.
+    pub fn set_observer<F>(&mut self, observer: F)
+    where
+        F: FnMut(InternEvent<&T>) + Send + Sync + 'static,
+    {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Records the position of every interned value.
+    ///
+    /// See [`positions`](Self::positions) for details.
+    pub fn with_recorded_positions(mut self) -> Self {
+        self.positions = Some(Vec::new());
+        self
+    }
+
+    /// The positions of interned values, in the order they were interned.
+    ///
+    /// Unlike [`iter`](Self::iter), this has an entry for every time a value
+    /// was interned, including duplicates. New values are recorded when they
+    /// are finished, so a value containing other interned values is recorded
+    /// after them. This is empty unless the interner was created with
+    /// [`with_recorded_positions`](Self::with_recorded_positions).
+    pub fn positions(&self) -> &[usize] {
+        self.positions.as_deref().unwrap_or(&[])
+    }
+
+    /// Records the position of an interned value, if enabled.
+    fn record_position(&mut self, pos: usize) {
+        if let Some(positions) = &mut self.positions {
+            positions.push(pos);
         }
     }
 
+    /// Limits new values to at most `limit` bytes.
+    ///
+    /// Values larger than `limit` are handled according to `policy`. See the
+    /// [type-level documentation](Self#maximum-value-size) for details.
+    pub fn with_max_value_size(
+        mut self,
+        limit: usize,
+        policy: OversizePolicy,
+    ) -> Self
+    where
+        T: MeasureSize,
+    {
+        self.max_value_size = Some((limit, T::measure_size, policy));
+        self
+    }
+
+    /// Seals the interner so that no new values are added to it.
+    ///
+    /// Values which aren't in the interner yet are handled according to
+    /// `policy`. Values can still be added explicitly, for example with
+    /// [`preseed`](Self::preseed). See the
+    /// [type-level documentation](Self#sealing) for details.
+    pub fn seal(&mut self, policy: SealPolicy) {
+        self.sealed = Some(policy);
+    }
+
+    /// Returns `true` if the interner is sealed.
+    pub fn is_sealed(&self) -> bool {
+        self.sealed.is_some()
+    }
+
+    /// Sets a predicate for values which should bypass the interner.
+    ///
+    /// This replaces the interner's policy with one which interns the values
+    /// for which `bypass` returns `false`. See the
+    /// [type-level documentation](Self#policies) for details.
+    pub fn set_bypass(&mut self, bypass: fn(&T) -> bool)
+    where
+        T: 'static,
+    {
+        self.policy = Some(Box::new(move |value: &T| !bypass(value)));
+    }
+
+    /// Returns `true` if the value should be interned.
+    fn should_intern(&self, value: &T) -> bool {
+        self.policy
+            .as_ref()
+            .is_none_or(|policy| policy.should_intern(value))
+    }
+
+    /// Looks up a value and its entry.
+    fn find<Q>(&self, value: &Q) -> Option<(&T, &Entry<N>)>
+    where
+        T: Borrow<Q>,
+        S: InternHasher<Q>,
+        Q: ?Sized,
+    {
+        let hasher = self.value_to_pos.hasher();
+        let handle = hasher.handle();
+        self.value_to_pos
+            .raw_entry()
+            .from_hash(hasher.hash_value(value), |key| {
+                S::values_eq(&handle, key.borrow(), value)
+            })
+    }
+
     /// The number of interned values.
     pub fn len(&self) -> usize {
         self.value_to_pos.len()
     }
 
+    /// Returns `true` if the interner contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_pos.is_empty()
+    }
+
+    /// The number of collisions found while looking up values to intern.
+    ///
+    /// A collision is counted each time a lookup compares the value with a
+    /// different value whose hash looked the same. The table only compares the
+    /// top seven bits of the hashes before comparing values, so a good hasher
+    /// still causes a few collisions. A count close to the number of lookups or
+    /// higher means that the hasher is distributing values poorly.
+    #[cfg(feature = "debug-checks")]
+    pub fn collision_count(&self) -> usize {
+        self.collisions
+    }
+
+    /// The number of values which were started interning but not finished.
+    ///
+    /// This is zero after a value is serialized successfully. If serialization
+    /// fails, the values which were being serialized are left pending.
+    pub fn pending_len(&self) -> usize {
+        self.value_to_pos
+            .values()
+            .filter(|entry| entry.is_pending())
+            .count()
+    }
+
+    /// Returns the number of values with each reference count.
+    ///
+    /// Each key is a reference count, and its value is the number of interned
+    /// values which were referenced that many times. Preseeded values which
+    /// were never interned have a reference count of zero.
+    #[cfg(feature = "statistics")]
+    pub fn ref_count_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for entry in self.value_to_pos.values() {
+            *histogram.entry(entry.ref_cnt).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Increments the reference count of a value without interning it.
+    ///
+    /// Values which the interner doesn't contain yet are preseeded with a
+    /// reference count of one. Positions are never changed, so this can count
+    /// the occurrences of values in a separate pass before anything is
+    /// serialized, for example to decide which values are worth interning.
+    /// Returns the new reference count, or `None` if the value bypasses the
+    /// interner.
+    ///
+    /// Interning a value also increments its reference count, so values which
+    /// are counted manually and then serialized with the same interner are
+    /// counted twice.
+    #[cfg(feature = "statistics")]
+    pub fn bump_ref<Q>(&mut self, value: &Q) -> Option<usize>
+    where
+        T: Hash + Borrow<Q>,
+        S: InternHasher<Q>,
+        Q: ToOwned<Owned = T> + ?Sized,
+    {
+        self.last_hit = None;
+        let hash = self.value_to_pos.hasher().hash_value(value);
+        let owned = match entry_mut(&mut self.value_to_pos, hash, value) {
+            (_, RawEntryMut::Occupied(entry)) => {
+                let entry = entry.into_mut();
+                entry.ref_cnt = entry.ref_cnt.saturating_add(1);
+                return Some(entry.ref_cnt);
+            }
+            (_, RawEntryMut::Vacant(_)) => value.to_owned(),
+        };
+        if !self.should_intern(&owned) {
+            return None;
+        }
+        let mut entry = Entry::preseeded();
+        entry.ref_cnt = 1;
+        if let (handle, RawEntryMut::Vacant(vacant)) =
+            entry_mut(&mut self.value_to_pos, hash, value)
+        {
+            S::insert(&handle, vacant, hash, owned, entry);
+        }
+        Some(1)
+    }
+
+    /// The number of values which were referenced exactly once.
+    ///
+    /// Interning these values didn't deduplicate them, so they only added an
+    /// indirection to the archive.
+    #[cfg(feature = "statistics")]
+    pub fn singleton_count(&self) -> usize {
+        self.value_to_pos
+            .values()
+            .filter(|entry| entry.ref_cnt == 1)
+            .count()
+    }
+
+    /// Returns a snapshot of the reference counts of the values.
+    ///
+    /// Values are ordered from most to least referenced. Values with the same
+    /// reference count are in no particular order.
+    #[cfg(feature = "statistics")]
+    pub fn stats_snapshot(&self) -> InternerSnapshot<T>
+    where
+        T: Clone,
+    {
+        let mut ref_counts = self
+            .value_to_pos
+            .iter()
+            .map(|(value, entry)| (value.clone(), entry.ref_cnt))
+            .collect::<Vec<_>>();
+        ref_counts.sort_unstable_by_key(|&(_, ref_cnt)| Reverse(ref_cnt));
+        InternerSnapshot { ref_counts }
+    }
+
+    /// Preseeds the values which were referenced more than once in a
+    /// snapshot.
+    ///
+    /// Values which were only referenced once didn't benefit from interning,
+    /// so they aren't preseeded. Returns the number of values which were
+    /// preseeded. Like [`preseed`](Self::preseed), values which are already in
+    /// the interner or bypass it are skipped.
+    #[cfg(feature = "statistics")]
+    pub fn warm_from_stats(&mut self, snapshot: InternerSnapshot<T>) -> usize
+    where
+        T: Hash,
+        S: InternHasher<T>,
+    {
+        let mut preseeded = 0;
+        for (value, ref_cnt) in snapshot.ref_counts {
+            if ref_cnt > 1 && self.preseed(value) {
+                preseeded += 1;
+            }
+        }
+        preseeded
+    }
+
+    /// Returns the map which stores the interned values.
+    pub fn as_map(&self) -> &HashMap<T, Entry<N>, S> {
+        &self.value_to_pos
+    }
+
+    /// Consumes the interner and returns the map which stores its values.
+    pub fn into_map(self) -> HashMap<T, Entry<N>, S> {
+        self.value_to_pos
+    }
+
     /// The interned values.
-    pub fn iter(&self) -> hashbrown::hash_map::Iter<'_, T, Entry> {
+    pub fn iter(&self) -> hashbrown::hash_map::Iter<'_, T, Entry<N>> {
         self.value_to_pos.iter()
     }
-}
 
-impl<T> Default for Interner<T> {
-    fn default() -> Self {
-        Self::new()
+    /// The finished values and their positions, in ascending order of
+    /// position.
+    ///
+    /// This follows the order the values were written in the archive. Values
+    /// which were preseeded or are still pending are skipped.
+    pub fn iter_by_position(&self) -> impl Iterator<Item = (&T, usize)> {
+        let mut values = self
+            .value_to_pos
+            .iter()
+            .filter_map(|(value, entry)| Some((value, entry.pos()?)))
+            .collect::<Vec<_>>();
+        values.sort_unstable_by_key(|&(_, pos)| pos);
+        values.into_iter()
     }
-}
 
-#[derive(Debug)]
-struct NotStarted;
+    /// Removes all values from the interner.
+    ///
+    /// This keeps the allocated memory for reuse.
+    pub fn clear(&mut self) {
+        self.value_to_pos.clear();
+        self.last_hit = None;
+        self.next_index = 0;
+        #[cfg(feature = "debug-checks")]
+        self.used_positions.clear();
+    }
 
-impl fmt::Display for NotStarted {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "value was not started interning")
+    /// The number of values the interner can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.value_to_pos.capacity()
     }
-}
 
-impl Error for NotStarted {}
+    /// Reserves capacity for at least `additional` more values.
+    pub fn reserve(&mut self, additional: usize)
+    where
+        T: Hash + Eq,
+        S: BuildHasher,
+    {
+        self.last_hit = None;
+        self.value_to_pos.reserve(additional);
+    }
 
-#[derive(Debug)]
-struct AlreadyFinished;
+    /// Shrinks the capacity of the interner as much as possible.
+    pub fn shrink_to_fit(&mut self)
+    where
+        T: Hash + Eq,
+        S: BuildHasher,
+    {
+        self.last_hit = None;
+        self.value_to_pos.shrink_to_fit();
+    }
 
-impl fmt::Display for AlreadyFinished {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "value was already finished interning")
+    /// Returns an estimate of the number of heap bytes used by the interner.
+    ///
+    /// This includes the memory allocated for the interner's table, any
+    /// recorded positions, and the heap memory owned by each value, as reported
+    /// by [`HeapSize`]. It does not include any overhead from the allocator
+    /// itself, or the size of the interner struct.
+    pub fn memory_usage(&self) -> usize
+    where
+        T: Hash + Eq + HeapSize,
+        S: BuildHasher,
+    {
+        self.value_to_pos.allocation_size()
+            + self.positions.as_ref().map_or(0, HeapSize::heap_size)
+            + self.value_to_pos.keys().map(T::heap_size).sum::<usize>()
+    }
+
+    /// Returns `true` if the interner contains the value.
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        S: InternHasher<Q>,
+        Q: ?Sized,
+    {
+        self.find(value).is_some()
     }
-}
 
-impl Error for AlreadyFinished {}
+    /// Returns the position of the value, if it has been written.
+    pub fn get_pos<Q>(&self, value: &Q) -> Option<usize>
+    where
+        T: Borrow<Q>,
+        S: InternHasher<Q>,
+        Q: ?Sized,
+    {
+        self.find(value).and_then(|(_, entry)| entry.pos())
+    }
 
-impl<T, E> Interning<T, E> for Interner<T::Owned>
-where
-    T::Owned: Hash + Eq + Borrow<T>,
-    T: Hash + Eq + ToOwned + ?Sized,
-    E: Source,
-{
-    type State<'a> = (&'a T, u64) where T: 'a;
+    /// Removes a value from the interner, returning its entry.
+    ///
+    /// If the value is interned again after it has been removed, it will be
+    /// written again. Pending values can't be removed because their position
+    /// isn't known yet, so this returns `None` if the value is pending or was
+    /// not in the interner.
+    pub fn remove<Q>(&mut self, value: &Q) -> Option<Entry<N>>
+    where
+        T: Borrow<Q>,
+        S: InternHasher<Q>,
+        Q: ?Sized,
+    {
+        let hash = self.value_to_pos.hasher().hash_value(value);
+        match entry_mut(&mut self.value_to_pos, hash, value).1 {
+            RawEntryMut::Occupied(entry) if !entry.get().is_pending() => {
+                self.last_hit = None;
+                let entry = entry.remove();
+                #[cfg(feature = "debug-checks")]
+                if let Some(pos) = entry.pos() {
+                    self.used_positions.remove(&pos);
+                }
+                Some(entry)
+            }
+            _ => None,
+        }
+    }
 
-    fn start_interning<'a>(&mut self, value: &'a T) -> InterningState<Self::State<'a>> {
+    /// Removes the value with the given hash which was written at `pos`.
+    /// Returns `false` if there was no such value.
+    pub(crate) fn remove_written(&mut self, hash: u64, pos: usize) -> bool
+    where
+        T: Hash,
+        S: BuildHasher,
+    {
         use hashbrown::hash_map::RawEntryMut::*;
-        let hash = self.value_to_pos.hasher().hash_one(value);
-        match self.value_to_pos.raw_entry_mut().from_key_hashed_nocheck(hash, value) {
-            Occupied(mut entry) => {
-                let entry = entry.get_mut();
-                #[cfg(feature = "statistics")]
-                {
-                    entry.ref_cnt = entry.ref_cnt.checked_add(1).unwrap();
-                }
-                match entry.pos {
-                    None => InterningState::Pending,
-                    Some(pos) => InterningState::Finished(pos.get() - 1),
-                }
+        let hasher = self.value_to_pos.hasher();
+        let found = self
+            .value_to_pos
+            .raw_entry()
+            .from_hash(hash, |key| hasher.hash_one(key) == hash);
+        // Values whose hashes collide are told apart by their positions, which
+        // takes a scan in the rare case that the first one found is another.
+        let ptr = match found {
+            Some((key, entry)) if entry.pos() == Some(pos) => key as *const T,
+            Some(_) => match self
+                .value_to_pos
+                .iter()
+                .find(|(_, entry)| entry.pos() == Some(pos))
+            {
+                Some((key, _)) => key as *const T,
+                None => return false,
             },
-            Vacant(entry) => {
-                entry.insert(value.to_owned(), Entry {
-                    pos: None,
-                    #[cfg(feature = "statistics")]
-                    ref_cnt: NonZeroUsize::new(1).unwrap(),
-                });
-                InterningState::Started((value, hash))
+            None => return false,
+        };
+        match self
+            .value_to_pos
+            .raw_entry_mut()
+            .from_hash(hash, |key| ptr::eq(key, ptr))
+        {
+            Occupied(entry) => {
+                entry.remove();
+                #[cfg(feature = "debug-checks")]
+                self.used_positions.remove(&pos);
+                self.last_hit = None;
+                true
             }
+            Vacant(_) => unreachable!(),
         }
     }
 
-    fn finish_interning(&mut self, state: Self::State<'_>, pos: usize) -> Result<(), E> {
-        use hashbrown::hash_map::RawEntryMut::*;
-        let (value, hash) = state;
-        match self.value_to_pos.raw_entry_mut().from_key_hashed_nocheck(hash, value) {
-            Occupied(entry) => match &mut entry.into_mut().pos {
-                Some(_) => fail!(AlreadyFinished),
-                x => {
-                    *x = Some(NonZeroUsize::new(pos + 1).unwrap());
-                    Ok(())
+    /// Preseeds the interner with a value.
+    ///
+    /// The value will be written the first time it is interned. Returns
+    /// `false` if the interner already contained the value, or if the value
+    /// bypasses the interner.
+    pub fn preseed(&mut self, value: T) -> bool
+    where
+        T: Hash,
+        S: InternHasher<T>,
+    {
+        if !self.should_intern(&value) {
+            return false;
+        }
+        self.last_hit = None;
+        let hash = self.value_to_pos.hasher().hash_value(&value);
+        match entry_mut(&mut self.value_to_pos, hash, &value) {
+            (_, RawEntryMut::Occupied(_)) => false,
+            (handle, RawEntryMut::Vacant(entry)) => {
+                S::insert(&handle, entry, hash, value, Entry::preseeded());
+                true
+            }
+        }
+    }
+
+    /// Returns the dictionary index of a value, giving it the next index if it
+    /// doesn't have one yet.
+    ///
+    /// See the [type-level documentation](Self#dictionaries) for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the next index can't be stored as `N`.
+    pub fn dictionary_index<Q>(&mut self, value: &Q) -> usize
+    where
+        T: Hash + Borrow<Q>,
+        S: InternHasher<Q>,
+        Q: ToOwned<Owned = T> + ?Sized,
+    {
+        self.last_hit = None;
+        let hash = self.value_to_pos.hasher().hash_value(value);
+        let entry = match entry_mut(&mut self.value_to_pos, hash, value) {
+            (_, RawEntryMut::Occupied(entry)) => entry.into_mut(),
+            (handle, RawEntryMut::Vacant(entry)) => {
+                let owned = value.to_owned();
+                S::insert(&handle, entry, hash, owned, Entry::preseeded()).1
+            }
+        };
+        if let InterningState::Finished(index) = entry.restart(()) {
+            return index;
+        }
+        let index = self.next_index;
+        entry.pos = Some(N::encode(index).unwrap_or_else(|| {
+            panic!("dictionary index {} is too large to be stored", index)
+        }));
+        self.next_index += 1;
+        #[cfg(feature = "debug-checks")]
+        self.used_positions.insert(index);
+        index
+    }
+
+    /// Consumes the interner and returns the finished values, in ascending
+    /// order of position.
+    ///
+    /// This is the order the values were written in, like
+    /// [`iter_by_position`](Self::iter_by_position). Values which were
+    /// preseeded or are still pending are skipped. For an interner used as a
+    /// dictionary, each value's index in the returned `Vec` is its dictionary
+    /// index.
+    pub fn into_dictionary(self) -> Vec<T> {
+        let mut values = self
+            .value_to_pos
+            .into_iter()
+            .filter_map(|(value, entry)| Some((entry.pos()?, value)))
+            .collect::<Vec<_>>();
+        values.sort_unstable_by_key(|&(pos, _)| pos);
+        values.into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// Returns the finished values and their positions.
+    ///
+    /// Values which were preseeded or are still pending are not included. See
+    /// the [type-level documentation](Self#incremental-archives) for details.
+    pub fn export_table(&self) -> Vec<(T, usize)>
+    where
+        T: Clone,
+    {
+        self.value_to_pos
+            .iter()
+            .filter_map(|(value, entry)| Some((value.clone(), entry.pos()?)))
+            .collect()
+    }
+
+    /// Adds finished values with their positions from a previous session.
+    ///
+    /// Interning an imported value returns its position without writing it
+    /// again. Values which are already in the interner are replaced. See the
+    /// [type-level documentation](Self#incremental-archives) for details.
+    ///
+    /// Values whose positions can't be stored as `N` are not imported, and are
+    /// returned instead. With the default `NonZeroUsize` this is only a value
+    /// at `usize::MAX`, but narrower integers like `NonZeroU32` can't store
+    /// any position at or above `u32::MAX`. See [`PositionInt`] for details.
+    pub fn import_table<I>(&mut self, iter: I) -> Vec<(T, usize)>
+    where
+        I: IntoIterator<Item = (T, usize)>,
+        T: Hash + Eq,
+        S: BuildHasher,
+    {
+        self.last_hit = None;
+        let mut skipped = Vec::new();
+        for (value, pos) in iter {
+            match Entry::finished(pos) {
+                Some(entry) => {
+                    let _replaced = self.value_to_pos.insert(value, entry);
+                    self.next_index = self.next_index.max(pos + 1);
+                    #[cfg(feature = "debug-checks")]
+                    {
+                        if let Some(old) = _replaced.and_then(|e| e.pos()) {
+                            self.used_positions.remove(&old);
+                        }
+                        self.used_positions.insert(pos);
+                    }
                 }
+                None => skipped.push((value, pos)),
+            }
+        }
+        skipped
+    }
+
+    /// Moves every written value to the position returned by `f`.
+    ///
+    /// This keeps the interner in sync with a buffer whose values were moved
+    /// after they were written, for example by a pass which compacts it, so
+    /// that more values can be appended to the buffer later. Recorded
+    /// positions are moved as well. Pending values are skipped because they
+    /// haven't been written yet, and should be finished at their new
+    /// positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns a position which can't be stored as `N`.
+    pub fn remap_positions(&mut self, f: impl Fn(usize) -> usize) {
+        self.last_hit = None;
+        // Bypassed values have positions too, so every used position is
+        // moved rather than only the positions of entries.
+        #[cfg(feature = "debug-checks")]
+        {
+            self.used_positions = self.used_positions.drain().map(&f).collect();
+        }
+        for entry in self.value_to_pos.values_mut() {
+            if let Some(pos) = &mut entry.pos {
+                let new_pos = f(pos.decode());
+                *pos = N::encode(new_pos).unwrap_or_else(|| {
+                    panic!("position {} is too large to be stored", new_pos)
+                });
+            }
+        }
+        self.next_index = next_index(&self.value_to_pos);
+        if let Some(positions) = &mut self.positions {
+            for pos in positions {
+                *pos = f(*pos);
             }
-            Vacant(_) => fail!(NotStarted),
         }
     }
 }
+
+/// A type which owns memory on the heap.
+///
+/// This is used by [`Interner::memory_usage`] to estimate how much memory is
+/// owned by interned values.
+pub trait HeapSize {
+    /// Returns the number of heap bytes owned by this value.
+    ///
+    /// This only counts memory owned directly by the value, and not memory
+    /// owned by any values it contains.
+    fn heap_size(&self) -> usize;
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl HeapSize for Box<str> {
+    fn heap_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>()
+    }
+}
+
+impl<T> HeapSize for Box<[T]> {
+    fn heap_size(&self) -> usize {
+        self.len() * size_of::<T>()
+    }
+}
+
+/// A type which can measure the size of its values.
+///
+/// This is used by [`Interner::with_max_value_size`] to find oversize values.
+pub trait MeasureSize {
+    /// Returns the size of this value in bytes.
+    fn measure_size(&self) -> usize;
+}
+
+impl MeasureSize for String {
+    fn measure_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl MeasureSize for Box<str> {
+    fn measure_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> MeasureSize for Vec<T> {
+    fn measure_size(&self) -> usize {
+        self.len() * size_of::<T>()
+    }
+}
+
+impl<T> MeasureSize for Box<[T]> {
+    fn measure_size(&self) -> usize {
+        self.len() * size_of::<T>()
+    }
+}
+
+impl<T, S: Default, N: PositionInt> Default for Interner<T, S, N> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<T, S, N> Extend<T> for Interner<T, S, N>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    N: PositionInt,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.preseed(value);
+        }
+    }
+}
+
+impl<T, S, N> FromIterator<T> for Interner<T, S, N>
+where
+    T: Hash + Eq,
+    S: BuildHasher + Default,
+    N: PositionInt,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = Self::default();
+        result.extend(iter);
+        result
+    }
+}
+
+#[derive(Debug)]
+struct PositionTooLarge(Option<String>, usize);
+
+impl fmt::Display for PositionTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => write!(
+                f,
+                "value {} can't be interned at position {}",
+                value, self.1,
+            ),
+            None => {
+                write!(f, "value can't be interned at position {}", self.1)
+            }
+        }
+    }
+}
+
+impl Error for PositionTooLarge {}
+
+#[derive(Debug)]
+struct CapacityExceeded(Option<String>);
+
+impl fmt::Display for CapacityExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => write!(
+                f,
+                "value {} exceeds the fixed capacity of the interner",
+                value,
+            ),
+            None => {
+                write!(f, "value exceeds the fixed capacity of the interner")
+            }
+        }
+    }
+}
+
+impl Error for CapacityExceeded {}
+
+#[derive(Debug)]
+struct ValueTooLarge(Option<String>);
+
+impl fmt::Display for ValueTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => write!(
+                f,
+                "value {} exceeds the maximum value size of the interner",
+                value,
+            ),
+            None => write!(
+                f,
+                "value exceeds the maximum value size of the interner",
+            ),
+        }
+    }
+}
+
+impl Error for ValueTooLarge {}
+
+#[cfg(feature = "debug-checks")]
+#[derive(Debug)]
+struct InconsistentHash(Option<String>);
+
+#[cfg(feature = "debug-checks")]
+impl fmt::Display for InconsistentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => write!(
+                f,
+                "value {} has `Hash` and `Eq` implementations which are \
+                 inconsistent",
+                value,
+            ),
+            None => write!(
+                f,
+                "value has `Hash` and `Eq` implementations which are \
+                 inconsistent",
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "debug-checks")]
+impl Error for InconsistentHash {}
+
+#[cfg(feature = "debug-checks")]
+#[derive(Debug)]
+struct DuplicatePosition(Option<String>, usize);
+
+#[cfg(feature = "debug-checks")]
+impl fmt::Display for DuplicatePosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => write!(
+                f,
+                "value {} was finished at position {}, which was already \
+                 used by another value",
+                value, self.1,
+            ),
+            None => write!(
+                f,
+                "value was finished at position {}, which was already used by \
+                 another value",
+                self.1,
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "debug-checks")]
+impl Error for DuplicatePosition {}
+
+#[cfg(feature = "debug-checks")]
+impl<T: Hash, S, N> Interner<T, S, N> {
+    /// Returns `true` if the value and the interned value equal to it hash to
+    /// the given hash.
+    fn is_consistent<Q>(&self, value: &Q, hash: u64) -> bool
+    where
+        T: Borrow<Q>,
+        S: InternHasher<Q>,
+        Q: ToOwned<Owned = T> + ?Sized,
+    {
+        let hasher = self.value_to_pos.hasher();
+        let check = |key: &T| {
+            hasher.hash_key(key) == hash
+                && hasher.hash_value(key.borrow()) == hash
+        };
+        let handle = hasher.handle();
+        match self
+            .value_to_pos
+            .raw_entry()
+            .from_hash(hash, |key| S::values_eq(&handle, key.borrow(), value))
+        {
+            Some((key, _)) => check(key),
+            None => check(&value.to_owned()),
+        }
+    }
+}
+
+impl<T, S, N, E> Interning<T, E> for Interner<T::Owned, S, N>
+where
+    T::Owned: Hash + Borrow<T>,
+    T: ToOwned + ?Sized,
+    S: InternHasher<T>,
+    N: PositionInt,
+    E: InterningError,
+{
+    type State<'a> = Option<(&'a T, u64)> where T: 'a;
+
+    fn start_interning<'a>(&mut self, value: &'a T) -> InterningState<Self::State<'a>> {
+        let hash = self.value_to_pos.hasher().hash_value(value);
+        Interning::<T, E>::start_interning_with_hash(self, value, hash)
+    }
+
+    fn start_interning_with_hash<'a>(
+        &mut self,
+        value: &'a T,
+        hash: u64,
+    ) -> InterningState<Self::State<'a>> {
+        use hashbrown::hash_map::RawEntryMut::*;
+        #[cfg(feature = "debug-checks")]
+        if !self.is_consistent(value, hash) {
+            // Start the value without changing its entry so that
+            // `finish_interning` reports the inconsistency.
+            return InterningState::Started(Some((value, hash)));
+        }
+        let handle = self.value_to_pos.hasher().handle();
+        if let Some(last_hit) = &mut self.last_hit {
+            // SAFETY: The cache is cleared before the table is changed, so the
+            // key and entry are still in the table and nothing else borrows
+            // them.
+            let (key, entry) =
+                unsafe { (last_hit.key.as_ref(), last_hit.entry.as_mut()) };
+            if last_hit.hash == hash
+                && S::values_eq(&handle, key.borrow(), value)
+            {
+                let state = entry.restart(Some((value, hash)));
+                if let InterningState::Finished(pos) = state {
+                    observe(&mut self.observer, InternEvent::Hit(key, pos));
+                    self.record_position(pos);
+                }
+                return state;
+            }
+        }
+        let full = self.is_full();
+        #[cfg(feature = "debug-checks")]
+        let collisions = &mut self.collisions;
+        let found = self.value_to_pos.raw_entry_mut().from_hash(hash, |key| {
+            let equal = S::values_eq(&handle, key.borrow(), value);
+            #[cfg(feature = "debug-checks")]
+            if !equal {
+                *collisions += 1;
+            }
+            equal
+        });
+        match found {
+            Occupied(entry) => {
+                let (key, entry) = entry.into_key_value();
+                let state = entry.restart(Some((value, hash)));
+                match state {
+                    InterningState::Started(_) => {
+                        observe(&mut self.observer, InternEvent::Started(key));
+                    }
+                    InterningState::Pending => (),
+                    InterningState::Finished(pos) => {
+                        observe(&mut self.observer, InternEvent::Hit(key, pos));
+                        if self.cache_last_hit {
+                            self.last_hit = Some(LastHit {
+                                hash,
+                                key: NonNull::from(key),
+                                entry: NonNull::from(entry),
+                            });
+                        }
+                        self.record_position(pos);
+                    }
+                }
+                state
+            }
+            Vacant(entry) => {
+                let owned = value.to_owned();
+                let policy = self.policy.as_ref();
+                if policy.is_some_and(|policy| !policy.should_intern(&owned)) {
+                    return InterningState::Started(None);
+                }
+                match oversize_policy(&self.max_value_size, &owned) {
+                    Some(OversizePolicy::Bypass) => {
+                        return InterningState::Started(None);
+                    }
+                    // Start the value without adding it so that
+                    // `finish_interning` reports that it is oversize.
+                    Some(OversizePolicy::Fail) => {
+                        return InterningState::Started(Some((value, hash)));
+                    }
+                    None => (),
+                }
+                match self.sealed {
+                    Some(SealPolicy::Bypass) => {
+                        return InterningState::Started(None);
+                    }
+                    // Start the value without adding it so that
+                    // `finish_interning` reports that the interner is sealed.
+                    Some(SealPolicy::Fail) => {
+                        return InterningState::Started(Some((value, hash)));
+                    }
+                    None => (),
+                }
+                if full {
+                    // Start the value without adding it so that
+                    // `finish_interning` reports that the interner is full.
+                    return InterningState::Started(Some((value, hash)));
+                }
+                self.last_hit = None;
+                let (key, _) =
+                    S::insert(&handle, entry, hash, owned, Entry::pending());
+                observe(&mut self.observer, InternEvent::Started(key));
+                InterningState::Started(Some((value, hash)))
+            }
+        }
+    }
+
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        if cfg!(feature = "statistics") || self.observer.is_some() {
+            self.last_hit = None;
+            let hash = self.value_to_pos.hasher().hash_value(value);
+            if let (_, RawEntryMut::Occupied(entry)) =
+                entry_mut(&mut self.value_to_pos, hash, value)
+            {
+                let (key, _entry) = entry.into_key_value();
+                #[cfg(feature = "statistics")]
+                {
+                    _entry.ref_cnt = _entry.ref_cnt.saturating_add(1);
+                }
+                observe(&mut self.observer, InternEvent::Hit(key, pos));
+            }
+        }
+        self.record_position(pos);
+    }
+
+    fn finish_interning(&mut self, state: Self::State<'_>, pos: usize) -> Result<(), E> {
+        use hashbrown::hash_map::RawEntryMut::*;
+        let (value, hash) = match state {
+            Some(state) => state,
+            None => {
+                #[cfg(feature = "debug-checks")]
+                if !self.used_positions.insert(pos) {
+                    return Err(E::new(DuplicatePosition(None, pos)));
+                }
+                self.record_position(pos);
+                return Ok(());
+            }
+        };
+        self.last_hit = None;
+        let describe = self.describe;
+        let full = self.is_full();
+        #[cfg(feature = "debug-checks")]
+        if !self.is_consistent(value, hash) {
+            let value = describe.map(|describe| describe(&value.to_owned()));
+            return Err(E::new(InconsistentHash(value)));
+        }
+        match entry_mut(&mut self.value_to_pos, hash, value).1 {
+            Occupied(entry) => {
+                let (value, entry) = entry.into_key_value();
+                #[cfg(feature = "debug-checks")]
+                if entry.is_pending() && !self.used_positions.insert(pos) {
+                    let value = describe.map(|describe| describe(value));
+                    return Err(E::new(DuplicatePosition(value, pos)));
+                }
+                let newly_finished = entry.is_pending();
+                entry.finish(pos, || describe.map(|describe| describe(value)))?;
+                if newly_finished {
+                    let event = InternEvent::Finished(&*value, pos);
+                    observe(&mut self.observer, event);
+                }
+                self.record_position(pos);
+                Ok(())
+            }
+            Vacant(_) => {
+                let owned = value.to_owned();
+                let value = describe.map(|f| f(&owned));
+                if oversize_policy(&self.max_value_size, &owned).is_some() {
+                    Err(E::new(ValueTooLarge(value)))
+                } else if self.sealed.is_some() {
+                    Err(E::new(SealedInterner(value)))
+                } else if full {
+                    Err(E::new(CapacityExceeded(value)))
+                } else {
+                    Err(E::new(NotStarted(value)))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        format,
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::{hash::BuildHasher, iter::FromIterator, ptr};
+
+    use hashbrown::HashSet;
+    use rkyv::{
+        access_unchecked,
+        api::serialize_using,
+        deserialize,
+        rancor::{Error, Failure, Panic, Strategy},
+        ser::{writer::Buffer, Serializer},
+        util::{with_arena, AlignedVec},
+        Archive, Archived, Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_with, USERS},
+        Intern, Interner, Interning, InterningAdapter, InterningExt as _,
+        InterningState, OversizePolicy,
+    };
+
+    #[test]
+    fn preseeded_values() {
+        #[derive(Archive, Serialize)]
+        struct Message {
+            #[rkyv(with = Intern)]
+            kind: String,
+        }
+
+        const KINDS: [&str; 3] = ["request", "response", "notification"];
+
+        let interner = KINDS
+            .iter()
+            .map(|kind| kind.to_string())
+            .collect::<Interner<String>>();
+        assert_eq!(interner.len(), KINDS.len());
+
+        let value = (0..10)
+            .map(|i| Message {
+                kind: KINDS[i % 2].to_string(),
+            })
+            .collect::<Vec<_>>();
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), KINDS.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Message>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(*a.kind, b.kind);
+        }
+
+        for (value, entry) in interner.iter() {
+            assert_eq!(entry.pos.is_some(), value != KINDS[2]);
+            #[cfg(feature = "statistics")]
+            assert_eq!(entry.ref_cnt, if value == KINDS[2] { 0 } else { 5 });
+        }
+    }
+
+    #[test]
+    fn intern_cloneable_values() {
+        #[derive(Archive, Serialize, Deserialize, Clone, Debug)]
+        #[derive(Hash, PartialEq, Eq)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Segment {
+            #[rkyv(with = Intern)]
+            start: Point,
+            #[rkyv(with = Intern)]
+            end: Point,
+        }
+
+        let points = (0..4).map(|i| Point { x: i, y: -i }).collect::<Vec<_>>();
+        let value = (0..100)
+            .map(|i| Segment {
+                start: points[i % 4].clone(),
+                end: points[(i + 1) % 4].clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, Interner::<Point>::new())
+                .unwrap();
+        assert_eq!(interner.len(), points.len());
+        assert!(points.iter().all(|point| interner.contains(point)));
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Segment>>>(&bytes) };
+        assert!(ptr::eq(&*archived[0].end, &*archived[1].start));
+
+        let deserialized =
+            deserialize::<Vec<Segment>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn empty() {
+        let mut interner = Interner::<String>::new();
+        assert!(interner.is_empty());
+
+        interner.preseed(USERS[0].to_string());
+        assert!(!interner.is_empty());
+
+        interner.clear();
+        assert!(interner.is_empty());
+
+        let interner = Interner::<String>::from_iter(None);
+        assert!(interner.is_empty());
+    }
+
+    #[test]
+    fn finish_twice() {
+        let mut interner = Interner::<String>::new();
+        let state = match Interning::<str, Panic>::start_interning(
+            &mut interner,
+            USERS[0],
+        ) {
+            InterningState::Started(state) => state,
+            _ => panic!("value should have started interning"),
+        };
+
+        let mut finish = |pos| {
+            Interning::<str, Failure>::finish_interning(
+                &mut interner,
+                state,
+                pos,
+            )
+        };
+        assert!(finish(8).is_ok());
+        assert!(finish(8).is_ok());
+        assert!(finish(16).is_err());
+        assert!(finish(8).is_ok());
+    }
+
+    #[test]
+    fn position_too_large() {
+        let mut interner = Interner::<String>::new().with_debug_errors();
+        let state = match Interning::<str, Panic>::start_interning(
+            &mut interner,
+            USERS[0],
+        ) {
+            InterningState::Started(state) => state,
+            _ => panic!("value should have started interning"),
+        };
+
+        let error = Interning::<str, Error>::finish_interning(
+            &mut interner,
+            state,
+            usize::MAX,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains(USERS[0]));
+        assert_eq!(interner.pending_len(), 1);
+
+        let skipped =
+            interner.import_table([(USERS[1].to_string(), usize::MAX)]);
+        assert_eq!(skipped, [(USERS[1].to_string(), usize::MAX)]);
+        assert!(!interner.contains(USERS[1]));
+    }
+
+    #[test]
+    fn fixed_capacity() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..100)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let interner = Interner::with_fixed_capacity(USERS.len());
+        let (_, interner) =
+            serialize_with::<_, _, Error>(&value, interner).unwrap();
+        assert_eq!(interner.len(), USERS.len());
+
+        #[cfg(feature = "debug-checks")]
+        {
+            let interner = Interner::with_fixed_capacity(USERS.len() - 1)
+                .with_debug_errors();
+            let error = serialize_with::<_, _, Error>(&value, interner)
+                .map(|_| ())
+                .unwrap_err();
+            assert!(error.to_string().contains(USERS[USERS.len() - 1]));
+        }
+    }
+
+    #[test]
+    fn sealed_interner() {
+        use rkyv::rancor::BoxedError;
+
+        use crate::{error::SealedInterner, SealPolicy};
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let logs = |users: usize| {
+            (0..20)
+                .map(|i| Log {
+                    user: USERS[i % users].to_string(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let value = logs(3);
+        let sealed = |policy| {
+            let mut interner = Interner::<String>::new().with_debug_errors();
+            interner.preseed(USERS[0].to_string());
+            interner.preseed(USERS[1].to_string());
+            interner.seal(policy);
+            interner
+        };
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, sealed(SealPolicy::Bypass))
+                .unwrap();
+        assert!(interner.is_sealed());
+        assert_eq!(interner.len(), 2);
+        assert!(!interner.contains(USERS[2]));
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        assert!(ptr::eq(&*archived[0].user, &*archived[3].user));
+        assert!(ptr::eq(&*archived[1].user, &*archived[4].user));
+        assert!(!ptr::eq(&*archived[2].user, &*archived[5].user));
+        assert_eq!(*archived[5].user, USERS[2]);
+
+        let error = serialize_with::<_, _, BoxedError>(
+            &value,
+            sealed(SealPolicy::Fail),
+        )
+        .map(|_| ())
+        .unwrap_err();
+        assert!(error.to_string().contains(USERS[2]), "{}", error);
+        let source = BoxedError::inner(&error);
+        assert!(source.downcast_ref::<SealedInterner>().is_some());
+
+        // Known values are still deduplicated when unknown values fail.
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&logs(2), sealed(SealPolicy::Fail))
+                .unwrap();
+        assert!(interner.get_pos(USERS[0]).is_some());
+        assert!(interner.get_pos(USERS[1]).is_some());
+    }
+
+    #[test]
+    fn recorded_positions() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let users = [0, 1, 0, 2, 1, 0];
+        let value = users
+            .iter()
+            .map(|&i| Log {
+                user: USERS[i].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let interner = Interner::<String>::new()
+            .with_recorded_positions()
+            .with_last_hit_cache();
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+
+        let positions = interner.positions();
+        assert_eq!(positions.len(), value.len());
+        for (a, &i) in positions.iter().zip(users.iter()) {
+            for (b, &j) in positions.iter().zip(users.iter()) {
+                assert_eq!(a == b, i == j);
+            }
+        }
+        for (user, entry) in interner.iter() {
+            let i = USERS.iter().position(|u| u == user).unwrap();
+            let first = users.iter().position(|&u| u == i).unwrap();
+            assert_eq!(entry.pos(), Some(positions[first]));
+        }
+
+        assert!(Interner::<String>::new().positions().is_empty());
+    }
+
+    #[test]
+    fn max_value_size() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let long = "x".repeat(100);
+        let value = (0..10)
+            .map(|i| Log {
+                user: if i % 2 == 0 { USERS[0] } else { &long }.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let interner = Interner::<String>::new()
+            .with_max_value_size(USERS[0].len(), OversizePolicy::Bypass);
+        let (bytes, interner) =
+            serialize_with::<_, _, Error>(&value, interner).unwrap();
+        assert_eq!(interner.len(), 1);
+        assert!(interner.contains(USERS[0]));
+        assert!(bytes.len() > 5 * long.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        assert!(ptr::eq(&*archived[0].user, &*archived[2].user));
+        assert!(!ptr::eq(&*archived[1].user, &*archived[3].user));
+        assert_eq!(*archived[1].user, long);
+
+        let interner = Interner::<String>::new()
+            .with_max_value_size(USERS[0].len(), OversizePolicy::Fail)
+            .with_debug_errors();
+        let error = serialize_with::<_, _, Error>(&value, interner)
+            .map(|_| ())
+            .unwrap_err();
+        assert!(error.to_string().contains(&long));
+    }
+
+    #[test]
+    fn debug_errors() {
+        let mut interner = Interner::<String>::new().with_debug_errors();
+        let state = match Interning::<str, Error>::start_interning(
+            &mut interner,
+            USERS[0],
+        ) {
+            InterningState::Started(state) => state,
+            _ => panic!("value should have started interning"),
+        };
+
+        let mut finish = |pos| {
+            Interning::<str, Error>::finish_interning(&mut interner, state, pos)
+        };
+        finish(8).unwrap();
+        let error = finish(16).unwrap_err().to_string();
+        assert!(error.contains(&format!("{:?}", USERS[0])), "{}", error);
+
+        // Values which were never started are described as well.
+        let hash = interner.hasher().hash_one(USERS[1]);
+        let error = Interning::<str, Error>::finish_interning(
+            &mut interner,
+            Some((USERS[1], hash)),
+            24,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("not started"), "{}", error);
+        assert!(error.contains(&format!("{:?}", USERS[1])), "{}", error);
+    }
+
+    #[test]
+    fn pending_values() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = USERS
+            .iter()
+            .map(|user| Log {
+                user: user.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, Interner::new()).unwrap();
+        assert_eq!(interner.len(), USERS.len());
+        assert_eq!(interner.pending_len(), 0);
+
+        let mut buffer = [0; 16];
+        let mut serializer = InterningAdapter::new(
+            Serializer::new(Buffer::from(&mut buffer), (), ()),
+            Interner::<String>::new(),
+        );
+        assert!(Strategy::<_, Failure>::wrap(&mut serializer)
+            .serialize_interned(USERS[0])
+            .is_err());
+        assert_eq!(serializer.interning().pending_len(), 1);
+    }
+
+    #[test]
+    fn remove_values() {
+        let mut interner = Interner::<String>::new();
+        let state = match Interning::<str, Panic>::start_interning(
+            &mut interner,
+            USERS[0],
+        ) {
+            InterningState::Started(state) => state,
+            _ => panic!("value should have started interning"),
+        };
+        Interning::<str, Panic>::finish_interning(&mut interner, state, 8)
+            .unwrap();
+        assert!(matches!(
+            Interning::<str, Panic>::start_interning(&mut interner, USERS[1]),
+            InterningState::Started(_),
+        ));
+        interner.preseed(USERS[2].to_string());
+
+        let finished = interner.remove(USERS[0]).unwrap();
+        assert_eq!(finished.pos(), Some(8));
+        assert!(interner.remove(USERS[1]).is_none());
+        assert!(interner.remove(USERS[2]).unwrap().pos().is_none());
+        assert!(interner.remove(USERS[3]).is_none());
+        assert_eq!(interner.len(), 1);
+
+        assert!(matches!(
+            Interning::<str, Panic>::start_interning(&mut interner, USERS[0]),
+            InterningState::Started(_),
+        ));
+        assert!(matches!(
+            Interning::<str, Panic>::start_interning(&mut interner, USERS[1]),
+            InterningState::Pending,
+        ));
+    }
+
+    #[test]
+    fn precomputed_hash() {
+        let serialize = |with_hash: bool| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<16>::new(), (), ()),
+                Interner::<String>::new(),
+            );
+            let mut positions = Vec::new();
+            for i in 0..100 {
+                let user = USERS[i * 7 % USERS.len()];
+                let hash = serializer.interning().hasher().hash_one(user);
+                let serializer = Strategy::<_, Panic>::wrap(&mut serializer);
+                let pos = if with_hash {
+                    serializer.serialize_interned_with_hash(user, hash)
+                } else {
+                    serializer.serialize_interned(user)
+                };
+                positions.push(pos.unwrap());
+            }
+            let (serializer, interner) = serializer.into_components();
+            assert_eq!(interner.len(), USERS.len());
+            (serializer.into_writer().to_vec(), positions)
+        };
+
+        assert_eq!(serialize(true), serialize(false));
+    }
+
+    #[test]
+    fn bypass_values() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..100)
+            .map(|i| Log {
+                user: match i % 2 {
+                    0 => String::new(),
+                    _ => USERS[i % USERS.len()].to_string(),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let mut interner = Interner::<String>::new();
+        interner.set_bypass(String::is_empty);
+        assert!(!interner.preseed(String::new()));
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), USERS.len() / 2);
+        assert!(!interner.contains(""));
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        let positions = |empty: bool| {
+            archived
+                .iter()
+                .filter(|log| log.user.is_empty() == empty)
+                .map(|log| log.user.as_ptr())
+                .collect::<HashSet<_>>()
+                .len()
+        };
+        assert_eq!(positions(true), 50);
+        assert_eq!(positions(false), USERS.len() / 2);
+
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn policy_values() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..100)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()][..i % 3 * 20].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let min_len = |value: &String| value.len() >= 10;
+        let not_bob = |value: &String| !value.starts_with("Bob");
+        let interner = Interner::new().with_policy((min_len, not_bob));
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert!(interner.iter().all(|(value, _)| value.len() >= 10));
+        assert!(!interner.iter().any(|(value, _)| value.starts_with("Bob")));
+        assert_eq!(interner.len(), 6);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn remap_positions() {
+        let mut interner = Interner::<String>::new().with_recorded_positions();
+        for (i, user) in USERS.iter().enumerate() {
+            let state = match Interning::<str, Panic>::start_interning(
+                &mut interner,
+                user,
+            ) {
+                InterningState::Started(state) => state,
+                _ => panic!("value was already interned"),
+            };
+            // Leave the last value pending.
+            if i + 1 < USERS.len() {
+                Interning::<str, Panic>::finish_interning(
+                    &mut interner,
+                    state,
+                    i * 64,
+                )
+                .unwrap();
+            }
+        }
+
+        interner.remap_positions(|pos| pos + 16);
+        for (i, user) in USERS[..USERS.len() - 1].iter().enumerate() {
+            assert_eq!(interner.get_pos(*user), Some(i * 64 + 16));
+        }
+        assert_eq!(interner.get_pos(USERS[USERS.len() - 1]), None);
+        assert_eq!(interner.pending_len(), 1);
+        assert_eq!(interner.positions(), [16, 80, 144]);
+    }
+
+    #[test]
+    fn incremental_archive() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let logs = |users: &[&str]| {
+            users
+                .iter()
+                .map(|user| Log {
+                    user: user.to_string(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let serialize = |bytes, interner, value: &Vec<Log>| {
+            with_arena(|arena| {
+                let mut serializer = InterningAdapter::new(
+                    Serializer::new(bytes, arena.acquire(), ()),
+                    interner,
+                );
+                serialize_using::<_, Panic>(value, &mut serializer).unwrap();
+                let (serializer, interner) = serializer.into_components();
+                (serializer.into_writer(), interner)
+            })
+        };
+
+        let first = logs(&USERS[..2]);
+        let (bytes, interner) =
+            serialize(AlignedVec::<8>::new(), Interner::new(), &first);
+        let table = interner.export_table();
+        assert_eq!(table.len(), 2);
+        let first_len = bytes.len();
+
+        let mut interner = Interner::<String>::new();
+        interner.import_table(table);
+        let second = logs(&[USERS[1], USERS[2], USERS[0], USERS[2]]);
+        let (bytes, interner) = serialize(bytes, interner, &second);
+        assert_eq!(interner.len(), 3);
+
+        let first_archived = unsafe {
+            access_unchecked::<Archived<Vec<Log>>>(&bytes[..first_len])
+        };
+        let second_archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        for (a, b) in second_archived.iter().zip(second.iter()) {
+            assert_eq!(*a.user, b.user);
+        }
+        assert!(ptr::eq(&*second_archived[0].user, &*first_archived[1].user));
+        assert!(ptr::eq(&*second_archived[2].user, &*first_archived[0].user));
+        assert!(ptr::eq(&*second_archived[1].user, &*second_archived[3].user));
+        let new_pos = second_archived[1].user.as_ptr() as usize
+            - bytes.as_ptr() as usize;
+        assert!(new_pos >= first_len);
+    }
+
+    #[test]
+    #[cfg(feature = "debug-checks")]
+    fn inconsistent_hash() {
+        use core::hash::{BuildHasherDefault, Hash, Hasher};
+
+        // Keys are equal if they have the same tens digit, but hash their
+        // whole value.
+        #[derive(Archive, Serialize, Clone, Debug)]
+        struct Key(u32);
+
+        impl PartialEq for Key {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 / 10 == other.0 / 10
+            }
+        }
+
+        impl Eq for Key {}
+
+        impl Hash for Key {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                state.write_u32(self.0);
+            }
+        }
+
+        // Shifts hashes so that small keys collide in the table and are
+        // compared with each other.
+        #[derive(Default)]
+        struct ShiftHasher(u64);
+
+        impl Hasher for ShiftHasher {
+            fn write(&mut self, bytes: &[u8]) {
+                for &byte in bytes {
+                    self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+                }
+            }
+
+            fn write_u32(&mut self, value: u32) {
+                self.0 = u64::from(value) << 32;
+            }
+
+            fn finish(&self) -> u64 {
+                self.0
+            }
+        }
+
+        #[derive(Archive, Serialize)]
+        struct Record {
+            #[rkyv(with = Intern)]
+            key: Key,
+        }
+
+        let value = [Record { key: Key(11) }, Record { key: Key(12) }];
+        let interner = Interner::<Key, _>::with_hasher(
+            BuildHasherDefault::<ShiftHasher>::default(),
+        )
+        .with_debug_errors();
+        let error = serialize_with::<_, _, Error>(&value, interner)
+            .map(|_| ())
+            .unwrap_err();
+        assert!(error.to_string().contains("Key(12)"));
+    }
+
+    #[test]
+    #[cfg(feature = "debug-checks")]
+    fn duplicate_position() {
+        fn intern(
+            interner: &mut Interner<String>,
+            value: &str,
+            pos: usize,
+        ) -> Result<(), Error> {
+            match Interning::<str, Error>::start_interning(interner, value) {
+                InterningState::Started(state) => {
+                    Interning::<str, Error>::finish_interning(
+                        interner, state, pos,
+                    )
+                }
+                _ => panic!("value was already interned"),
+            }
+        }
+
+        let mut interner = Interner::<String>::new().with_debug_errors();
+        intern(&mut interner, USERS[0], 8).unwrap();
+        intern(&mut interner, USERS[1], 16).unwrap();
+
+        // Finishing the same value at the same position again is fine.
+        let hash = interner.hasher().hash_one(USERS[0]);
+        Interning::<str, Error>::finish_interning(
+            &mut interner,
+            Some((USERS[0], hash)),
+            8,
+        )
+        .unwrap();
+
+        let error = intern(&mut interner, USERS[2], 8).unwrap_err();
+        assert!(error.to_string().contains(USERS[2]));
+        assert!(error.to_string().contains("position 8"));
+
+        interner.set_bypass(|value| value == USERS[3]);
+        assert!(intern(&mut interner, USERS[3], 16).is_err());
+        intern(&mut interner, USERS[3], 24).unwrap();
+
+        // Positions of removed and replaced values can be used again.
+        interner.remove(USERS[0]).unwrap();
+        intern(&mut interner, "Dave", 8).unwrap();
+        interner.import_table([("Dave".to_string(), 32)]);
+        intern(&mut interner, USERS[0], 8).unwrap();
+
+        // Remapped positions are freed at their old positions.
+        interner.remap_positions(|pos| pos + 100);
+        intern(&mut interner, "Eve", 16).unwrap();
+        assert!(intern(&mut interner, "Frank", 124).is_err());
+
+        interner.clear();
+        intern(&mut interner, USERS[2], 8).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "debug-checks")]
+    fn hash_collisions() {
+        use core::hash::{BuildHasherDefault, Hasher};
+
+        // Hashes every value to the same bucket.
+        #[derive(Default)]
+        struct ConstantHasher;
+
+        impl Hasher for ConstantHasher {
+            fn write(&mut self, _: &[u8]) {}
+
+            fn finish(&self) -> u64 {
+                0
+            }
+        }
+
+        #[derive(Archive, Serialize)]
+        struct Name(#[rkyv(with = Intern)] String);
+
+        let value = (0..100).map(|i| Name(i.to_string())).collect::<Vec<_>>();
+        let interner = Interner::<String, _>::with_hasher(
+            BuildHasherDefault::<ConstantHasher>::default(),
+        );
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        // Each new value is compared with every value before it.
+        assert!(interner.collision_count() >= 100 * 99 / 2);
+
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, Interner::<String>::new())
+                .unwrap();
+        assert!(interner.collision_count() < 100);
+    }
+
+    #[test]
+    fn interner_from_map() {
+        use hashbrown::HashMap;
+
+        use crate::Entry;
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..10)
+            .map(|i| Log {
+                user: USERS[i % 2].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut map = HashMap::<String, Entry>::new();
+        map.insert(USERS[0].to_string(), Entry::preseeded());
+        map.insert(USERS[2].to_string(), Entry::preseeded());
+        let interner = Interner::from_map(map);
+        assert_eq!(interner.len(), 2);
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        let expected = serialize_with::<_, _, Panic>(&value, Interner::new())
+            .unwrap()
+            .0;
+        assert_eq!(bytes[..], expected[..]);
+
+        let map = interner.into_map();
+        assert_eq!(map.len(), 3);
+        assert!(map[USERS[0]].pos().is_some());
+        assert!(map[USERS[1]].pos().is_some());
+        assert!(map[USERS[2]].pos().is_none());
+    }
+
+    #[test]
+    fn construct_entries() {
+        use core::{hash::BuildHasher, num::NonZeroU32};
+
+        use hashbrown::HashMap;
+
+        use crate::{Entry, InterningState};
+
+        let finished: Entry = Entry::finished(24).unwrap();
+        assert_eq!(finished.pos(), Some(24));
+        assert!(!finished.is_pending());
+        let pending: Entry = Entry::pending();
+        assert_eq!(pending.pos(), None);
+        assert!(pending.is_pending());
+        assert!(Entry::<NonZeroU32>::finished(u32::MAX as usize).is_none());
+
+        let mut map = HashMap::<String, Entry>::new();
+        map.insert(USERS[0].to_string(), finished);
+        map.insert(USERS[1].to_string(), pending);
+        map.insert(USERS[2].to_string(), Entry::preseeded());
+        let mut interner = Interner::from_map(map);
+        assert_eq!(interner.get_pos(USERS[0]), Some(24));
+        assert_eq!(interner.get_pos(USERS[1]), None);
+        assert_eq!(interner.get_pos(USERS[2]), None);
+        assert_eq!(interner.pending_len(), 1);
+
+        assert!(matches!(
+            Interning::<str, Panic>::start_interning(&mut interner, USERS[1]),
+            InterningState::Pending,
+        ));
+        let hash = interner.hasher().hash_one(USERS[1]);
+        Interning::<str, Panic>::finish_interning(
+            &mut interner,
+            Some((USERS[1], hash)),
+            48,
+        )
+        .unwrap();
+        assert_eq!(interner.get_pos(USERS[1]), Some(48));
+        assert_eq!(interner.pending_len(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn observer_events() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::InternEvent;
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = [0, 1, 0, 2, 1]
+            .iter()
+            .map(|&i| Log {
+                user: USERS[i].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut interner = Interner::<String>::new().with_recorded_positions();
+        interner.preseed(USERS[3].to_string());
+        interner.set_bypass(|user| user == USERS[2]);
+        let observed = Arc::clone(&events);
+        interner.set_observer(move |event: InternEvent<&String>| {
+            let event = match event {
+                InternEvent::Started(user) => (0, user.clone(), None),
+                InternEvent::Hit(user, pos) => (1, user.clone(), Some(pos)),
+                InternEvent::Finished(user, pos) => {
+                    (2, user.clone(), Some(pos))
+                }
+            };
+            observed.lock().unwrap().push(event);
+        });
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+
+        let positions = interner.positions();
+        let (alice, bob) = (positions[0], positions[1]);
+        let user = |i: usize| USERS[i].to_string();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                (0, user(0), None),
+                (2, user(0), Some(alice)),
+                (0, user(1), None),
+                (2, user(1), Some(bob)),
+                (1, user(0), Some(alice)),
+                (1, user(1), Some(bob)),
+            ],
+        );
+    }
+
+    #[test]
+    fn narrow_positions() {
+        use core::{mem::size_of, num::NonZeroU32};
+
+        use hashbrown::DefaultHashBuilder;
+
+        use crate::Entry;
+
+        type NarrowInterner = Interner<String, DefaultHashBuilder, NonZeroU32>;
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        assert!(size_of::<Entry<NonZeroU32>>() <= size_of::<Entry>());
+
+        let value = (0..10)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+            })
+            .collect::<Vec<_>>();
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, NarrowInterner::default())
+                .unwrap();
+        assert_eq!(interner.len(), USERS.len());
+        let expected = serialize_with::<_, _, Panic>(&value, Interner::new())
+            .unwrap()
+            .0;
+        assert_eq!(bytes[..], expected[..]);
+
+        let mut interner = NarrowInterner::default();
+        for (value, pos) in [("last", u32::MAX - 1), ("too large", u32::MAX)] {
+            let state = Interning::<str, Error>::start_interning(
+                &mut interner,
+                value,
+            );
+            let state = match state {
+                InterningState::Started(state) => state,
+                _ => panic!("value was already interned"),
+            };
+            let result = Interning::<str, Error>::finish_interning(
+                &mut interner,
+                state,
+                pos as usize,
+            );
+            assert_eq!(result.is_ok(), pos < u32::MAX);
+        }
+        assert_eq!(
+            interner.iter().find_map(|(_, entry)| entry.pos()),
+            Some(u32::MAX as usize - 1),
+        );
+    }
+
+    #[test]
+    fn narrow_positions_import_table() {
+        use core::num::NonZeroU32;
+
+        use hashbrown::DefaultHashBuilder;
+
+        let table = [
+            (USERS[0].to_string(), 0),
+            (USERS[1].to_string(), u32::MAX as usize - 1),
+            (USERS[2].to_string(), u32::MAX as usize),
+            (USERS[3].to_string(), usize::MAX),
+        ];
+        let mut interner =
+            Interner::<String, DefaultHashBuilder, NonZeroU32>::default();
+        let skipped = interner.import_table(table.iter().cloned());
+        assert_eq!(skipped, table[2..]);
+
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get_pos(USERS[0]), Some(0));
+        assert_eq!(interner.get_pos(USERS[1]), Some(u32::MAX as usize - 1));
+        assert!(!interner.contains(USERS[2]));
+        assert!(!interner.contains(USERS[3]));
+    }
+
+    #[test]
+    fn iter_by_position() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = [3, 1, 3, 0, 2, 1]
+            .iter()
+            .map(|&i| Log {
+                user: USERS[i].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut interner = Interner::<String>::new();
+        interner.preseed("unused".to_string());
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+
+        let users = interner
+            .iter_by_position()
+            .map(|(user, _)| user.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(users, [USERS[3], USERS[1], USERS[0], USERS[2]]);
+        assert!(interner
+            .iter_by_position()
+            .zip(interner.iter_by_position().skip(1))
+            .all(|((_, a), (_, b))| a < b));
+    }
+
+    #[test]
+    fn last_hit_cache() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: USERS[i / 100 % USERS.len()].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let serialize = |interner| {
+            serialize_with::<_, _, Panic>(&value, interner).unwrap().0.to_vec()
+        };
+        assert_eq!(
+            serialize(Interner::new().with_last_hit_cache()),
+            serialize(Interner::new()),
+        );
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn last_hit_cache_ref_counts() {
+        use alloc::collections::BTreeMap;
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: USERS[i / 100 % USERS.len()].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let histogram = |interner: Interner<String>| {
+            let (_, interner) =
+                serialize_with::<_, _, Panic>(&value, interner).unwrap();
+            interner.ref_count_histogram()
+        };
+        let expected = BTreeMap::from([(200, 2), (300, 2)]);
+        assert_eq!(histogram(Interner::new()), expected);
+        assert_eq!(histogram(Interner::new().with_last_hit_cache()), expected);
+    }
+
+    #[test]
+    fn last_hit_cache_invalidation() {
+        fn intern(
+            interner: &mut Interner<String>,
+            value: &str,
+            pos: usize,
+        ) -> Option<usize> {
+            match Interning::<str, Panic>::start_interning(interner, value) {
+                InterningState::Started(state) => {
+                    Interning::<str, Panic>::finish_interning(
+                        interner, state, pos,
+                    )
+                    .unwrap();
+                    None
+                }
+                InterningState::Pending => panic!("value is still pending"),
+                InterningState::Finished(pos) => Some(pos),
+            }
+        }
+
+        let mut interner = Interner::<String>::new().with_last_hit_cache();
+
+        assert_eq!(intern(&mut interner, USERS[0], 0), None);
+        assert_eq!(intern(&mut interner, USERS[0], 8), Some(0));
+        assert_eq!(intern(&mut interner, USERS[0], 8), Some(0));
+        assert!(interner.last_hit.is_some());
+        assert_eq!(intern(&mut interner, USERS[1], 16), None);
+        assert_eq!(intern(&mut interner, USERS[0], 24), Some(0));
+
+        assert!(interner.remove(USERS[0]).is_some());
+        assert_eq!(intern(&mut interner, USERS[0], 32), None);
+        assert_eq!(intern(&mut interner, USERS[0], 40), Some(32));
+
+        interner.import_table([(USERS[0].to_string(), 48)]);
+        assert_eq!(intern(&mut interner, USERS[0], 56), Some(48));
+
+        interner.clear();
+        assert_eq!(intern(&mut interner, USERS[0], 64), None);
+        assert_eq!(intern(&mut interner, USERS[0], 72), Some(64));
+    }
+
+    #[test]
+    fn deterministic_output() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: USERS[i * 7 % USERS.len()].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let serialize = |interner| {
+            serialize_with::<_, _, Panic>(&value, interner).unwrap().0.to_vec()
+        };
+        let users = || USERS.iter().map(|user| user.to_string());
+        let expected = serialize(Interner::new());
+        assert_eq!(serialize(Interner::new()), expected);
+        assert_eq!(serialize(users().collect()), expected);
+        assert_eq!(serialize(users().rev().collect()), expected);
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn saturating_ref_cnt() {
+        use crate::Entry;
+
+        let mut entry: Entry = Entry::pending();
+        entry.ref_cnt = usize::MAX - 1;
+        for _ in 0..3 {
+            entry.restart(());
+        }
+        assert_eq!(entry.ref_cnt, usize::MAX);
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn ref_count_histogram() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let counts = [1, 1, 3, 10];
+        let value = USERS
+            .iter()
+            .zip(counts)
+            .flat_map(|(user, count)| (0..count).map(move |_| *user))
+            .map(|user| Log {
+                user: user.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut interner = Interner::<String>::new();
+        interner.preseed("unused".to_string());
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+
+        let histogram = interner.ref_count_histogram();
+        assert!(histogram.into_iter().eq([(0, 1), (1, 2), (3, 1), (10, 1)]));
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn bump_ref() {
+        let counts = [1, 1, 3, 10];
+        let users = USERS
+            .iter()
+            .zip(counts)
+            .flat_map(|(user, count)| (0..count).map(move |_| *user))
+            .collect::<Vec<_>>();
+
+        let mut interner =
+            Interner::<String>::new().with_policy(|user: &String| {
+                !user.is_empty()
+            });
+        for user in users.iter() {
+            assert!(interner.bump_ref(*user).is_some());
+        }
+        assert_eq!(interner.bump_ref(""), None);
+
+        let histogram = interner.ref_count_histogram();
+        assert!(histogram.into_iter().eq([(1, 2), (3, 1), (10, 1)]));
+        assert_eq!(interner.len(), 4);
+        assert_eq!(interner.pending_len(), 0);
+        assert!(USERS.iter().all(|user| interner.get_pos(*user).is_none()));
+
+        // Serializing with the same interner counts every value again.
+        let mut adapter = InterningAdapter::new(
+            Serializer::new(AlignedVec::<8>::new(), (), ()),
+            interner,
+        );
+        for user in users.iter() {
+            adapter.serialize_interned::<str, Panic>(user).unwrap();
+        }
+        let interner = adapter.into_components().1;
+        let histogram = interner.ref_count_histogram();
+        assert!(histogram.into_iter().eq([(2, 2), (6, 1), (20, 1)]));
+    }
+
+    #[test]
+    #[cfg(feature = "statistics")]
+    fn warm_from_stats() {
+        use crate::InternerSnapshot;
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = [0, 1, 1, 2, 3, 3, 3]
+            .iter()
+            .map(|&i| Log {
+                user: USERS[i].to_string(),
+            })
+            .collect::<Vec<_>>();
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, Interner::new()).unwrap();
+        let snapshot = interner.stats_snapshot();
+        assert_eq!(snapshot.ref_counts.len(), 4);
+        assert_eq!(snapshot.ref_counts[0], (USERS[3].to_string(), 3));
+        assert_eq!(snapshot.ref_counts[1], (USERS[1].to_string(), 2));
+
+        // Save the snapshot and restore it in a new interner.
+        let bytes = rkyv::to_bytes::<Panic>(&snapshot).unwrap();
+        let archived = unsafe {
+            access_unchecked::<Archived<InternerSnapshot<String>>>(&bytes)
+        };
+        let snapshot =
+            deserialize::<InternerSnapshot<String>, Panic>(archived).unwrap();
+        let mut interner = Interner::<String>::new();
+        assert_eq!(interner.warm_from_stats(snapshot), 2);
+        assert_eq!(interner.len(), 2);
+        assert!(interner.contains(USERS[1]) && interner.contains(USERS[3]));
+        assert_eq!(interner.get_pos(USERS[3]), None);
+        assert_eq!(interner.pending_len(), 0);
+
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), 4);
+        assert!(interner.get_pos(USERS[3]).is_some());
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn singleton_count() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = [0, 1, 1, 2, 3, 3, 3]
+            .iter()
+            .map(|&i| Log {
+                user: USERS[i].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut interner = Interner::<String>::new();
+        assert_eq!(interner.singleton_count(), 0);
+        interner.preseed("unused".to_string());
+        let (_, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.singleton_count(), 2);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut interner = Interner::<String>::new();
+        assert_eq!(interner.capacity(), 0);
+
+        interner.reserve(100);
+        let reserved = interner.capacity();
+        assert!(reserved >= 100);
+
+        interner.extend(USERS.iter().map(|user| user.to_string()));
+        assert!(interner.capacity() >= interner.len());
+        assert_eq!(interner.capacity(), reserved);
+
+        interner.clear();
+        assert_eq!(interner.capacity(), reserved);
+
+        interner.preseed(USERS[0].to_string());
+        interner.shrink_to_fit();
+        assert!(interner.capacity() >= interner.len());
+        assert!(interner.capacity() < reserved);
+    }
+
+    #[test]
+    fn memory_usage() {
+        let mut interner = Interner::<String>::new();
+        assert_eq!(interner.memory_usage(), 0);
+
+        interner.extend(USERS.iter().map(|user| user.to_string()));
+        let table_size = interner.memory_usage()
+            - USERS.iter().map(|user| user.len()).sum::<usize>();
+        assert!(table_size > 0);
+
+        interner.preseed("x".repeat(1000));
+        assert!(interner.memory_usage() >= table_size + 1000);
+
+        interner.clear();
+        assert!(interner.memory_usage() > 0);
+        assert!(interner.memory_usage() < 1000);
+
+        interner.shrink_to_fit();
+        assert_eq!(interner.memory_usage(), 0);
+    }
+
+    fn hash_ignore_case(value: &str) -> u64 {
+        value.chars().fold(0, |hash, c| {
+            hash.rotate_left(5) ^ u64::from(c.to_ascii_lowercase())
+        })
+    }
+
+    #[test]
+    fn custom_equality() {
+        use crate::{DerefIntern, SealPolicy};
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = DerefIntern)]
+            user: String,
+        }
+
+        let users = ["Alice", "ALICE", "alice", "Bob", "BOB", "Carol"];
+        let value = users
+            .iter()
+            .map(|user| Log {
+                user: user.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut interner =
+            Interner::<String, _>::with_eq(hash_ignore_case, |a, b| {
+                str::eq_ignore_ascii_case(a, b)
+            })
+            .with_debug_errors();
+        interner.preseed("alice".to_string());
+        interner.preseed("bob".to_string());
+        interner.seal(SealPolicy::Bypass);
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), 2);
+        assert!(interner.contains("aLiCe"));
+        assert!(!interner.contains("carol"));
+
+        // The first value interned is archived, not the preseeded one.
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        assert!(archived.iter().take(3).all(|log| &*log.user == "Alice"));
+        assert!(archived
+            .iter()
+            .skip(3)
+            .take(2)
+            .all(|log| &*log.user == "Bob"));
+        assert!(ptr::eq(&*archived[0].user, &*archived[2].user));
+        assert_eq!(&*archived[5].user, "Carol");
+    }
+
+    #[test]
+    fn custom_equality_keeps_lengths_apart() {
+        use crate::DerefIntern;
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = DerefIntern)]
+            user: String,
+        }
+
+        fn hash(value: &str) -> u64 {
+            value
+                .trim_end()
+                .bytes()
+                .fold(0, |hash, byte| hash.rotate_left(5) ^ u64::from(byte))
+        }
+
+        // Equates values of different lengths.
+        fn eq(a: &str, b: &str) -> bool {
+            a.trim_end() == b.trim_end()
+        }
+
+        let users = ["ab", "ab      ", "ab", "ab  ", "ab      "];
+        let value = users
+            .iter()
+            .map(|user| Log {
+                user: user.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) = serialize_with::<_, _, Panic>(
+            &value,
+            Interner::<String, _>::with_eq(hash, eq),
+        )
+        .unwrap();
+        assert_eq!(interner.len(), 3);
+        assert!(interner.contains("ab  "));
+        assert!(!interner.contains("ab "));
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        for (log, user) in archived.iter().zip(users.iter()) {
+            assert_eq!(&*log.user, *user);
+        }
+        assert!(ptr::eq(&*archived[1].user, &*archived[4].user));
+    }
+
+    #[test]
+    fn custom_equality_follows_contract() {
+        use crate::testing::check_interning_contract;
+
+        let mut interner =
+            Interner::<String, _>::with_eq(hash_ignore_case, |a, b| {
+                str::eq_ignore_ascii_case(a, b)
+            });
+        check_interning_contract::<_, Panic>(&mut interner);
+    }
+}
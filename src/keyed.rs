@@ -0,0 +1,378 @@
+use core::hash::Hash;
+
+use hashbrown::{hash_map, HashMap};
+
+use crate::{
+    error::NotStarted, DerefIntern, Entry, InternFlavor, Interning,
+    InterningError, InterningState,
+};
+
+/// A value interner which identifies values by a key.
+///
+/// This works like [`Interner`](crate::Interner), but doesn't store the values
+/// it interns. Instead, it calls `key` on each value and stores the key, so it
+/// can intern values which don't implement `Hash`, `Eq`, or `ToOwned`. This
+/// includes slices of floats and trait objects, which are interned behind a
+/// pointer with [`DynIntern`].
+///
+/// Values with equal keys share the value which was interned first. Each
+/// pointer to a shared value is archived with the metadata of the value it
+/// replaced, like the length of a slice or the type of a trait object, so
+/// constructing a `KeyedInterner` is unsafe. See [`KeyedInterner::new`] for
+/// the requirements on `key`.
+///
+/// # Example
+///
+/// ```
+/// use rkyv_intern::KeyedInterner;
+///
+/// fn key(value: &[f32]) -> Vec<u32> {
+///     value.iter().map(|x| x.to_bits()).collect()
+/// }
+///
+/// // SAFETY: The key contains every element, so slices with equal keys have
+/// // the same length.
+/// let interner = unsafe { KeyedInterner::<[f32], _>::new(key) };
+/// ```
+pub struct KeyedInterner<T: ?Sized, K> {
+    entries: HashMap<K, Entry>,
+    key: fn(&T) -> K,
+}
+
+impl<T: ?Sized, K> KeyedInterner<T, K> {
+    /// Returns a new, empty interner which identifies values with `key`.
+    ///
+    /// # Safety
+    ///
+    /// `key` must return different keys for values with different metadata,
+    /// and should return different keys for values which archive differently.
+    /// Two values with different metadata but the same key make one pointer
+    /// claim the wrong length or type for its data, and accessing it is
+    /// undefined behavior.
+    ///
+    /// For trait objects, this means the key must identify the concrete type,
+    /// for example with a type tag or `TypeId`, and not only the data.
+    pub unsafe fn new(key: fn(&T) -> K) -> Self {
+        Self {
+            entries: HashMap::new(),
+            key,
+        }
+    }
+
+    /// The number of interned values.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the interner contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The keys of the interned values and their entries.
+    pub fn iter(&self) -> hash_map::Iter<'_, K, Entry> {
+        self.entries.iter()
+    }
+
+    /// Returns `true` if the interner contains a value with the same key as
+    /// `value`.
+    pub fn contains(&self, value: &T) -> bool
+    where
+        K: Hash + Eq,
+    {
+        self.entries.contains_key(&(self.key)(value))
+    }
+}
+
+impl<T, K, E> Interning<T, E> for KeyedInterner<T, K>
+where
+    T: ?Sized,
+    K: Hash + Eq,
+    E: InterningError,
+{
+    type State<'a> = &'a T where T: 'a;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a T,
+    ) -> InterningState<Self::State<'a>> {
+        match self.entries.entry((self.key)(value)) {
+            hash_map::Entry::Occupied(mut entry) => {
+                entry.get_mut().restart(value)
+            }
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(Entry::pending());
+                InterningState::Started(value)
+            }
+        }
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        match self.entries.get_mut(&(self.key)(state)) {
+            Some(entry) => entry.finish(pos, || None),
+            None => Err(E::new(NotStarted(None))),
+        }
+    }
+}
+
+/// A wrapper that interns trait objects behind a pointer.
+///
+/// This is another name for [`DerefIntern`], which interns the target of any
+/// pointer that implements `ArchiveUnsized`. A `Box<dyn Trait>` is serialized
+/// with `SerializeUnsized` for `dyn Trait`, archived as an `ArchivedRc` of the
+/// archived trait object, and deserialized into a new `Box` through
+/// `DeserializeUnsized` and the archived value's vtable. `dyn Trait` also has
+/// to implement `LayoutRaw` so the `Box` can be allocated.
+///
+/// Trait objects don't implement `Hash` or `Eq`, so they're interned with a
+/// [`KeyedInterner`] whose key identifies both the concrete type and the
+/// value.
+pub type DynIntern<F = InternFlavor> = DerefIntern<F>;
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, string::String, vec, vec::Vec};
+    use core::{
+        alloc::{Layout, LayoutError},
+        ptr,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize,
+        ptr_meta::{self, DynMetadata, Pointee},
+        rancor::{Fallible, Panic, Strategy},
+        ser::Writer,
+        traits::{ArchivePointee, LayoutRaw},
+        Archive, ArchiveUnsized, Archived, ArchivedMetadata, Deserialize,
+        DeserializeUnsized, Portable, Serialize, SerializeUnsized,
+    };
+
+    use crate::{
+        testing::check_interning_contract, tests::serialize_with, DerefIntern,
+        DynIntern, KeyedInterner,
+    };
+
+    const CIRCLE: u8 = 0;
+    const SQUARE: u8 = 1;
+
+    trait Shape {
+        fn key(&self) -> (u8, u32);
+
+        fn serialize_shape(
+            &self,
+            serializer: &mut Strategy<dyn Writer<Panic> + '_, Panic>,
+        ) -> Result<usize, Panic>;
+    }
+
+    // SAFETY: The metadata of a trait object is its vtable.
+    unsafe impl Pointee for dyn Shape {
+        type Metadata = DynMetadata<dyn Shape>;
+    }
+
+    impl LayoutRaw for dyn Shape {
+        fn layout_raw(
+            metadata: DynMetadata<dyn Shape>,
+        ) -> Result<Layout, LayoutError> {
+            Ok(metadata.layout())
+        }
+    }
+
+    impl ArchiveUnsized for dyn Shape {
+        type Archived = dyn ArchivedShape;
+
+        fn archived_metadata(&self) -> ArchivedMetadata<Self> {
+            self.key().0
+        }
+    }
+
+    impl<S> SerializeUnsized<S> for dyn Shape
+    where
+        S: Writer<Panic> + Fallible<Error = Panic>,
+    {
+        fn serialize_unsized(
+            &self,
+            serializer: &mut S,
+        ) -> Result<usize, Panic> {
+            self.serialize_shape(Strategy::wrap(
+                serializer as &mut dyn Writer<Panic>,
+            ))
+        }
+    }
+
+    trait ArchivedShape {
+        fn key(&self) -> (u8, u32);
+
+        fn shape_metadata(&self) -> DynMetadata<dyn Shape>;
+
+        unsafe fn deserialize_shape(&self, out: *mut dyn Shape);
+    }
+
+    // SAFETY: The metadata of a trait object is its vtable.
+    unsafe impl Pointee for dyn ArchivedShape {
+        type Metadata = DynMetadata<dyn ArchivedShape>;
+    }
+
+    // SAFETY: `ArchivedShape` is only implemented by archived types.
+    unsafe impl Portable for dyn ArchivedShape {}
+
+    impl ArchivePointee for dyn ArchivedShape {
+        type ArchivedMetadata = u8;
+
+        fn pointer_metadata(tag: &u8) -> DynMetadata<dyn ArchivedShape> {
+            let ptr: *const dyn ArchivedShape = match *tag {
+                CIRCLE => ptr::null::<ArchivedCircle>(),
+                SQUARE => ptr::null::<ArchivedSquare>(),
+                _ => unreachable!(),
+            };
+            ptr_meta::metadata(ptr)
+        }
+    }
+
+    impl<D: Fallible + ?Sized> DeserializeUnsized<dyn Shape, D>
+        for dyn ArchivedShape
+    {
+        unsafe fn deserialize_unsized(
+            &self,
+            _: &mut D,
+            out: *mut dyn Shape,
+        ) -> Result<(), D::Error> {
+            unsafe { self.deserialize_shape(out) };
+            Ok(())
+        }
+
+        fn deserialize_metadata(&self) -> DynMetadata<dyn Shape> {
+            self.shape_metadata()
+        }
+    }
+
+    macro_rules! shape {
+        ($ty:ident, $archived:ident, $tag:ident) => {
+            #[derive(Archive, Serialize)]
+            struct $ty(u32);
+
+            impl Shape for $ty {
+                fn key(&self) -> (u8, u32) {
+                    ($tag, self.0)
+                }
+
+                fn serialize_shape(
+                    &self,
+                    serializer: &mut Strategy<dyn Writer<Panic> + '_, Panic>,
+                ) -> Result<usize, Panic> {
+                    self.serialize_unsized(serializer)
+                }
+            }
+
+            impl ArchivedShape for $archived {
+                fn key(&self) -> (u8, u32) {
+                    ($tag, self.0.to_native())
+                }
+
+                fn shape_metadata(&self) -> DynMetadata<dyn Shape> {
+                    ptr_meta::metadata(ptr::null::<$ty>() as *const dyn Shape)
+                }
+
+                unsafe fn deserialize_shape(&self, out: *mut dyn Shape) {
+                    unsafe { out.cast::<$ty>().write($ty(self.0.to_native())) }
+                }
+            }
+        };
+    }
+
+    shape!(Circle, ArchivedCircle, CIRCLE);
+    shape!(Square, ArchivedSquare, SQUARE);
+
+    #[derive(Archive, Serialize, Deserialize)]
+    struct Drawing {
+        #[rkyv(with = DynIntern)]
+        shape: Box<dyn Shape>,
+    }
+
+    #[test]
+    fn keyed_interner_trait_objects() {
+        let value = vec![
+            Box::new(Circle(1)) as Box<dyn Shape>,
+            Box::new(Square(1)),
+            Box::new(Circle(1)),
+            Box::new(Square(2)),
+            Box::new(Square(1)),
+            Box::new(Circle(2)),
+        ]
+        .into_iter()
+        .map(|shape| Drawing { shape })
+        .collect::<Vec<_>>();
+
+        // SAFETY: The key starts with a tag for the concrete type.
+        let interner =
+            unsafe { KeyedInterner::<dyn Shape, _>::new(Shape::key) };
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), 4);
+        assert!(interner.contains(&Circle(2)));
+        assert!(!interner.contains(&Square(3)));
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Drawing>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.shape.key(), b.shape.key());
+        }
+        assert!(ptr::addr_eq(&*archived[0].shape, &*archived[2].shape));
+        assert!(ptr::addr_eq(&*archived[1].shape, &*archived[4].shape));
+        assert!(!ptr::addr_eq(&*archived[0].shape, &*archived[1].shape));
+
+        let deserialized =
+            deserialize::<Vec<Drawing>, Panic>(archived).unwrap();
+        for (a, b) in deserialized.iter().zip(value.iter()) {
+            assert_eq!(a.shape.key(), b.shape.key());
+        }
+    }
+
+    #[test]
+    fn keyed_interner_unhashable_values() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Reading {
+            #[rkyv(with = DerefIntern)]
+            samples: Box<[f32]>,
+        }
+
+        fn key(value: &[f32]) -> Vec<u32> {
+            value.iter().map(|x| x.to_bits()).collect()
+        }
+
+        let value = (0..20)
+            .map(|i| Reading {
+                samples: (0..i % 4).map(|x| x as f32 / 2.0).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        // SAFETY: The key contains every sample, so slices with equal keys
+        // have the same length.
+        let interner = unsafe { KeyedInterner::<[f32], _>::new(key) };
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, interner).unwrap();
+        assert_eq!(interner.len(), 4);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Reading>>>(&bytes) };
+        assert!(core::ptr::eq(
+            archived[1].samples.as_ptr(),
+            archived[5].samples.as_ptr(),
+        ));
+        let deserialized =
+            deserialize::<Vec<Reading>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn keyed_interner_follows_contract() {
+        let key = |value: &str| String::from(value);
+        // SAFETY: `str` has no metadata besides its length, which is part of
+        // the key.
+        let mut interner = unsafe { KeyedInterner::new(key) };
+        check_interning_contract::<_, Panic>(&mut interner);
+    }
+}
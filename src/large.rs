@@ -0,0 +1,184 @@
+use core::mem::size_of;
+
+use rkyv::{
+    rancor::Fallible,
+    ser::Writer,
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    Archive, Deserialize, Place, Serialize,
+};
+
+use crate::{
+    ArchivedMaybeInterned, Interning, InterningError, InterningExt as _,
+    MaybeInternedResolver,
+};
+
+/// A wrapper that only interns values whose archived type is larger than `N`
+/// bytes.
+///
+/// Every interned value is accessed through a relative pointer, which costs
+/// more than storing small values like enums and integers inline. Since the
+/// decision only depends on the size of `T::Archived`, every value of a type is
+/// either stored inline or interned, and values which are stored inline are
+/// never added to the interner. This lets `InternLarge` be applied to fields of
+/// any type, for example by a macro, without paying for indirection on small
+/// ones.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::InternLarge;
+///
+/// #[derive(Archive)]
+/// enum Status {
+///     Active,
+///     Inactive,
+/// }
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     // Archived inline.
+///     #[rkyv(with = InternLarge<8>)]
+///     status: Status,
+///     // Interned.
+///     #[rkyv(with = InternLarge<8>)]
+///     checksum: [u64; 4],
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InternLarge<const N: usize>;
+
+impl<const N: usize> InternLarge<N> {
+    /// Returns `true` if values of type `T` are interned.
+    pub const fn interns<T: Archive>() -> bool {
+        size_of::<T::Archived>() > N
+    }
+}
+
+impl<T, const N: usize> ArchiveWith<T> for InternLarge<N>
+where
+    T: Archive,
+{
+    type Archived = ArchivedMaybeInterned<T::Archived, T::Archived>;
+    type Resolver = MaybeInternedResolver<T::Resolver>;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedMaybeInterned::resolve_from_parts(field, field, resolver, out);
+    }
+}
+
+impl<T, S, const N: usize> SerializeWith<T, S> for InternLarge<N>
+where
+    T: Serialize<S>,
+    S: Interning<T> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        if Self::interns::<T>() {
+            let resolver = serializer.resolve_interned(field)?;
+            Ok(MaybeInternedResolver::Interned(resolver))
+        } else {
+            Ok(MaybeInternedResolver::Inline(field.serialize(serializer)?))
+        }
+    }
+}
+
+impl<T, D, const N: usize>
+    DeserializeWith<ArchivedMaybeInterned<T::Archived, T::Archived>, T, D>
+    for InternLarge<N>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize_with(
+        field: &ArchivedMaybeInterned<T::Archived, T::Archived>,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        field.get().deserialize(deserializer)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{tests::serialize_with, InternLarge, Interner, MultiInterner};
+
+    #[derive(
+        Archive, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+        Hash,
+    )]
+    enum Status {
+        Active,
+        Inactive,
+    }
+
+    #[derive(
+        Archive, Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash,
+    )]
+    struct Location {
+        latitude: i64,
+        longitude: i64,
+        altitude: i64,
+    }
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Device {
+        #[rkyv(with = InternLarge<8>)]
+        status: Status,
+        #[rkyv(with = InternLarge<8>)]
+        location: Location,
+    }
+
+    #[test]
+    fn intern_large_values() {
+        assert!(!InternLarge::<8>::interns::<Status>());
+        assert!(InternLarge::<8>::interns::<Location>());
+
+        let value = (0..20)
+            .map(|i| Device {
+                status: [Status::Active, Status::Inactive][i % 2],
+                location: Location {
+                    latitude: (i % 3) as i64,
+                    longitude: 0,
+                    altitude: 0,
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .unwrap();
+        assert_eq!(interner.get::<Location>().map(Interner::len), Some(3));
+        assert!(interner.get::<Status>().is_none());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Device>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert!(a.status.is_inline());
+            assert!(a.location.is_interned());
+            assert_eq!(a.location.latitude, b.location.latitude);
+        }
+        assert!(core::ptr::eq(
+            archived[0].location.get(),
+            archived[6].location.get(),
+        ));
+
+        let deserialized =
+            deserialize::<Vec<Device>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+}
@@ -29,32 +29,133 @@
     13.512-13.512-2.702 2.703-2.702-8.107-8.107z"/%3E%3C/svg%3E
 "#)]
 #![cfg_attr(miri, feature(alloc_layout_extra))]
-#![cfg(feature = "alloc")]
+#[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+#[cfg(feature = "alloc")]
+mod api;
+#[cfg(feature = "alloc")]
+mod arena;
+#[cfg(feature = "alloc")]
+mod auto;
+#[cfg(feature = "alloc")]
+mod borrowed;
+#[cfg(feature = "alloc")]
+mod bounded;
+mod boxed;
+#[cfg(feature = "alloc")]
+mod btree;
+#[cfg(feature = "alloc")]
+mod collection;
+#[cfg(feature = "alloc")]
+mod cow;
+#[cfg(feature = "alloc")]
+mod dict;
+pub mod error;
+mod inline;
+mod interned;
 #[cfg(feature = "alloc")]
 mod interner;
+#[cfg(feature = "alloc")]
+mod keyed;
+mod large;
+mod maybe_interned;
+#[cfg(feature = "alloc")]
+mod min_len;
+#[cfg(feature = "alloc")]
+mod multi;
+mod niche;
+#[cfg(feature = "alloc")]
+mod normalize;
+#[cfg(feature = "std")]
+mod os_str;
 mod polyfill;
+#[cfg(feature = "alloc")]
+mod pool;
+mod project;
+mod reference;
+#[cfg(feature = "alloc")]
+mod shared;
+#[cfg(feature = "alloc")]
+mod string_pool;
+#[cfg(feature = "alloc")]
+mod symbol;
+#[cfg(feature = "std")]
+mod sync;
+pub mod testing;
 
 #[cfg(feature = "alloc")]
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec, vec::Vec};
 use core::{
     alloc::Layout, borrow::Borrow, error::Error, fmt, marker::PhantomData,
     ops::Deref, ptr::NonNull,
 };
 
 use rkyv::{
-    rancor::{fail, Fallible, ResultExt as _, Source, Strategy},
+    rancor::{Fallible, Source, Strategy},
     rc::{ArchivedRc, Flavor, RcResolver},
-    ser::{sharing::SharingState, Allocator, Positional, Sharing, Writer},
-    traits::LayoutRaw,
+    ser::{
+        sharing::SharingState, Allocator, Positional, Sharing, Writer,
+        WriterExt as _,
+    },
     with::{ArchiveWith, DeserializeWith, SerializeWith},
-    Archive, ArchiveUnsized, Deserialize, DeserializeUnsized, Place, Serialize,
-    SerializeUnsized,
+    Archive, ArchiveUnsized, Deserialize, Place, Serialize, SerializeUnsized,
 };
+#[cfg(feature = "alloc")]
+use rkyv::{rancor::ResultExt as _, traits::LayoutRaw, DeserializeUnsized};
+
+use self::error::CyclicInternedValueError;
 
+#[cfg(feature = "alloc")]
+pub use self::api::*;
+#[cfg(feature = "alloc")]
+pub use self::arena::*;
+#[cfg(feature = "alloc")]
+pub use self::auto::*;
+#[cfg(feature = "alloc")]
+pub use self::borrowed::*;
+#[cfg(feature = "alloc")]
+pub use self::bounded::*;
+pub use self::boxed::*;
+#[cfg(feature = "alloc")]
+pub use self::btree::*;
+#[cfg(feature = "alloc")]
+pub use self::collection::*;
+#[cfg(feature = "alloc")]
+pub use self::cow::*;
+#[cfg(feature = "alloc")]
+pub use self::dict::*;
+pub use self::inline::*;
+pub use self::interned::*;
 #[cfg(feature = "alloc")]
 pub use self::interner::*;
+#[cfg(feature = "alloc")]
+pub use self::keyed::*;
+pub use self::large::*;
+pub use self::maybe_interned::*;
+#[cfg(feature = "alloc")]
+pub use self::min_len::*;
+#[cfg(feature = "alloc")]
+pub use self::multi::*;
+pub use self::niche::*;
+#[cfg(feature = "alloc")]
+pub use self::normalize::*;
+#[cfg(feature = "std")]
+pub use self::os_str::*;
+#[cfg(feature = "alloc")]
+pub use self::pool::*;
+pub use self::project::*;
+pub use self::reference::*;
+#[cfg(feature = "alloc")]
+pub use self::shared::*;
+#[cfg(feature = "alloc")]
+pub use self::string_pool::*;
+#[cfg(feature = "alloc")]
+pub use self::symbol::*;
+#[cfg(feature = "std")]
+pub use self::sync::*;
 
 /// The result of starting to serialize a shared pointer.
 pub enum InterningState<S> {
@@ -73,6 +174,43 @@ pub enum InterningState<S> {
 /// A shared value interning strategy.
 ///
 /// This trait is required to use [`Intern`] and [`DerefIntern`].
+///
+/// # Contract
+///
+/// Each value moves through three states:
+///
+/// 1. The first time a value is started, `start_interning` returns
+///    [`InterningState::Started`]. The caller serializes the value and passes
+///    the returned state and the value's position to `finish_interning`.
+/// 2. Until then, the value is pending and starting it again returns
+///    [`InterningState::Pending`] every time.
+/// 3. Once it has been finished, starting it again returns
+///    [`InterningState::Finished`] with exactly the position that was passed to
+///    `finish_interning`.
+///
+/// `finish_interning` should only be called with a state returned from
+/// `start_interning` on the same interner. Implementations should return an
+/// error if the state's value is not pending, rather than overwriting the
+/// position of another value. They may accept finishing a value again with the
+/// same position.
+///
+/// [`testing::check_interning_contract`] can be used to check that an
+/// implementation follows this contract.
+///
+/// # Lifetimes
+///
+/// `start_interning` borrows the value for `'a`, and `State<'a>` may keep that
+/// borrow, for example by holding a `&'a T` which `finish_interning` uses to
+/// find the value again. Callers keep the value borrowed until they pass the
+/// state to `finish_interning`, so the borrow is valid for the whole time the
+/// value is pending. The state doesn't borrow the interner, so other values
+/// can be started and finished while it is held, which happens when the value
+/// contains interned values itself.
+///
+/// The interner itself can't keep the borrow after `start_interning` returns,
+/// since `'a` is chosen by each caller and may end before the interner does.
+/// An interner which stores borrowed values needs a lifetime parameter of its
+/// own, like [`BorrowedInterner`].
 pub trait Interning<T: ?Sized, E = <Self as Fallible>::Error> {
     /// Internal interning state.
     type State<'a> where T: 'a;
@@ -80,46 +218,313 @@ pub trait Interning<T: ?Sized, E = <Self as Fallible>::Error> {
     /// Starts interning the given value.
     fn start_interning<'a>(&mut self, value: &'a T) -> InterningState<Self::State<'a>>;
 
+    /// Starts interning the given value with a precomputed hash.
+    ///
+    /// `hash` must be the hash of the value as computed by the interning's
+    /// hasher. Implementations which don't hash values can ignore it, and by
+    /// default this calls [`start_interning`](Self::start_interning).
+    fn start_interning_with_hash<'a>(
+        &mut self,
+        value: &'a T,
+        hash: u64,
+    ) -> InterningState<Self::State<'a>> {
+        let _ = hash;
+        self.start_interning(value)
+    }
+
     /// Finishes interning the given value.
     ///
     /// Returns an error if the value was not pending.
     fn finish_interning(&mut self, state: Self::State<'_>, pos: usize) -> Result<(), E>;
+
+    /// Records a reference to a value which was already written at `pos`.
+    ///
+    /// This is called instead of [`start_interning`](Self::start_interning)
+    /// when the position of a value was found some other way, like the
+    /// pointer cache used by `RcIntern`. Interners which count references
+    /// or report hits should treat it like a value found already written. By
+    /// default this does nothing.
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        let _ = (value, pos);
+    }
+
+    /// The alignment to pad the writer to before writing a new value.
+    ///
+    /// This must be a power of two. By default, new values are written
+    /// wherever the writer is, which is the same as an alignment of 1.
+    fn value_alignment(&self) -> usize {
+        1
+    }
 }
 
-#[derive(Debug)]
-struct CyclicInternedValueError;
+/// An error type which can report interning errors.
+///
+/// This is implemented for every [`Source`] error type. Serializers with
+/// simpler error types can implement it directly to support interning.
+pub trait InterningError {
+    /// Returns a new error from the given source error.
+    fn new<T: Error + Send + Sync + 'static>(source: T) -> Self;
+}
+
+impl<E: Source> InterningError for E {
+    fn new<T: Error + Send + Sync + 'static>(source: T) -> Self {
+        Source::new(source)
+    }
+}
+
+/// A policy which decides which values are interned.
+///
+/// Policies can be set on an [`Interner`] with
+/// [`with_policy`](Interner::with_policy). Closures taking a value and
+/// returning a `bool` are policies, and a pair of policies only interns values
+/// which both of them would intern.
+pub trait InterningPolicy<T: ?Sized> {
+    /// Returns `true` if the value should be interned.
+    fn should_intern(&self, value: &T) -> bool;
+}
 
-impl fmt::Display for CyclicInternedValueError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "encountered cyclic shared pointers while interning")
+impl<T: ?Sized, F: Fn(&T) -> bool> InterningPolicy<T> for F {
+    fn should_intern(&self, value: &T) -> bool {
+        self(value)
     }
 }
 
-impl Error for CyclicInternedValueError {}
+impl<T, A, B> InterningPolicy<T> for (A, B)
+where
+    T: ?Sized,
+    A: InterningPolicy<T>,
+    B: InterningPolicy<T>,
+{
+    fn should_intern(&self, value: &T) -> bool {
+        self.0.should_intern(value) && self.1.should_intern(value)
+    }
+}
 
 /// Helper methods for [`Interning`].
 pub trait InterningExt<T: ?Sized, E>: Interning<T, E> {
     /// Interns and serializes a value.
     ///
-    /// Returns the position of the interned value.
+    /// Returns the position of the interned value. Every value is written at a
+    /// unique position, so zero-sized values are followed by a byte of padding.
+    /// Otherwise, empty values like `""` are interned like any other value,
+    /// and all of their references share a single archived copy.
     fn serialize_interned(
         &mut self,
         value: &T,
     ) -> Result<usize, <Self as Fallible>::Error>
     where
-        Self: Fallible<Error = E>,
-        E: Source,
+        Self: Fallible<Error = E> + Writer<E>,
+        E: InterningError,
         T: SerializeUnsized<Self>,
     {
-        match self.start_interning(value) {
-            InterningState::Started(state) => {
-                let pos = value.serialize_unsized(self)?;
-                self.finish_interning(state, pos)?;
-                Ok(pos)
+        self.serialize_interned_reporting(value).map(|(pos, _)| pos)
+    }
+
+    /// Interns and serializes a value, returning a resolver for a shared
+    /// pointer to it.
+    ///
+    /// This is the same as calling
+    /// [`serialize_interned`](InterningExt::serialize_interned) and creating an
+    /// `RcResolver` from the position. Custom containers can use it to resolve
+    /// an `ArchivedRc<_, InternFlavor>` for each interned element.
+    fn resolve_interned(
+        &mut self,
+        value: &T,
+    ) -> Result<RcResolver, <Self as Fallible>::Error>
+    where
+        Self: Fallible<Error = E> + Writer<E>,
+        E: InterningError,
+        T: SerializeUnsized<Self>,
+    {
+        self.serialize_interned(value).map(RcResolver::from_pos)
+    }
+
+    /// Interns and serializes a value, reporting whether it was written.
+    ///
+    /// Returns the position of the interned value, and `true` if the value was
+    /// serialized by this call or `false` if an earlier copy was reused.
+    fn serialize_interned_reporting(
+        &mut self,
+        value: &T,
+    ) -> Result<(usize, bool), <Self as Fallible>::Error>
+    where
+        Self: Fallible<Error = E> + Writer<E>,
+        E: InterningError,
+        T: SerializeUnsized<Self>,
+    {
+        let state = self.start_interning(value);
+        serialize_from_state(self, value, state, None)
+    }
+
+    /// Interns and serializes a value, describing it in errors.
+    ///
+    /// This works like
+    /// [`serialize_interned`](InterningExt::serialize_interned), but if the
+    /// value is part of a cycle, the error includes its `Debug` representation
+    /// as well as its type. The representation is only included when the
+    /// `alloc` feature is enabled.
+    fn serialize_interned_debug(
+        &mut self,
+        value: &T,
+    ) -> Result<usize, <Self as Fallible>::Error>
+    where
+        Self: Fallible<Error = E> + Writer<E>,
+        E: InterningError,
+        T: SerializeUnsized<Self> + fmt::Debug,
+    {
+        let state = self.start_interning(value);
+        serialize_from_state(self, value, state, Some(&value))
+            .map(|(pos, _)| pos)
+    }
+
+    /// Interns and serializes a value with a precomputed hash.
+    ///
+    /// `hash` must be the hash of the value as computed by the interning's
+    /// hasher. See [`Interning::start_interning_with_hash`] for details.
+    /// Returns the position of the interned value.
+    fn serialize_interned_with_hash(
+        &mut self,
+        value: &T,
+        hash: u64,
+    ) -> Result<usize, <Self as Fallible>::Error>
+    where
+        Self: Fallible<Error = E> + Writer<E>,
+        E: InterningError,
+        T: SerializeUnsized<Self>,
+    {
+        let state = self.start_interning_with_hash(value, hash);
+        serialize_from_state(self, value, state, None).map(|(pos, _)| pos)
+    }
+
+    /// Interns and serializes the unsized value borrowed from `value`.
+    ///
+    /// This is the same as calling [`serialize_interned`] with
+    /// `value.borrow()`, and can be used to intern values from custom
+    /// serialization code. Returns the position of the interned value.
+    ///
+    /// [`serialize_interned`]: InterningExt::serialize_interned
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "alloc")]
+    /// # {
+    /// use rkyv::{
+    ///     rancor::{Error, Strategy},
+    ///     ser::{allocator::Arena, Serializer},
+    ///     util::AlignedVec,
+    /// };
+    /// use rkyv_intern::{Interner, InterningAdapter, InterningExt};
+    ///
+    /// let mut arena = Arena::new();
+    /// let mut serializer = InterningAdapter::new(
+    ///     Serializer::new(AlignedVec::<16>::new(), arena.acquire(), ()),
+    ///     Interner::<String>::new(),
+    /// );
+    /// let serializer = Strategy::<_, Error>::wrap(&mut serializer);
+    ///
+    /// let first = serializer.serialize_interned_unsized("hello").unwrap();
+    /// let name = String::from("hello");
+    /// let second = InterningExt::<str, _>::serialize_interned_unsized(
+    ///     serializer,
+    ///     &name,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(first, second);
+    /// # }
+    /// ```
+    fn serialize_interned_unsized<U>(
+        &mut self,
+        value: &U,
+    ) -> Result<usize, <Self as Fallible>::Error>
+    where
+        Self: Fallible<Error = E> + Writer<E>,
+        E: InterningError,
+        T: SerializeUnsized<Self>,
+        U: Borrow<T> + ?Sized,
+    {
+        self.serialize_interned(value.borrow())
+    }
+
+    /// Interns and serializes each value in a slice.
+    ///
+    /// Returns the position of each interned value, in the same order as the
+    /// values. Equal values share a single position.
+    #[cfg(feature = "alloc")]
+    fn serialize_interned_slice(
+        &mut self,
+        values: &[T],
+    ) -> Result<Vec<usize>, <Self as Fallible>::Error>
+    where
+        Self: Fallible<Error = E> + Writer<E>,
+        E: InterningError,
+        T: SerializeUnsized<Self> + Sized,
+    {
+        let mut positions = vec![0; values.len()];
+        self.serialize_interned_into(values, &mut positions)?;
+        Ok(positions)
+    }
+
+    /// Interns and serializes each value in a slice, writing the position of
+    /// each value to `positions`.
+    ///
+    /// This works like
+    /// [`serialize_interned_slice`](InterningExt::serialize_interned_slice),
+    /// but doesn't allocate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` and `positions` have different lengths.
+    fn serialize_interned_into(
+        &mut self,
+        values: &[T],
+        positions: &mut [usize],
+    ) -> Result<(), <Self as Fallible>::Error>
+    where
+        Self: Fallible<Error = E> + Writer<E>,
+        E: InterningError,
+        T: SerializeUnsized<Self> + Sized,
+    {
+        assert_eq!(
+            values.len(),
+            positions.len(),
+            "values and positions must have the same length",
+        );
+        for (value, pos) in values.iter().zip(positions.iter_mut()) {
+            *pos = self.serialize_interned(value)?;
+        }
+        Ok(())
+    }
+}
+
+fn serialize_from_state<'a, S, T, E>(
+    serializer: &mut S,
+    value: &'a T,
+    state: InterningState<S::State<'a>>,
+    describe: Option<&dyn fmt::Debug>,
+) -> Result<(usize, bool), E>
+where
+    S: Interning<T, E> + Fallible<Error = E> + Writer<E> + ?Sized,
+    E: InterningError,
+    T: SerializeUnsized<S> + ?Sized,
+{
+    match state {
+        InterningState::Started(state) => {
+            serializer.align(serializer.value_alignment())?;
+            let pos = value.serialize_unsized(serializer)?;
+            // Interned values must have unique positions. If serializing the
+            // value didn't write any data, pad by a byte so the next value
+            // written can't have the same position.
+            if serializer.pos() == pos {
+                serializer.pad(1)?;
             }
-            InterningState::Pending => fail!(CyclicInternedValueError),
-            InterningState::Finished(pos) => Ok(pos),
+            serializer.finish_interning(state, pos)?;
+            Ok((pos, true))
         }
+        InterningState::Pending => {
+            Err(E::new(CyclicInternedValueError::new::<T>(describe)))
+        }
+        InterningState::Finished(pos) => Ok((pos, false)),
     }
 }
 
@@ -131,6 +536,32 @@ where
 }
 
 /// The flavor type for interned values.
+///
+/// With the `bytecheck` feature, archives containing interned values can be
+/// validated with `rkyv::access`. Each interned value is checked once, however
+/// many times it is shared, and cycles through interned values are rejected.
+///
+/// Validation doesn't trust that interned pointers were written by an
+/// interner. Each target must be a valid archived value of the pointer's type
+/// and lie in bounds, outside of any data it's nested in, like any other
+/// pointer in an archive. Interned values aren't stored in a separate region,
+/// so this is what keeps a pointer from reaching into other values. A target
+/// shared by pointers of different types is rejected as well, since it would
+/// have to be read as both types.
+///
+/// # Relocation
+///
+/// Interned values are archived as relative pointers, like every other pointer
+/// in an rkyv archive. Positions from the interner are only used while
+/// serializing, to compute the distance from each pointer to its value, and are
+/// never written to the archive. An archive can therefore be moved or
+/// concatenated with other archives, as long as it's copied as a whole and its
+/// start stays aligned to its original alignment.
+///
+/// This doesn't apply to positions saved with
+/// [`Interner::export_table`], which are only valid in the buffer they were
+/// written to.
+#[derive(Debug)]
 pub struct InternFlavor;
 
 impl Flavor for InternFlavor {
@@ -139,12 +570,29 @@ impl Flavor for InternFlavor {
 
 /// A wrapper that pools copies of the same value to reduce serialized size.
 ///
+/// Any sized value can be interned, including structs and tuples, as long as
+/// the interning supports it. For example, [`Interner`] supports values which
+/// are `Hash + Eq + Clone`.
+///
+/// Optional fields can be interned with `rkyv::with::Map<Intern>`, which
+/// interns the value inside each `Some` and archives `None` without touching
+/// the interner. `Map<Intern>` also interns each element of a fixed-size array,
+/// archiving a `[T; N]` as an array of `N` shared pointers. [`DerefIntern`]
+/// and [`BorrowIntern`] nest inside rkyv's `Map` and `MapKV` the same way, so
+/// the elements of a `Vec` or the values of a map can be interned too.
+///
+/// Interned values are archived as `ArchivedRc<_, InternFlavor>` by default.
+/// The flavor can be replaced with `F`, for example so interned fields have the
+/// same type as a project's existing `ArchivedRc<T, F>` fields. The flavor only
+/// changes the archived type, and values are interned the same way.
+///
 /// # Example
 ///
 /// ```
 /// use rkyv::Archive;
 /// use rkyv_intern::Intern;
 ///
+/// # #[cfg(feature = "alloc")]
 /// #[derive(Archive)]
 /// struct Example {
 ///     #[rkyv(with = Intern)]
@@ -152,10 +600,12 @@ impl Flavor for InternFlavor {
 /// }
 /// ```
 #[derive(Debug)]
-pub struct Intern;
+pub struct Intern<F = InternFlavor> {
+    _phantom: PhantomData<F>,
+}
 
-impl<T: Archive> ArchiveWith<T> for Intern {
-    type Archived = ArchivedRc<T::Archived, InternFlavor>;
+impl<T: Archive, F: Flavor> ArchiveWith<T> for Intern<F> {
+    type Archived = ArchivedRc<T::Archived, F>;
     type Resolver = RcResolver;
 
     fn resolve_with(
@@ -167,38 +617,49 @@ impl<T: Archive> ArchiveWith<T> for Intern {
     }
 }
 
-impl<T, S> SerializeWith<T, S> for Intern
+impl<T, S, F> SerializeWith<T, S> for Intern<F>
 where
     T: Serialize<S>,
+    F: Flavor,
     S: Interning<T> + Writer + Fallible + ?Sized,
-    S::Error: Source,
+    S::Error: InterningError,
 {
     fn serialize_with(
         field: &T,
         serializer: &mut S,
     ) -> Result<Self::Resolver, <S as Fallible>::Error> {
-        Ok(RcResolver::from_pos(serializer.serialize_interned(field)?))
+        serializer.resolve_interned(field)
     }
 }
 
-impl<T, D> DeserializeWith<ArchivedRc<T::Archived, InternFlavor>, T, D>
-    for Intern
+impl<T, D, F> DeserializeWith<ArchivedRc<T::Archived, F>, T, D> for Intern<F>
 where
     T: Archive,
     T::Archived: Deserialize<T, D>,
     D: Fallible + ?Sized,
+    F: Flavor,
 {
     fn deserialize_with(
-        field: &ArchivedRc<T::Archived, InternFlavor>,
+        field: &ArchivedRc<T::Archived, F>,
         deserializer: &mut D,
     ) -> Result<T, <D as Fallible>::Error> {
-        field.deserialize(deserializer)
+        field.get().deserialize(deserializer)
     }
 }
 
 /// A wrapper that shares copies of the same `Deref`-ed value to reduce
 /// serialized size.
 ///
+/// Values are deserialized into a new `Box` and converted with `From`, so any
+/// type which implements `From<Box<T::Target>>` can be deserialized. This
+/// includes `Box`, `String`, `Vec`, `Rc`, and `Arc`, and custom smart pointers
+/// can implement it as well. Each reference gets its own copy of the value;
+/// use [`PooledDerefIntern`] to share them when deserializing.
+///
+/// Targets which don't implement `Hash` and `Eq`, like trait objects and
+/// slices of floats, can be interned with a [`KeyedInterner`]. Like
+/// [`Intern`], the archived flavor can be replaced with `F`.
+///
 /// # Example
 ///
 /// ```
@@ -212,14 +673,15 @@ where
 /// }
 /// ```
 #[derive(Debug)]
-pub struct DerefIntern;
+pub struct DerefIntern<F = InternFlavor> {
+    _phantom: PhantomData<F>,
+}
 
-impl<T: Deref> ArchiveWith<T> for DerefIntern
+impl<T: Deref, F: Flavor> ArchiveWith<T> for DerefIntern<F>
 where
     T::Target: ArchiveUnsized,
 {
-    type Archived =
-        ArchivedRc<<T::Target as ArchiveUnsized>::Archived, InternFlavor>;
+    type Archived = ArchivedRc<<T::Target as ArchiveUnsized>::Archived, F>;
     type Resolver = RcResolver;
 
     fn resolve_with(
@@ -231,63 +693,50 @@ where
     }
 }
 
-impl<T, S> SerializeWith<T, S> for DerefIntern
+impl<T, S, F> SerializeWith<T, S> for DerefIntern<F>
 where
     T: Deref,
     T::Target: SerializeUnsized<S>,
+    F: Flavor,
     S: Interning<T::Target> + Writer + Fallible + ?Sized,
-    S::Error: Source,
+    S::Error: InterningError,
 {
     fn serialize_with(
         field: &T,
         serializer: &mut S,
     ) -> Result<Self::Resolver, <S as Fallible>::Error> {
-        Ok(RcResolver::from_pos(serializer.serialize_interned(field)?))
+        serializer.resolve_interned(field)
     }
 }
 
 #[cfg(feature = "alloc")]
-impl<T, D>
+impl<T, D, F>
     DeserializeWith<
-        ArchivedRc<<T::Target as ArchiveUnsized>::Archived, InternFlavor>,
+        ArchivedRc<<T::Target as ArchiveUnsized>::Archived, F>,
         T,
         D,
-    > for DerefIntern
+    > for DerefIntern<F>
 where
     T: Deref + From<Box<T::Target>>,
     T::Target: ArchiveUnsized + LayoutRaw,
     <T::Target as ArchiveUnsized>::Archived: DeserializeUnsized<T::Target, D>,
     D: Fallible + ?Sized,
     D::Error: Source,
+    F: Flavor,
 {
     fn deserialize_with(
-        field: &ArchivedRc<
-            <T::Target as ArchiveUnsized>::Archived,
-            InternFlavor,
-        >,
+        field: &ArchivedRc<<T::Target as ArchiveUnsized>::Archived, F>,
         deserializer: &mut D,
     ) -> Result<T, <D as Fallible>::Error> {
-        let metadata = field.get().deserialize_metadata();
-        let layout = T::Target::layout_raw(metadata).into_error()?;
-        let data_address = if layout.size() > 0 {
-            unsafe { ::alloc::alloc::alloc(layout) }
-        } else {
-            polyfill::dangling(&layout).as_ptr()
-        };
-
-        let out =
-            rkyv::ptr_meta::from_raw_parts_mut(data_address.cast(), metadata);
-
-        unsafe {
-            field.get().deserialize_unsized(deserializer, out)?;
-        }
-        unsafe { Ok(T::from(Box::from_raw(out))) }
+        Ok(T::from(deserialize_boxed(field.get(), deserializer)?))
     }
 }
 
 /// A wrapper that shares copies of the same `Borrow`-ed value to reduce
 /// serialized size.
 ///
+/// Like [`Intern`], the archived flavor can be replaced with `F`.
+///
 /// # Example
 ///
 /// ```
@@ -301,16 +750,54 @@ where
 /// }
 /// ```
 #[derive(Debug)]
-pub struct BorrowIntern<B: ?Sized> {
+pub struct BorrowIntern<B: ?Sized, F = InternFlavor> {
+    _flavor: PhantomData<F>,
     _phantom: PhantomData<B>,
 }
 
-impl<T, B> ArchiveWith<T> for BorrowIntern<B>
+mod sealed {
+    use core::borrow::Borrow;
+
+    pub trait Sealed<B: ?Sized> {}
+
+    impl<T: Borrow<B> + ?Sized, B: ?Sized> Sealed<B> for T {}
+}
+
+/// A field type which can be interned with [`BorrowIntern<B>`].
+///
+/// This is implemented for every type which implements `Borrow<B>`, and can't
+/// be implemented otherwise. `BorrowIntern` requires it instead of `Borrow<B>`
+/// so that using it on a field of the wrong type reports which borrow is
+/// missing.
+///
+/// ```compile_fail,E0277
+/// use rkyv::Archive;
+/// use rkyv_intern::BorrowIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     // error: `Vec<u8>` can't be interned as `str`
+///     #[rkyv(with = BorrowIntern<str>)]
+///     bytes: Vec<u8>,
+/// }
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be interned as `{B}`",
+    label = "field type must implement `Borrow<{B}>`",
+    note = "`BorrowIntern<{B}>` interns fields through `Borrow<{B}>`"
+)]
+pub trait BorrowInternField<B: ?Sized>: Borrow<B> + sealed::Sealed<B> {}
+
+#[diagnostic::do_not_recommend]
+impl<T: Borrow<B> + ?Sized, B: ?Sized> BorrowInternField<B> for T {}
+
+impl<T, B, F> ArchiveWith<T> for BorrowIntern<B, F>
 where
-    T: Borrow<B>,
+    T: BorrowInternField<B>,
     B: ArchiveUnsized + ?Sized,
+    F: Flavor,
 {
-    type Archived = ArchivedRc<B::Archived, InternFlavor>;
+    type Archived = ArchivedRc<B::Archived, F>;
     type Resolver = RcResolver;
 
     fn resolve_with(
@@ -322,83 +809,346 @@ where
     }
 }
 
-impl<T, S, B> SerializeWith<T, S> for BorrowIntern<B>
+impl<T, S, B, F> SerializeWith<T, S> for BorrowIntern<B, F>
 where
-    T: Borrow<B>,
+    T: BorrowInternField<B>,
     S: Interning<B> + Writer + Fallible + ?Sized,
-    S::Error: Source,
+    S::Error: InterningError,
     B: SerializeUnsized<S> + ?Sized,
+    F: Flavor,
 {
     fn serialize_with(
         field: &T,
         serializer: &mut S,
     ) -> Result<Self::Resolver, <S as Fallible>::Error> {
-        Ok(RcResolver::from_pos(
-            serializer.serialize_interned(field.borrow())?,
-        ))
+        serializer.resolve_interned(field.borrow())
     }
 }
 
 #[cfg(feature = "alloc")]
-impl<T, D, B> DeserializeWith<ArchivedRc<B::Archived, InternFlavor>, T, D>
-    for BorrowIntern<B>
+impl<T, D, B, F> DeserializeWith<ArchivedRc<B::Archived, F>, T, D>
+    for BorrowIntern<B, F>
 where
-    T: Borrow<B> + From<Box<B>>,
+    T: BorrowInternField<B> + From<Box<B>>,
     D: Fallible + ?Sized,
     D::Error: Source,
     B: ArchiveUnsized + LayoutRaw + ?Sized,
     B::Archived: DeserializeUnsized<B, D>,
+    F: Flavor,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<B::Archived, F>,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        Ok(T::from(deserialize_boxed(field.get(), deserializer)?))
+    }
+}
+
+/// A wrapper that shares copies of the same sized `Borrow`-ed value to reduce
+/// serialized size.
+///
+/// This archives values the same way as [`BorrowIntern`], but only applies to
+/// sized `Copy` targets. Unlike `BorrowIntern`, it deserializes the target on
+/// the stack and converts it with `From` instead of allocating a `Box`, so it
+/// can be used without an allocator.
+///
+/// # Example
+///
+/// ```
+/// use std::borrow::Borrow;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::CopyIntern;
+///
+/// struct Hash([u8; 16]);
+///
+/// impl Borrow<[u8; 16]> for Hash {
+///     fn borrow(&self) -> &[u8; 16] {
+///         &self.0
+///     }
+/// }
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = CopyIntern<[u8; 16]>)]
+///     hash: Hash,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CopyIntern<B> {
+    _phantom: PhantomData<B>,
+}
+
+impl<T, B> ArchiveWith<T> for CopyIntern<B>
+where
+    T: Borrow<B>,
+    B: Archive + Copy,
+{
+    type Archived = ArchivedRc<B::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedRc::resolve_from_ref(field.borrow(), resolver, out);
+    }
+}
+
+impl<T, S, B> SerializeWith<T, S> for CopyIntern<B>
+where
+    T: Borrow<B>,
+    S: Interning<B> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+    B: Serialize<S> + Copy,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        serializer.resolve_interned(field.borrow())
+    }
+}
+
+impl<T, D, B> DeserializeWith<ArchivedRc<B::Archived, InternFlavor>, T, D>
+    for CopyIntern<B>
+where
+    T: From<B>,
+    D: Fallible + ?Sized,
+    B: Archive + Copy,
+    B::Archived: Deserialize<B, D>,
 {
     fn deserialize_with(
         field: &ArchivedRc<B::Archived, InternFlavor>,
         deserializer: &mut D,
     ) -> Result<T, <D as Fallible>::Error> {
-        let metadata = field.get().deserialize_metadata();
-        let layout = B::layout_raw(metadata).into_error()?;
-        let data_address = if layout.size() > 0 {
-            unsafe { ::alloc::alloc::alloc(layout) }
-        } else {
-            polyfill::dangling(&layout).as_ptr()
-        };
+        Ok(T::from(field.get().deserialize(deserializer)?))
+    }
+}
+
+/// Deserializes an unsized archived value into a new `Box`.
+#[cfg(feature = "alloc")]
+fn deserialize_boxed<T, D>(
+    archived: &T::Archived,
+    deserializer: &mut D,
+) -> Result<Box<T>, D::Error>
+where
+    T: ArchiveUnsized + LayoutRaw + ?Sized,
+    T::Archived: DeserializeUnsized<T, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    let metadata = archived.deserialize_metadata();
+    let layout = T::layout_raw(metadata).into_error()?;
+    let data_address = if layout.size() > 0 {
+        unsafe { ::alloc::alloc::alloc(layout) }
+    } else {
+        polyfill::dangling(&layout).as_ptr()
+    };
 
-        let out =
-            rkyv::ptr_meta::from_raw_parts_mut(data_address.cast(), metadata);
+    let out =
+        rkyv::ptr_meta::from_raw_parts_mut(data_address.cast(), metadata);
 
-        unsafe {
-            field.get().deserialize_unsized(deserializer, out)?;
-        }
-        unsafe { Ok(T::from(Box::from_raw(out))) }
+    unsafe {
+        archived.deserialize_unsized(deserializer, out)?;
     }
+    unsafe { Ok(Box::from_raw(out)) }
 }
 
 /// A basic adapter that can add interning capabilities to a serializer.
 ///
 /// While this struct is useful for ergonomics, it's best to define a custom
 /// serializer when combining capabilities across many crates.
+///
+/// The adapter forwards [`Sharing`] to the underlying serializer, so shared
+/// pointers like `Rc` and interned values are deduplicated separately. Sharing
+/// maps the addresses of shared pointers to the positions they were written
+/// at, while interning maps values to the positions they were written at. Both
+/// only ever return positions of values they wrote, and each write has a unique
+/// position, so an `Rc` and an interned value never alias each other even if
+/// they are equal.
+///
+/// The adapter also counts the bytes written for interned values, which can be
+/// read with [`interned_bytes`](Self::interned_bytes). With
+/// [`with_scratch_accounting`](Self::with_scratch_accounting), it also tracks
+/// the scratch space allocated through the serializer.
+///
+/// The adapter only implements `Default` if both the serializer and the
+/// interning do. Serializers which borrow an arena, like
+/// `rkyv::ser::Serializer<_, ArenaHandle, _>`, don't, so use
+/// [`with_default_interner`](Self::with_default_interner) to default just the
+/// interning.
 #[derive(Debug, Default)]
 pub struct InterningAdapter<S, I> {
     serializer: S,
     interning: I,
+    interned_bytes: usize,
+    depth: usize,
+    value_alignment: usize,
+    scratch: Option<ScratchUsage>,
+}
+
+/// The scratch space used while serializing, in bytes.
+#[derive(Debug, Default)]
+struct ScratchUsage {
+    current: usize,
+    interned_base: usize,
+    peak: usize,
+    peak_interned: usize,
 }
 
 impl<S, I> InterningAdapter<S, I> {
     /// Constructs a new interning adapter from a serializer and interning.
+    ///
+    /// The interning can also be a mutable reference, so that an interner
+    /// outlives the adapter and can be inspected or reused afterwards.
     pub fn new(serializer: S, interning: I) -> Self {
         Self {
             serializer,
             interning,
+            interned_bytes: 0,
+            depth: 0,
+            value_alignment: 1,
+            scratch: None,
         }
     }
 
-    /// Consumes the adapter and returns the components.
-    pub fn into_components(self) -> (S, I) {
-        (self.serializer, self.interning)
-    }
+    /// Constructs a new interning adapter from a serializer and a default
+    /// interning.
+    pub fn with_default_interner(serializer: S) -> Self
+    where
+        I: Default,
+    {
+        Self::new(serializer, I::default())
+    }
+
+    /// Aligns every new interned value to a multiple of `align`.
+    ///
+    /// The writer is padded to the alignment before each value is written for
+    /// the first time, so the position recorded in the interning is aligned.
+    /// Values which write other data before themselves, like a struct
+    /// containing a `String`, start that data at the aligned position instead
+    /// and are only aligned to their own alignment. Archived values are read
+    /// the same way regardless of the alignment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn with_value_alignment(mut self, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        self.value_alignment = align;
+        self
+    }
+
+    /// Tracks the scratch space allocated through the adapter.
+    ///
+    /// The adapter forwards [`Allocator`] to the underlying serializer, so
+    /// interned values and everything else share the same scratch space. With
+    /// scratch accounting, the adapter also records the peak scratch space used
+    /// overall and the peak used while serializing interned values, which can
+    /// be read with [`peak_scratch`](Self::peak_scratch) and
+    /// [`peak_interned_scratch`](Self::peak_interned_scratch).
+    pub fn with_scratch_accounting(mut self) -> Self {
+        self.scratch = Some(ScratchUsage::default());
+        self
+    }
+
+    /// Returns a reference to the underlying serializer.
+    pub fn serializer(&self) -> &S {
+        &self.serializer
+    }
+
+    /// Returns a mutable reference to the underlying serializer.
+    pub fn serializer_mut(&mut self) -> &mut S {
+        &mut self.serializer
+    }
+
+    /// Returns a reference to the interning.
+    pub fn interning(&self) -> &I {
+        &self.interning
+    }
+
+    /// Returns a mutable reference to the interning.
+    pub fn interning_mut(&mut self) -> &mut I {
+        &mut self.interning
+    }
+
+    /// The number of bytes written for interned values.
+    ///
+    /// This counts the bytes written while serializing each interned value for
+    /// the first time, including padding for alignment and any data the value
+    /// points to. Later references to the value and the shared pointers to it
+    /// are not counted.
+    pub fn interned_bytes(&self) -> usize {
+        self.interned_bytes
+    }
+
+    /// The peak number of bytes of scratch space in use.
+    ///
+    /// This is always zero without
+    /// [`with_scratch_accounting`](Self::with_scratch_accounting).
+    pub fn peak_scratch(&self) -> usize {
+        self.scratch.as_ref().map_or(0, |scratch| scratch.peak)
+    }
+
+    /// The peak number of bytes of scratch space allocated while serializing
+    /// interned values.
+    ///
+    /// This only counts scratch space allocated after the outermost interned
+    /// value was started, and not the scratch space which was already in use
+    /// by the values containing it. The difference from
+    /// [`peak_scratch`](Self::peak_scratch) is the scratch space needed by the
+    /// rest of the serialization. This is always zero without
+    /// [`with_scratch_accounting`](Self::with_scratch_accounting).
+    pub fn peak_interned_scratch(&self) -> usize {
+        self.scratch.as_ref().map_or(0, |scratch| scratch.peak_interned)
+    }
+
+    /// Consumes the adapter and returns the components.
+    pub fn into_components(self) -> (S, I) {
+        (self.serializer, self.interning)
+    }
 
     /// Consumes the adapter and returns the underlying serializer.
     pub fn into_serializer(self) -> S {
         self.serializer
     }
+
+    /// Interns and serializes a value, returning its position.
+    ///
+    /// This is a convenience for calling
+    /// [`InterningExt::serialize_interned`] on the adapter wrapped in a
+    /// `Strategy` with the error type `E`. Generic code should use the
+    /// extension trait instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "alloc")]
+    /// # {
+    /// use rkyv::{rancor::Error, ser::Serializer, util::AlignedVec};
+    /// use rkyv_intern::{Interner, InterningAdapter};
+    ///
+    /// let mut adapter = InterningAdapter::new(
+    ///     Serializer::new(AlignedVec::<16>::new(), (), ()),
+    ///     Interner::<String>::new(),
+    /// );
+    /// let first = adapter.serialize_interned::<str, Error>("hello")?;
+    /// let second = adapter.serialize_interned::<str, Error>("hello")?;
+    /// assert_eq!(first, second);
+    /// # }
+    /// # Ok::<(), rkyv::rancor::Error>(())
+    /// ```
+    pub fn serialize_interned<T, E>(&mut self, value: &T) -> Result<usize, E>
+    where
+        T: SerializeUnsized<Strategy<Self, E>> + ?Sized,
+        S: Writer<E>,
+        I: Interning<T, E>,
+        E: InterningError,
+    {
+        Strategy::<Self, E>::wrap(self).serialize_interned(value)
+    }
 }
 
 unsafe impl<S: Allocator<E>, I, E> Allocator<E> for InterningAdapter<S, I> {
@@ -406,7 +1156,16 @@ unsafe impl<S: Allocator<E>, I, E> Allocator<E> for InterningAdapter<S, I> {
         &mut self,
         layout: Layout,
     ) -> Result<NonNull<[u8]>, E> {
-        unsafe { self.serializer.push_alloc(layout) }
+        let result = unsafe { self.serializer.push_alloc(layout)? };
+        if let Some(scratch) = &mut self.scratch {
+            scratch.current += layout.size();
+            scratch.peak = scratch.peak.max(scratch.current);
+            if self.depth > 0 {
+                let interned = scratch.current - scratch.interned_base;
+                scratch.peak_interned = scratch.peak_interned.max(interned);
+            }
+        }
+        Ok(result)
     }
 
     unsafe fn pop_alloc(
@@ -414,7 +1173,11 @@ unsafe impl<S: Allocator<E>, I, E> Allocator<E> for InterningAdapter<S, I> {
         ptr: NonNull<u8>,
         layout: Layout,
     ) -> Result<(), E> {
-        unsafe { self.serializer.pop_alloc(ptr, layout) }
+        unsafe { self.serializer.pop_alloc(ptr, layout)? };
+        if let Some(scratch) = &mut self.scratch {
+            scratch.current -= layout.size();
+        }
+        Ok(())
     }
 }
 
@@ -440,19 +1203,70 @@ impl<S: Sharing<E>, I, E> Sharing<E> for InterningAdapter<S, I> {
     }
 }
 
+impl<S: Positional, I> InterningAdapter<S, I> {
+    /// Records the start position of a started value.
+    fn track<T>(
+        &mut self,
+        state: InterningState<T>,
+    ) -> InterningState<(T, usize)> {
+        match state {
+            InterningState::Started(state) => {
+                if self.depth == 0 {
+                    if let Some(scratch) = &mut self.scratch {
+                        scratch.interned_base = scratch.current;
+                    }
+                }
+                self.depth += 1;
+                InterningState::Started((state, self.serializer.pos()))
+            }
+            InterningState::Pending => InterningState::Pending,
+            InterningState::Finished(pos) => InterningState::Finished(pos),
+        }
+    }
+}
+
 impl<S, I, T, E> Interning<T, E> for InterningAdapter<S, I>
 where
+    S: Positional,
     I: Interning<T, E>,
     T: ?Sized,
 {
-    type State<'a> = I::State<'a> where T: 'a;
+    type State<'a> = (I::State<'a>, usize) where T: 'a;
 
     fn start_interning<'a>(&mut self, value: &'a T) -> InterningState<Self::State<'a>> {
-        self.interning.start_interning(value)
+        let state = self.interning.start_interning(value);
+        self.track(state)
+    }
+
+    fn start_interning_with_hash<'a>(
+        &mut self,
+        value: &'a T,
+        hash: u64,
+    ) -> InterningState<Self::State<'a>> {
+        let state = self.interning.start_interning_with_hash(value, hash);
+        self.track(state)
     }
 
     fn finish_interning(&mut self, state: Self::State<'_>, pos: usize) -> Result<(), E> {
-        self.interning.finish_interning(state, pos)
+        let (state, start) = state;
+        self.interning.finish_interning(state, pos)?;
+        // Values interned while serializing another interned value are
+        // counted as part of the outermost value.
+        if let Some(depth) = self.depth.checked_sub(1) {
+            self.depth = depth;
+            if depth == 0 {
+                self.interned_bytes += self.serializer.pos() - start;
+            }
+        }
+        Ok(())
+    }
+
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        self.interning.record_hit(value, pos);
+    }
+
+    fn value_alignment(&self) -> usize {
+        self.value_alignment.max(self.interning.value_alignment())
     }
 }
 
@@ -467,14 +1281,64 @@ where
         S::start_interning(self, value)
     }
 
+    fn start_interning_with_hash<'a>(
+        &mut self,
+        value: &'a T,
+        hash: u64,
+    ) -> InterningState<Self::State<'a>> {
+        S::start_interning_with_hash(self, value, hash)
+    }
+
     fn finish_interning(&mut self, state: Self::State<'_>, pos: usize) -> Result<(), E> {
         S::finish_interning(self, state, pos)
     }
+
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        S::record_hit(self, value, pos);
+    }
+
+    fn value_alignment(&self) -> usize {
+        S::value_alignment(self)
+    }
+}
+
+impl<I, T, E> Interning<T, E> for &mut I
+where
+    I: Interning<T, E> + ?Sized,
+    T: ?Sized,
+{
+    type State<'a> = I::State<'a> where T: 'a;
+
+    fn start_interning<'a>(&mut self, value: &'a T) -> InterningState<Self::State<'a>> {
+        I::start_interning(self, value)
+    }
+
+    fn start_interning_with_hash<'a>(
+        &mut self,
+        value: &'a T,
+        hash: u64,
+    ) -> InterningState<Self::State<'a>> {
+        I::start_interning_with_hash(self, value, hash)
+    }
+
+    fn finish_interning(&mut self, state: Self::State<'_>, pos: usize) -> Result<(), E> {
+        I::finish_interning(self, state, pos)
+    }
+
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        I::record_hit(self, value, pos);
+    }
+
+    fn value_alignment(&self) -> usize {
+        I::value_alignment(self)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use ::alloc::{
+        boxed::Box,
+        rc::Rc,
         string::{String, ToString},
         vec::Vec,
     };
@@ -483,46 +1347,102 @@ mod tests {
         api::serialize_using,
         deserialize,
         rancor::{Panic, ResultExt, Strategy},
-        ser::{allocator::ArenaHandle, Serializer},
+        ser::{allocator::ArenaHandle, sharing::Share, Serializer},
         util::{with_arena, AlignedVec},
         Archive, Archived, Deserialize, Serialize,
     };
 
+    use core::error::Error;
+
     use crate::{
-        BorrowIntern, DerefIntern, Intern, Interner, InterningAdapter,
+        BorrowIntern, DerefIntern, Intern, Interner, Interning,
+        InterningAdapter, InterningError, InterningExt as _, MultiInterner,
     };
 
-    const USERS: [&str; 4] = [
+    pub const USERS: [&str; 4] = [
         "Alice, the leader and brains behind the team",
         "Bob, bodybuilder and the muscle of the operation",
         "Carol, safe-cracker and swindler extraordinaire",
         "Dave, Jumanji master of the spirit dimension",
     ];
 
-    type InterningSerializer<'a, E> = Strategy<
-        InterningAdapter<
-            Serializer<AlignedVec<8>, ArenaHandle<'a>, ()>,
-            Interner<String>,
-        >,
+    pub type InterningSerializer<'a, E, I = Interner<String>> = Strategy<
+        InterningAdapter<Serializer<AlignedVec<8>, ArenaHandle<'a>, ()>, I>,
         E,
     >;
 
-    fn serialize_interned<T, E>(value: &T) -> Result<AlignedVec<8>, E>
+    pub fn serialize_with<T, I, E>(
+        value: &T,
+        interner: I,
+    ) -> Result<(AlignedVec<8>, I), E>
     where
-        T: for<'a> Serialize<InterningSerializer<'a, E>>,
+        T: for<'a> Serialize<InterningSerializer<'a, E, I>>,
     {
         with_arena(|arena| {
             let mut serializer = InterningAdapter::new(
                 Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
-                Interner::default(),
+                interner,
             );
 
             serialize_using::<_, E>(value, &mut serializer)?;
 
-            Ok(serializer.into_serializer().into_writer())
+            let (serializer, interner) = serializer.into_components();
+            Ok((serializer.into_writer(), interner))
         })
     }
 
+    pub fn serialize_with_interner<T, E>(
+        value: &T,
+    ) -> Result<(AlignedVec<8>, Interner<String>), E>
+    where
+        T: for<'a> Serialize<InterningSerializer<'a, E>>,
+    {
+        serialize_with(value, Interner::default())
+    }
+
+    pub fn serialize_interned<T, E>(value: &T) -> Result<AlignedVec<8>, E>
+    where
+        T: for<'a> Serialize<InterningSerializer<'a, E>>,
+    {
+        serialize_with_interner(value).map(|(bytes, _)| bytes)
+    }
+
+    #[test]
+    fn cyclic_value_error() {
+        use rkyv::{
+            rancor::{Error, Fallible},
+            ser::Writer,
+        };
+
+        // Interns an equal copy of itself while it is being serialized.
+        #[derive(Archive, Clone, Debug, Hash, PartialEq, Eq)]
+        struct Cycle(u32);
+
+        impl<S> Serialize<S> for Cycle
+        where
+            S: Interning<Cycle> + Writer + Fallible + ?Sized,
+            S::Error: InterningError,
+        {
+            fn serialize(
+                &self,
+                serializer: &mut S,
+            ) -> Result<Self::Resolver, S::Error> {
+                serializer.serialize_interned_debug(self)?;
+                Ok(CycleResolver(()))
+            }
+        }
+
+        let error = serialize_with::<_, _, Error>(
+            &Cycle(42),
+            Interner::<Cycle>::new(),
+        )
+        .map(|_| ())
+        .unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("::Cycle`"), "{}", message);
+        assert!(message.contains("Cycle(42)"), "{}", message);
+    }
+
     #[test]
     fn intern_strings() {
         #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
@@ -555,6 +1475,510 @@ mod tests {
         assert_eq!(deserialized, value);
     }
 
+    #[test]
+    fn borrowing_state() {
+        use crate::InterningState;
+
+        // Keeps owned copies of finished values, but only borrows values
+        // while they are pending.
+        #[derive(Default)]
+        struct ListInterner {
+            pending: usize,
+            finished: Vec<(String, usize)>,
+        }
+
+        impl<E: InterningError> Interning<str, E> for ListInterner {
+            type State<'a> = &'a str;
+
+            fn start_interning<'a>(
+                &mut self,
+                value: &'a str,
+            ) -> InterningState<Self::State<'a>> {
+                match self.finished.iter().find(|(v, _)| v == value) {
+                    Some(&(_, pos)) => InterningState::Finished(pos),
+                    None => {
+                        self.pending += 1;
+                        InterningState::Started(value)
+                    }
+                }
+            }
+
+            fn finish_interning(
+                &mut self,
+                state: Self::State<'_>,
+                pos: usize,
+            ) -> Result<(), E> {
+                self.pending -= 1;
+                self.finished.push((state.to_string(), pos));
+                Ok(())
+            }
+        }
+
+        let mut interner = ListInterner::default();
+        let outer = String::from(USERS[0]);
+        let state =
+            Interning::<str, Panic>::start_interning(&mut interner, &outer);
+        let outer_state = match state {
+            InterningState::Started(state) => state,
+            _ => panic!("new value was not started"),
+        };
+        // Another value is interned while the first is still borrowed.
+        let inner = String::from(USERS[1]);
+        match Interning::<str, Panic>::start_interning(&mut interner, &inner) {
+            InterningState::Started(state) => {
+                Interning::<str, Panic>::finish_interning(
+                    &mut interner,
+                    state,
+                    0,
+                )
+                .always_ok();
+            }
+            _ => panic!("new value was not started"),
+        }
+        drop(inner);
+        assert_eq!(interner.pending, 1);
+        Interning::<str, Panic>::finish_interning(
+            &mut interner,
+            outer_state,
+            64,
+        )
+        .always_ok();
+        assert_eq!(interner.pending, 0);
+        assert_eq!(interner.finished[1], (outer, 64));
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = DerefIntern)]
+            user: String,
+        }
+
+        let value = (0..10)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+            })
+            .collect::<Vec<_>>();
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, ListInterner::default())
+                .always_ok();
+        assert_eq!(interner.finished.len(), USERS.len());
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        assert!(core::ptr::eq(&*archived[0].user, &*archived[4].user));
+    }
+
+    #[test]
+    fn resolve_interned() {
+        use rkyv::{
+            rancor::{Fallible, Source},
+            rc::{ArchivedRc, RcResolver},
+            ser::Writer,
+            string::ArchivedString,
+            Place,
+        };
+
+        use crate::InternFlavor;
+
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        // A hand-rolled interned field which resolves its own pointer.
+        struct User(String);
+
+        impl Archive for User {
+            type Archived = ArchivedRc<ArchivedString, InternFlavor>;
+            type Resolver = RcResolver;
+
+            fn resolve(
+                &self,
+                resolver: RcResolver,
+                out: Place<Self::Archived>,
+            ) {
+                ArchivedRc::resolve_from_ref(&self.0, resolver, out);
+            }
+        }
+
+        impl<S> Serialize<S> for User
+        where
+            S: Interning<String> + Writer + Fallible + ?Sized,
+            S::Error: Source,
+        {
+            fn serialize(
+                &self,
+                serializer: &mut S,
+            ) -> Result<RcResolver, S::Error> {
+                serializer.resolve_interned(&self.0)
+            }
+        }
+
+        let users = (0..20).map(|i| USERS[i % USERS.len()].to_string());
+        let logs = users.clone().map(|user| Log { user }).collect::<Vec<_>>();
+        let hand_rolled = users.map(User).collect::<Vec<_>>();
+
+        let expected = serialize_interned::<_, Panic>(&logs).always_ok();
+        let bytes = serialize_interned::<_, Panic>(&hand_rolled).always_ok();
+        assert_eq!(bytes[..], expected[..]);
+    }
+
+    #[test]
+    fn default_interner() {
+        let bytes = with_arena(|arena| {
+            let serializer =
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ());
+            let mut serializer =
+                InterningAdapter::<_, Interner<String>>::with_default_interner(
+                    serializer,
+                );
+            let first = serializer
+                .serialize_interned::<str, Panic>(USERS[0])
+                .always_ok();
+            let second = serializer
+                .serialize_interned::<str, Panic>(USERS[0])
+                .always_ok();
+            assert_eq!(first, second);
+            assert_eq!(serializer.interning().len(), 1);
+            serializer.into_serializer().into_writer()
+        });
+        assert_eq!(&bytes[..USERS[0].len()], USERS[0].as_bytes());
+    }
+
+    #[test]
+    fn value_alignment() {
+        #[derive(Archive, Serialize)]
+        struct Record {
+            #[rkyv(with = Intern)]
+            header: [u8; 3],
+            #[rkyv(with = DerefIntern)]
+            name: String,
+        }
+
+        let value = (0..20)
+            .map(|i| Record {
+                header: [i as u8 % 5; 3],
+                name: USERS[i % USERS.len()][..i % 7].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) = with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<16>::new(), arena.acquire(), ()),
+                MultiInterner::new(),
+            )
+            .with_value_alignment(16);
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+            let (serializer, interner) = serializer.into_components();
+            (serializer.into_writer(), interner)
+        });
+
+        let headers = interner.get::<[u8; 3]>().unwrap();
+        let names = interner.get::<str>().unwrap();
+        assert_eq!(headers.len(), 5);
+        let entries = headers.iter().map(|(_, entry)| entry);
+        for entry in entries.chain(names.iter().map(|(_, entry)| entry)) {
+            assert_eq!(entry.pos().unwrap() % 16, 0);
+        }
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Record>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.header.as_ptr() as usize % 16, 0);
+            assert_eq!(*a.header, b.header);
+            assert_eq!(&*a.name, b.name);
+        }
+    }
+
+    #[test]
+    fn scratch_accounting() {
+        #[derive(Archive, Serialize)]
+        struct Team {
+            #[rkyv(with = Intern)]
+            interned: Vec<String>,
+            inline: Vec<String>,
+        }
+
+        fn peak_scratch(value: &Vec<Team>) -> (usize, usize) {
+            with_arena(|arena| {
+                let writer = AlignedVec::<8>::new();
+                let serializer = Serializer::new(writer, arena.acquire(), ());
+                let mut serializer =
+                    InterningAdapter::new(serializer, MultiInterner::new())
+                        .with_scratch_accounting();
+                serialize_using::<_, Panic>(value, &mut serializer)
+                    .always_ok();
+                (serializer.peak_scratch(), serializer.peak_interned_scratch())
+            })
+        }
+
+        let members = |i: usize| {
+            USERS.iter().cycle().skip(i).take(3).map(|user| user.to_string())
+        };
+        let heavy = (0..4)
+            .map(|i| Team {
+                interned: members(i).collect(),
+                inline: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let free = (0..4)
+            .map(|i| Team {
+                interned: Vec::new(),
+                inline: members(i).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (heavy_peak, heavy_interned) = peak_scratch(&heavy);
+        let (free_peak, free_interned) = peak_scratch(&free);
+        assert!(heavy_interned > 0);
+        assert!(heavy_interned <= heavy_peak);
+        assert_eq!(free_interned, 0);
+        assert!(free_peak > 0);
+
+        let serializer = InterningAdapter::new(
+            Serializer::new(AlignedVec::<8>::new(), (), ()),
+            Interner::<String>::new(),
+        );
+        assert_eq!(serializer.peak_scratch(), 0);
+        assert_eq!(serializer.peak_interned_scratch(), 0);
+    }
+
+    #[test]
+    fn borrowed_interner() {
+        let mut interner = Interner::<String>::new();
+
+        let mut adapter = InterningAdapter::new(
+            Serializer::new(AlignedVec::<8>::new(), (), ()),
+            &mut interner,
+        );
+        let first =
+            adapter.serialize_interned::<str, Panic>(USERS[0]).always_ok();
+        adapter.serialize_interned::<str, Panic>(USERS[1]).always_ok();
+        adapter.serialize_interned::<str, Panic>(USERS[0]).always_ok();
+        let serializer = adapter.into_serializer();
+
+        assert_eq!(interner.len(), 2);
+        #[cfg(feature = "statistics")]
+        assert_eq!(interner.singleton_count(), 1);
+
+        let mut adapter = InterningAdapter::new(serializer, &mut interner);
+        let second =
+            adapter.serialize_interned::<str, Panic>(USERS[0]).always_ok();
+        adapter.serialize_interned::<str, Panic>(USERS[2]).always_ok();
+        assert_eq!(first, second);
+        assert_eq!(adapter.interned_bytes(), USERS[2].len());
+
+        assert_eq!(interner.len(), 3);
+        #[cfg(feature = "statistics")]
+        assert_eq!(interner.singleton_count(), 2);
+    }
+
+    #[cfg(feature = "bytecheck")]
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct CheckedLog {
+        #[rkyv(with = Intern)]
+        user: String,
+        #[rkyv(with = crate::MinLenIntern<8>)]
+        tag: String,
+        code: u16,
+    }
+
+    #[cfg(feature = "bytecheck")]
+    fn checked_logs() -> Vec<CheckedLog> {
+        (0..100)
+            .map(|i| CheckedLog {
+                user: USERS[i % USERS.len()].to_string(),
+                tag: ["short", "a much longer tag"][i % 2].to_string(),
+                code: i as u16,
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "bytecheck")]
+    #[test]
+    fn access_checked() {
+        use rkyv::{access, rancor::Error};
+
+        let value = checked_logs();
+        let bytes = serialize_interned::<_, Error>(&value).unwrap();
+
+        let archived =
+            access::<Archived<Vec<CheckedLog>>, Error>(&bytes).unwrap();
+        assert!(core::ptr::eq(&*archived[0].user, &*archived[4].user));
+        assert!(archived[1].tag.is_interned());
+        assert!(archived[0].tag.is_inline());
+
+        let deserialized =
+            deserialize::<Vec<CheckedLog>, Error>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "bytecheck")]
+    #[test]
+    fn access_checked_corrupted() {
+        use rkyv::{access, rancor::Error};
+
+        let mut bytes =
+            serialize_interned::<_, Error>(&checked_logs()).unwrap();
+
+        let offset = {
+            let archived =
+                access::<Archived<Vec<CheckedLog>>, Error>(&bytes).unwrap();
+            let user = &archived[0].user as *const _ as usize;
+            user - bytes.as_ptr() as usize
+        };
+        // Point the first interned user past the end of the buffer.
+        let out_of_bounds = bytes.len() as i32;
+        bytes[offset..offset + 4].copy_from_slice(&out_of_bounds.to_le_bytes());
+
+        assert!(access::<Archived<Vec<CheckedLog>>, Error>(&bytes).is_err());
+    }
+
+    #[cfg(feature = "bytecheck")]
+    #[test]
+    fn access_checked_invalid_targets() {
+        use rkyv::{access, rancor::Error};
+
+        #[derive(Archive, Serialize)]
+        struct Record {
+            #[rkyv(with = Intern)]
+            name: String,
+            #[rkyv(with = Intern)]
+            id: [u8; 4],
+        }
+
+        fn check(bytes: &[u8]) -> Result<(), Error> {
+            access::<Archived<Vec<Record>>, Error>(bytes).map(|_| ())
+        }
+
+        // Points the interned pointer at `from` to `to` instead.
+        fn retarget(
+            bytes: &AlignedVec<8>,
+            from: usize,
+            to: usize,
+        ) -> AlignedVec<8> {
+            let mut bytes = bytes.clone();
+            let offset = (to as isize - from as isize) as i32;
+            bytes[from..from + 4].copy_from_slice(&offset.to_le_bytes());
+            bytes
+        }
+
+        let value = (0..4)
+            .map(|i| Record {
+                name: USERS[i].to_string(),
+                id: [i as u8; 4],
+            })
+            .collect::<Vec<_>>();
+        let (bytes, _) =
+            serialize_with::<_, _, Error>(&value, MultiInterner::new())
+                .unwrap();
+
+        let (name, name_target, id, second) = {
+            let archived = access::<Archived<Vec<Record>>, Error>(&bytes)
+                .unwrap();
+            let base = bytes.as_ptr() as usize;
+            (
+                &archived[0].name as *const _ as usize - base,
+                &*archived[0].name as *const _ as usize - base,
+                &archived[0].id as *const _ as usize - base,
+                &archived[1] as *const _ as usize - base,
+            )
+        };
+
+        // An interned `[u8; 4]` which points to an interned `String`.
+        let error = check(&retarget(&bytes, id, name_target)).unwrap_err();
+        assert!(
+            error.to_string().contains("claimed as two different types"),
+            "{}",
+            error,
+        );
+
+        // An interned `String` which points into the non-interned data of the
+        // enclosing vector.
+        let error = check(&retarget(&bytes, name, second)).unwrap_err();
+        assert!(error.to_string().contains("overran range"), "{}", error);
+
+        // An interned `String` which points past the end of the archive.
+        let error = check(&retarget(&bytes, name, bytes.len())).unwrap_err();
+        assert!(error.to_string().contains("overran range"), "{}", error);
+    }
+
+    #[test]
+    fn relocate_archives() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+            #[rkyv(with = DerefIntern)]
+            alias: String,
+            #[rkyv(with = BorrowIntern<str>)]
+            team: Box<str>,
+        }
+
+        let segment = |offset: usize| {
+            (0..100)
+                .map(|i| Log {
+                    user: USERS[(i + offset) % USERS.len()].to_string(),
+                    alias: USERS[(i + offset + 1) % USERS.len()].to_string(),
+                    team: USERS[(i + offset + 2) % USERS.len()].into(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let values = [segment(0), segment(1)];
+
+        let mut buffer = AlignedVec::<8>::new();
+        let mut ranges = Vec::new();
+        buffer.extend_from_slice(&[0xff; 24]);
+        for value in values.iter() {
+            let (bytes, _) =
+                serialize_with::<_, _, Panic>(value, MultiInterner::new())
+                    .always_ok();
+            let start = buffer.len();
+            buffer.extend_from_slice(&bytes);
+            ranges.push(start..buffer.len());
+            while buffer.len() % 8 != 0 {
+                buffer.push(0xff);
+            }
+        }
+
+        for (range, value) in ranges.into_iter().zip(values.iter()) {
+            let archived = unsafe {
+                access_unchecked::<Archived<Vec<Log>>>(&buffer[range])
+            };
+            let deserialized =
+                deserialize::<Vec<Log>, Panic>(archived).always_ok();
+            assert_eq!(&deserialized, value);
+        }
+    }
+
+    #[test]
+    fn serialize_interned_slice() {
+        with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                Interner::<String>::new(),
+            );
+            let serializer = Strategy::<_, Panic>::wrap(&mut serializer);
+
+            let values = [0, 1, 0, 2, 1]
+                .iter()
+                .map(|&i| USERS[i].to_string())
+                .collect::<Vec<_>>();
+            let positions =
+                serializer.serialize_interned_slice(&values).always_ok();
+            assert_eq!(positions.len(), values.len());
+            assert_eq!(positions[0], positions[2]);
+            assert_eq!(positions[1], positions[4]);
+            assert_ne!(positions[0], positions[1]);
+            assert_ne!(positions[0], positions[3]);
+
+            let mut reused = [0; 5];
+            serializer
+                .serialize_interned_into(&values, &mut reused)
+                .always_ok();
+            assert_eq!(reused[..], positions[..]);
+        });
+    }
+
     #[test]
     fn deref_intern_strings() {
         #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
@@ -587,6 +2011,385 @@ mod tests {
         assert_eq!(deserialized, value);
     }
 
+    #[test]
+    fn deref_intern_boxes() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Node {
+            #[rkyv(with = DerefIntern)]
+            name: Box<str>,
+            #[rkyv(with = DerefIntern)]
+            children: Box<[u32]>,
+        }
+
+        let value = (0..100)
+            .map(|i| Node {
+                name: USERS[i % USERS.len()][..i % 3 * 5].into(),
+                children: (0..i as u32 % 3).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .always_ok();
+        assert_eq!(interner.get::<[u32]>().unwrap().len(), 3);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Node>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(*a.name, *b.name);
+            assert_eq!(a.children.len(), b.children.len());
+        }
+
+        let deserialized =
+            deserialize::<Vec<Node>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+        assert!(deserialized.iter().any(|node| node.name.is_empty()));
+        assert!(deserialized.iter().any(|node| node.children.is_empty()));
+    }
+
+    #[cfg(target_has_atomic = "ptr")]
+    #[test]
+    fn deref_intern_smart_pointers() {
+        use ::alloc::sync::Arc;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Node {
+            #[rkyv(with = DerefIntern)]
+            name: Rc<str>,
+            #[rkyv(with = DerefIntern)]
+            children: Arc<[u32]>,
+        }
+
+        let value = (0..100)
+            .map(|i| Node {
+                name: Rc::from(&USERS[i % USERS.len()][..i % 3 * 5]),
+                children: (0..i as u32 % 3).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .always_ok();
+        assert_eq!(interner.get::<[u32]>().unwrap().len(), 3);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Node>>>(&bytes) };
+        let deserialized =
+            deserialize::<Vec<Node>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+        assert!(deserialized.iter().any(|node| node.name.is_empty()));
+        assert!(deserialized.iter().any(|node| node.children.is_empty()));
+    }
+
+    #[test]
+    fn intern_optional_values() {
+        use rkyv::with::Map;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = Map<Intern>)]
+            user: Option<String>,
+            #[rkyv(with = Map<DerefIntern>)]
+            team: Option<String>,
+        }
+
+        let value = (0..100)
+            .map(|i| Log {
+                user: match i % 3 {
+                    0 => None,
+                    1 => Some(String::new()),
+                    _ => Some(USERS[i % USERS.len()].to_string()),
+                },
+                team: (i % 2 == 0).then(|| USERS[0].to_string()),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .always_ok();
+        // `None` values don't create entries.
+        assert_eq!(interner.get::<String>().unwrap().len(), USERS.len() + 1);
+        assert_eq!(interner.get::<str>().unwrap().len(), 1);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        let empty = archived[1].user.as_ref().unwrap();
+        assert!(archived.iter().skip(1).step_by(3).all(|log| {
+            core::ptr::eq(&**log.user.as_ref().unwrap(), &**empty)
+        }));
+        assert!(core::ptr::eq(
+            &**archived[0].team.as_ref().unwrap(),
+            &**archived[2].team.as_ref().unwrap(),
+        ));
+
+        let deserialized =
+            deserialize::<Vec<Log>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_array_elements() {
+        use rkyv::with::Map;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Record {
+            #[rkyv(with = Map<Intern>)]
+            none: [String; 0],
+            #[rkyv(with = Map<Intern>)]
+            same: [String; 8],
+            #[rkyv(with = Map<Intern>)]
+            mixed: [String; 8],
+        }
+
+        let value = (0..10)
+            .map(|i| Record {
+                none: [],
+                same: core::array::from_fn(|_| USERS[0].to_string()),
+                mixed: core::array::from_fn(|j| {
+                    USERS[(i + j) % USERS.len()].to_string()
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).always_ok();
+        assert_eq!(interner.len(), USERS.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Record>>>(&bytes) };
+        let first = &*archived[0].same[0];
+        let mut shared = alloc::collections::BTreeSet::new();
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert!(a.none.is_empty());
+            assert!(a.same.iter().all(|user| core::ptr::eq(&**user, first)));
+            for (user, expected) in a.mixed.iter().zip(b.mixed.iter()) {
+                assert_eq!(**user, *expected);
+                shared.insert(user.as_ptr());
+            }
+        }
+        // Each distinct element is written once, however many arrays and
+        // elements refer to it.
+        assert_eq!(shared.len(), USERS.len());
+        assert!(shared.contains(&first.as_ptr()));
+
+        let deserialized =
+            deserialize::<Vec<Record>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_nested_in_map() {
+        use rkyv::with::Map;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Team {
+            #[rkyv(with = Map<Intern>)]
+            lead: Option<String>,
+            #[rkyv(with = Map<Intern>)]
+            members: Vec<String>,
+            #[rkyv(with = Map<DerefIntern>)]
+            nickname: Option<Box<str>>,
+            #[rkyv(with = Map<DerefIntern>)]
+            aliases: Vec<Box<str>>,
+            #[rkyv(with = Map<BorrowIntern<str>>)]
+            owner: Option<String>,
+            #[rkyv(with = Map<BorrowIntern<str>>)]
+            reviewers: Vec<String>,
+        }
+
+        let user = |i: usize| USERS[i % USERS.len()];
+        let value = (0..20)
+            .map(|i| Team {
+                lead: (i % 2 == 0).then(|| user(i).to_string()),
+                members: (0..i % 5).map(|j| user(j).to_string()).collect(),
+                nickname: (i % 3 == 0).then(|| user(i).into()),
+                aliases: (0..i % 4).map(|j| user(i + j).into()).collect(),
+                owner: (i % 2 == 1).then(|| user(i).to_string()),
+                reviewers: (0..i % 3).map(|j| user(j).to_string()).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .always_ok();
+        assert_eq!(interner.get::<String>().unwrap().len(), USERS.len());
+        assert_eq!(interner.get::<str>().unwrap().len(), USERS.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Team>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.members.len(), b.members.len());
+            assert_eq!(a.aliases.len(), b.aliases.len());
+            assert_eq!(a.owner.as_deref(), b.owner.as_deref());
+        }
+        // `DerefIntern` and `BorrowIntern<str>` share the same `str` values.
+        assert!(core::ptr::eq(
+            archived[3].nickname.as_deref().unwrap().as_ptr(),
+            archived[3].owner.as_deref().unwrap().as_ptr(),
+        ));
+        assert!(core::ptr::eq(
+            &*archived[4].members[1],
+            &*archived[9].members[1],
+        ));
+
+        let deserialized =
+            deserialize::<Vec<Team>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_with_custom_flavor() {
+        use rkyv::{
+            rc::{ArchivedRc, Flavor},
+            string::ArchivedString,
+        };
+
+        struct CustomFlavor;
+
+        impl Flavor for CustomFlavor {
+            const ALLOW_CYCLES: bool = false;
+        }
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = Intern<CustomFlavor>)]
+            user: String,
+            #[rkyv(with = DerefIntern<CustomFlavor>)]
+            team: Box<str>,
+            #[rkyv(with = BorrowIntern<str, CustomFlavor>)]
+            owner: String,
+        }
+
+        let value = (0..20)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+                team: USERS[i % 2].into(),
+                owner: USERS[i % 2].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, _) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .always_ok();
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        let user: &ArchivedRc<ArchivedString, CustomFlavor> =
+            &archived[0].user;
+        assert!(core::ptr::eq(&**user, &*archived[USERS.len()].user));
+        let team: &ArchivedRc<str, CustomFlavor> = &archived[1].team;
+        assert!(core::ptr::eq(&**team, &*archived[1].owner));
+
+        let deserialized =
+            deserialize::<Vec<Log>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_composite_values() {
+        #[derive(Archive, Serialize, Deserialize, Clone, Copy, Debug)]
+        #[derive(Hash, PartialEq, Eq)]
+        enum Lang {
+            En,
+            Fr,
+        }
+
+        #[derive(Archive, Serialize, Deserialize, Clone, Debug)]
+        #[derive(Hash, PartialEq, Eq)]
+        struct Label {
+            lang: Lang,
+            text: String,
+        }
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Widget {
+            #[rkyv(with = Intern)]
+            label: Label,
+            #[rkyv(with = Intern)]
+            tag: (Lang, u32),
+        }
+
+        let value = (0..100)
+            .map(|i| Widget {
+                label: Label {
+                    lang: if i % 2 == 0 { Lang::En } else { Lang::Fr },
+                    text: USERS[i % USERS.len()].to_string(),
+                },
+                tag: (Lang::En, i as u32 % 5),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .always_ok();
+        assert_eq!(interner.get::<Label>().unwrap().len(), 4);
+        assert_eq!(interner.get::<(Lang, u32)>().unwrap().len(), 5);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Widget>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.label.text, b.label.text);
+            assert_eq!(a.tag.1, b.tag.1);
+        }
+        assert!(core::ptr::eq(&*archived[0].label, &*archived[4].label));
+
+        let deserialized =
+            deserialize::<Vec<Widget>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_empty_values() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Empty {
+            #[rkyv(with = Intern)]
+            string: String,
+            #[rkyv(with = DerefIntern)]
+            deref_str: String,
+            #[rkyv(with = BorrowIntern<str>)]
+            borrow_str: String,
+            #[rkyv(with = DerefIntern)]
+            deref_bytes: Vec<u8>,
+            #[rkyv(with = BorrowIntern<[u8]>)]
+            borrow_bytes: Vec<u8>,
+        }
+
+        let value = (0..10)
+            .map(|_| Empty {
+                string: String::new(),
+                deref_str: String::new(),
+                borrow_str: String::new(),
+                deref_bytes: Vec::new(),
+                borrow_bytes: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .always_ok();
+        assert_eq!(interner.get::<String>().unwrap().len(), 1);
+        assert_eq!(interner.get::<str>().unwrap().len(), 1);
+        assert_eq!(interner.get::<[u8]>().unwrap().len(), 1);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Empty>>>(&bytes) };
+        let first = &archived[0];
+        assert!(!core::ptr::eq(
+            first.deref_str.as_ptr(),
+            first.deref_bytes.as_ptr(),
+        ));
+        for empty in archived.iter() {
+            assert!(core::ptr::eq(&*empty.string, &*first.string));
+            assert!(core::ptr::eq(&*empty.deref_str, &*first.deref_str));
+            assert!(core::ptr::eq(&*empty.borrow_str, &*first.deref_str));
+            assert!(core::ptr::eq(&*empty.deref_bytes, &*first.deref_bytes));
+            assert!(core::ptr::eq(&*empty.borrow_bytes, &*first.deref_bytes));
+        }
+
+        let deserialized =
+            deserialize::<Vec<Empty>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+    }
+
     #[test]
     fn borrow_intern_strings() {
         #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
@@ -618,4 +2421,185 @@ mod tests {
         let deserialized = deserialize::<Vec<Log>, Panic>(archived).always_ok();
         assert_eq!(deserialized, value);
     }
+
+    #[test]
+    fn custom_error_type() {
+        #[derive(Debug, PartialEq)]
+        struct CustomError;
+
+        impl InterningError for CustomError {
+            fn new<T: Error + Send + Sync + 'static>(_: T) -> Self {
+                CustomError
+            }
+        }
+
+        // Serializing strings and vectors requires a `Source` error, so this
+        // interns integers in an array instead.
+        #[derive(Archive, Serialize)]
+        struct Record {
+            #[rkyv(with = Intern)]
+            id: u64,
+        }
+
+        let value = core::array::from_fn::<_, 16, _>(|i| Record {
+            id: i as u64 % 3,
+        });
+
+        let (bytes, interner) = serialize_with::<_, _, CustomError>(
+            &value,
+            Interner::<u64>::new(),
+        )
+        .unwrap();
+        assert_eq!(interner.len(), 3);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<[Record; 16]>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(*a.id, b.id);
+        }
+
+        let mut interner = Interner::<String>::new();
+        let state = Some((USERS[0], 0));
+        assert_eq!(
+            Interning::<str, CustomError>::finish_interning(
+                &mut interner,
+                state,
+                0,
+            ),
+            Err(CustomError),
+        );
+    }
+
+    #[test]
+    fn inspect_adapter() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = Intern)]
+            user: String,
+        }
+
+        let value = (0..100)
+            .map(|i| Log {
+                user: USERS[i % 2].to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                Interner::<String>::new(),
+            );
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+
+            assert_eq!(serializer.interning().len(), 2);
+            #[cfg(feature = "statistics")]
+            for (_, entry) in serializer.interning().iter() {
+                assert_eq!(entry.ref_cnt, 50);
+            }
+
+            serializer.interning_mut().clear();
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+            assert_eq!(serializer.interning().len(), 2);
+        });
+    }
+
+    #[test]
+    fn count_interned_bytes() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = DerefIntern)]
+            user: String,
+            code: u32,
+        }
+
+        let value = (0..100)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+                code: i as u32,
+            })
+            .collect::<Vec<_>>();
+
+        with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                Interner::<String>::new(),
+            );
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+
+            // String slices don't need any padding for alignment.
+            assert_eq!(
+                serializer.interned_bytes(),
+                USERS.iter().map(|user| user.len()).sum::<usize>(),
+            );
+        });
+    }
+
+    #[test]
+    fn report_interned_writes() {
+        with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                Interner::<String>::new(),
+            );
+            let serializer = Strategy::<_, Panic>::wrap(&mut serializer);
+
+            let mut intern = |value: &str| {
+                serializer.serialize_interned_reporting(value).always_ok()
+            };
+            let (first, written) = intern(USERS[0]);
+            assert!(written);
+            assert_eq!(intern(USERS[0]), (first, false));
+            let (second, written) = intern(USERS[1]);
+            assert!(written);
+            assert_ne!(first, second);
+            assert_eq!(intern(USERS[1]), (second, false));
+            assert_eq!(intern(USERS[0]), (first, false));
+        });
+    }
+
+    #[test]
+    fn intern_with_shared_pointers() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Node {
+            #[rkyv(with = DerefIntern)]
+            name: String,
+            shared: Rc<str>,
+        }
+
+        let shared = [Rc::<str>::from(""), Rc::from(USERS[0])];
+        let value = (0..100)
+            .map(|i| Node {
+                name: ["", USERS[0]][i % 2].to_string(),
+                shared: shared[i / 2 % 2].clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(
+                    AlignedVec::<8>::new(),
+                    arena.acquire(),
+                    Share::new(),
+                ),
+                Interner::<String>::new(),
+            );
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+            serializer.into_serializer().into_writer()
+        });
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Node>>>(&bytes) };
+        let mut positions = Vec::new();
+        for node in archived.iter() {
+            positions.push(node.name.as_ptr());
+            positions.push(node.shared.as_ptr());
+        }
+        positions.sort();
+        positions.dedup();
+        assert_eq!(positions.len(), 4);
+
+        let deserialized =
+            deserialize::<Vec<Node>, Panic>(archived).always_ok();
+        assert_eq!(deserialized, value);
+    }
 }
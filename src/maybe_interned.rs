@@ -0,0 +1,164 @@
+use core::{borrow::Borrow, fmt, ops::Deref};
+
+use rkyv::{
+    munge::munge,
+    rc::{ArchivedRc, RcResolver},
+    traits::{ArchivePointee, NoUndef},
+    Archive, ArchiveUnsized, Place, Portable,
+};
+
+use crate::InternFlavor;
+
+/// An archived value which was either stored inline or interned.
+///
+/// This is the archived type of wrappers which only intern some of their
+/// values, like [`MinLenIntern`](crate::MinLenIntern). `T` is the type that
+/// both variants can be borrowed as, and `I` is the archived type of inline
+/// values.
+#[derive(Portable)]
+#[rkyv(crate = rkyv)]
+#[cfg_attr(
+    feature = "bytecheck",
+    derive(rkyv::bytecheck::CheckBytes),
+    bytecheck(crate = rkyv::bytecheck)
+)]
+#[repr(u8)]
+pub enum ArchivedMaybeInterned<T: ArchivePointee + ?Sized, I> {
+    /// The value was stored inline.
+    Inline(I),
+    /// The value was interned.
+    Interned(ArchivedRc<T, InternFlavor>),
+}
+
+impl<T: ArchivePointee + ?Sized, I> ArchivedMaybeInterned<T, I> {
+    /// Returns `true` if the value was stored inline.
+    pub fn is_inline(&self) -> bool {
+        matches!(self, Self::Inline(_))
+    }
+
+    /// Returns `true` if the value was interned.
+    pub fn is_interned(&self) -> bool {
+        matches!(self, Self::Interned(_))
+    }
+
+    /// Gets the archived value.
+    pub fn get(&self) -> &T
+    where
+        I: Borrow<T>,
+    {
+        match self {
+            Self::Inline(value) => value.borrow(),
+            Self::Interned(value) => value.get(),
+        }
+    }
+}
+
+impl<T, I> Borrow<T> for ArchivedMaybeInterned<T, I>
+where
+    T: ArchivePointee + ?Sized,
+    I: Borrow<T>,
+{
+    fn borrow(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T, I> Deref for ArchivedMaybeInterned<T, I>
+where
+    T: ArchivePointee + ?Sized,
+    I: Borrow<T>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
+
+impl<T, I> fmt::Debug for ArchivedMaybeInterned<T, I>
+where
+    T: ArchivePointee + fmt::Debug + ?Sized,
+    I: Borrow<T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl<T, I> PartialEq for ArchivedMaybeInterned<T, I>
+where
+    T: ArchivePointee + PartialEq + ?Sized,
+    I: Borrow<T>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<T, I> Eq for ArchivedMaybeInterned<T, I>
+where
+    T: ArchivePointee + Eq + ?Sized,
+    I: Borrow<T>,
+{
+}
+
+/// The resolver for [`ArchivedMaybeInterned`].
+pub enum MaybeInternedResolver<R> {
+    /// The value was stored inline.
+    Inline(R),
+    /// The value was interned.
+    Interned(RcResolver),
+}
+
+#[allow(dead_code)]
+#[repr(u8)]
+enum ArchivedTag {
+    Inline,
+    Interned,
+}
+
+// SAFETY: `ArchivedTag` is `repr(u8)` and so always consists of a single
+// well-defined byte.
+unsafe impl NoUndef for ArchivedTag {}
+
+#[repr(C)]
+struct InlineVariant<I>(ArchivedTag, I);
+
+#[repr(C)]
+struct InternedVariant<T: ArchivePointee + ?Sized>(
+    ArchivedTag,
+    ArchivedRc<T, InternFlavor>,
+);
+
+impl<T: ArchivePointee + ?Sized, I> ArchivedMaybeInterned<T, I> {
+    /// Resolves an `ArchivedMaybeInterned` from an inline value or a shared
+    /// reference.
+    ///
+    /// `inline` is only resolved if `resolver` is an inline resolver, and
+    /// `shared` is only resolved if it is an interned resolver.
+    pub fn resolve_from_parts<V, U>(
+        inline: &V,
+        shared: &U,
+        resolver: MaybeInternedResolver<V::Resolver>,
+        out: Place<Self>,
+    ) where
+        V: Archive<Archived = I> + ?Sized,
+        U: ArchiveUnsized<Archived = T> + ?Sized,
+    {
+        match resolver {
+            MaybeInternedResolver::Inline(resolver) => {
+                let out = unsafe { out.cast_unchecked::<InlineVariant<I>>() };
+                munge!(let InlineVariant(tag, value) = out);
+                tag.write(ArchivedTag::Inline);
+                inline.resolve(resolver, value);
+            }
+            MaybeInternedResolver::Interned(resolver) => {
+                let out =
+                    unsafe { out.cast_unchecked::<InternedVariant<T>>() };
+                munge!(let InternedVariant(tag, value) = out);
+                tag.write(ArchivedTag::Interned);
+                ArchivedRc::resolve_from_ref(shared, resolver, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use alloc::boxed::Box;
+use core::ops::Deref;
+
+use rkyv::{
+    rancor::{Fallible, Source},
+    ser::Writer,
+    traits::{ArchivePointee as _, LayoutRaw},
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    Archive, ArchiveUnsized, Deserialize, DeserializeUnsized, Place, Serialize,
+    SerializeUnsized,
+};
+
+use crate::{
+    deserialize_boxed, ArchivedMaybeInterned, Interning, InterningError,
+    InterningExt as _, MaybeInternedResolver,
+};
+
+/// A wrapper that only interns strings which are at least `N` bytes long.
+///
+/// Interning a short string can cost more than it saves, since every interned
+/// value is accessed through a relative pointer. Strings shorter than `N` bytes
+/// are archived inline instead, and are never added to the interner.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::MinLenIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = MinLenIntern<4>)]
+///     name: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MinLenIntern<const N: usize>;
+
+impl<T, const N: usize> ArchiveWith<T> for MinLenIntern<N>
+where
+    T: Archive + Deref<Target = str>,
+{
+    type Archived = ArchivedMaybeInterned<str, T::Archived>;
+    type Resolver = MaybeInternedResolver<T::Resolver>;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedMaybeInterned::resolve_from_parts(
+            field,
+            field.deref(),
+            resolver,
+            out,
+        );
+    }
+}
+
+impl<T, S, const N: usize> SerializeWith<T, S> for MinLenIntern<N>
+where
+    T: Serialize<S> + Deref<Target = str>,
+    S: Interning<str> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        if field.len() >= N {
+            let resolver = serializer.resolve_interned(field.deref())?;
+            Ok(MaybeInternedResolver::Interned(resolver))
+        } else {
+            Ok(MaybeInternedResolver::Inline(field.serialize(serializer)?))
+        }
+    }
+}
+
+impl<T, D, const N: usize>
+    DeserializeWith<ArchivedMaybeInterned<str, T::Archived>, T, D>
+    for MinLenIntern<N>
+where
+    T: Archive + Deref<Target = str> + From<Box<str>>,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedMaybeInterned<str, T::Archived>,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        match field {
+            ArchivedMaybeInterned::Inline(value) => {
+                value.deserialize(deserializer)
+            }
+            ArchivedMaybeInterned::Interned(value) => {
+                Ok(T::from(deserialize_boxed(value.get(), deserializer)?))
+            }
+        }
+    }
+}
+
+/// A wrapper that only interns values whose archived size is at least `N`
+/// bytes.
+///
+/// This generalizes [`MinLenIntern`] to any field which derefs to a value that
+/// [`DerefIntern`](crate::DerefIntern) could intern, like `Box<[T]>` or
+/// `Vec<T>`. The archived size of the target is computed from its metadata, so
+/// a `Vec<u32>` of four elements has an archived size of 16 bytes. Smaller
+/// values are archived inline as the field's own archived type and are never
+/// added to the interner. Unlike [`InternLarge`](crate::InternLarge), the
+/// decision is made for each value instead of for each type.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::MinSizeIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = MinSizeIntern<32>)]
+///     samples: Vec<u32>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MinSizeIntern<const N: usize>;
+
+impl<const N: usize> MinSizeIntern<N> {
+    /// Returns `true` if `value` is interned.
+    pub fn interns<U>(value: &U) -> bool
+    where
+        U: ArchiveUnsized + ?Sized,
+        U::Archived: LayoutRaw,
+    {
+        let metadata =
+            U::Archived::pointer_metadata(&value.archived_metadata());
+        U::Archived::layout_raw(metadata)
+            .map_or(true, |layout| layout.size() >= N)
+    }
+}
+
+impl<T, const N: usize> ArchiveWith<T> for MinSizeIntern<N>
+where
+    T: Archive + Deref,
+    T::Target: ArchiveUnsized,
+{
+    type Archived = ArchivedMaybeInterned<
+        <T::Target as ArchiveUnsized>::Archived,
+        T::Archived,
+    >;
+    type Resolver = MaybeInternedResolver<T::Resolver>;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedMaybeInterned::resolve_from_parts(
+            field,
+            field.deref(),
+            resolver,
+            out,
+        );
+    }
+}
+
+impl<T, S, const N: usize> SerializeWith<T, S> for MinSizeIntern<N>
+where
+    T: Serialize<S> + Deref,
+    T::Target: SerializeUnsized<S>,
+    <T::Target as ArchiveUnsized>::Archived: LayoutRaw,
+    S: Interning<T::Target> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        if Self::interns(field.deref()) {
+            let resolver = serializer.resolve_interned(field.deref())?;
+            Ok(MaybeInternedResolver::Interned(resolver))
+        } else {
+            Ok(MaybeInternedResolver::Inline(field.serialize(serializer)?))
+        }
+    }
+}
+
+impl<T, D, const N: usize>
+    DeserializeWith<
+        ArchivedMaybeInterned<
+            <T::Target as ArchiveUnsized>::Archived,
+            T::Archived,
+        >,
+        T,
+        D,
+    > for MinSizeIntern<N>
+where
+    T: Archive + Deref + From<Box<T::Target>>,
+    T::Archived: Deserialize<T, D>,
+    T::Target: ArchiveUnsized + LayoutRaw,
+    <T::Target as ArchiveUnsized>::Archived: DeserializeUnsized<T::Target, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedMaybeInterned<
+            <T::Target as ArchiveUnsized>::Archived,
+            T::Archived,
+        >,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        match field {
+            ArchivedMaybeInterned::Inline(value) => {
+                value.deserialize(deserializer)
+            }
+            ArchivedMaybeInterned::Interned(value) => {
+                Ok(T::from(deserialize_boxed(value.get(), deserializer)?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_with, serialize_with_interner},
+        Interner, MinLenIntern, MinSizeIntern,
+    };
+
+    #[test]
+    fn min_len_intern_strings() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Tag {
+            #[rkyv(with = MinLenIntern<4>)]
+            name: String,
+        }
+
+        const NAMES: [&str; 4] = ["a", "abc", "abcd", "a much longer name"];
+
+        let value = (0..100)
+            .map(|i| Tag {
+                name: NAMES[i % NAMES.len()].into(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        let mut interned = interner.iter().map(|(k, _)| k).collect::<Vec<_>>();
+        interned.sort();
+        assert_eq!(interned, [NAMES[3], NAMES[2]]);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Tag>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.name.is_interned(), b.name.len() >= 4);
+            assert_eq!(&*a.name, b.name);
+        }
+
+        let deserialized = deserialize::<Vec<Tag>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn min_size_intern_slices() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Reading {
+            #[rkyv(with = MinSizeIntern<8>)]
+            samples: Vec<u32>,
+        }
+
+        assert!(!MinSizeIntern::<8>::interns(&[1u32][..]));
+        assert!(MinSizeIntern::<8>::interns(&[1u32, 2][..]));
+
+        let value = (0..20)
+            .map(|i| Reading {
+                samples: (0..i % 4).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) = serialize_with::<_, _, Panic>(
+            &value,
+            Interner::<Vec<u32>>::new(),
+        )
+        .unwrap();
+        assert_eq!(interner.len(), 2);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Reading>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.samples.is_interned(), b.samples.len() >= 2);
+            assert_eq!(*a.samples, b.samples[..]);
+        }
+        assert!(core::ptr::eq(
+            archived[3].samples.as_ptr(),
+            archived[7].samples.as_ptr(),
+        ));
+
+        let deserialized =
+            deserialize::<Vec<Reading>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+}
@@ -0,0 +1,293 @@
+#[cfg(feature = "statistics")]
+use alloc::collections::BTreeMap;
+use alloc::{borrow::ToOwned, boxed::Box};
+use core::{
+    any::{type_name, Any, TypeId},
+    borrow::Borrow,
+    hash::Hash,
+};
+
+use hashbrown::HashMap;
+
+use crate::{Interner, Interning, InterningError, InterningState};
+
+/// An interner which pools each type of value in its own table.
+///
+/// Interning a value dispatches to an [`Interner`] for the type being interned,
+/// which is created the first time a value of that type is interned. Each
+/// table is keyed by the interned type rather than the field type: a `String`
+/// field with [`Intern`](crate::Intern) is pooled in the `String` table, while
+/// a `String` field with [`DerefIntern`](crate::DerefIntern) is pooled in the
+/// `str` table. This keeps values with different archived representations
+/// apart, so a single `MultiInterner` can be used with any mix of wrappers.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{
+///     rancor::Error,
+///     ser::{allocator::Arena, Serializer},
+///     util::AlignedVec,
+/// };
+/// use rkyv_intern::{Intern, InterningAdapter, MultiInterner};
+///
+/// #[derive(rkyv::Archive, rkyv::Serialize)]
+/// struct Example {
+///     #[rkyv(with = Intern)]
+///     name: String,
+///     #[rkyv(with = Intern)]
+///     data: Vec<u8>,
+/// }
+///
+/// let value = Example {
+///     name: "example".to_string(),
+///     data: b"example".to_vec(),
+/// };
+///
+/// let mut arena = Arena::new();
+/// let mut serializer = InterningAdapter::new(
+///     Serializer::new(AlignedVec::<16>::new(), arena.acquire(), ()),
+///     MultiInterner::new(),
+/// );
+/// rkyv::api::serialize_using::<_, Error>(&value, &mut serializer).unwrap();
+///
+/// let (_, interner) = serializer.into_components();
+/// assert_eq!(interner.get::<String>().unwrap().len(), 1);
+/// assert_eq!(interner.get::<Vec<u8>>().unwrap().len(), 1);
+/// assert!(interner.get::<str>().is_none());
+/// ```
+///
+/// # Consistency checks
+///
+/// When the `debug-checks` feature is enabled, each table remembers the type
+/// of values it was created for. Interning a value checks that it was routed
+/// to a table for its own type, and panics otherwise.
+#[derive(Default)]
+pub struct MultiInterner {
+    interners: HashMap<TypeId, Table>,
+}
+
+struct Table {
+    interner: Box<dyn Any>,
+    #[cfg(feature = "debug-checks")]
+    value_type: (TypeId, &'static str),
+    #[cfg(feature = "statistics")]
+    ref_count_histogram: fn(&dyn Any) -> BTreeMap<usize, usize>,
+}
+
+impl Table {
+    fn new<T>() -> Self
+    where
+        T: ToOwned + ?Sized + 'static,
+        T::Owned: 'static,
+    {
+        Self {
+            interner: Box::new(Interner::<T::Owned>::new()),
+            #[cfg(feature = "debug-checks")]
+            value_type: (TypeId::of::<T>(), type_name::<T>()),
+            #[cfg(feature = "statistics")]
+            ref_count_histogram: |interner| {
+                interner
+                    .downcast_ref::<Interner<T::Owned>>()
+                    .unwrap_or_else(|| wrong_table::<T>())
+                    .ref_count_histogram()
+            },
+        }
+    }
+
+    fn get<T>(&self) -> &Interner<T::Owned>
+    where
+        T: ToOwned + ?Sized + 'static,
+        T::Owned: 'static,
+    {
+        self.check::<T>();
+        self.interner.downcast_ref().unwrap_or_else(|| wrong_table::<T>())
+    }
+
+    fn get_mut<T>(&mut self) -> &mut Interner<T::Owned>
+    where
+        T: ToOwned + ?Sized + 'static,
+        T::Owned: 'static,
+    {
+        self.check::<T>();
+        self.interner.downcast_mut().unwrap_or_else(|| wrong_table::<T>())
+    }
+
+    /// Checks that values of type `T` belong in this table.
+    fn check<T: ?Sized + 'static>(&self) {
+        #[cfg(feature = "debug-checks")]
+        assert!(
+            self.value_type.0 == TypeId::of::<T>(),
+            "value of type `{}` was routed to the table for `{}`",
+            type_name::<T>(),
+            self.value_type.1,
+        );
+    }
+}
+
+fn wrong_table<T: ?Sized>() -> ! {
+    panic!("the table for `{}` has the wrong type", type_name::<T>())
+}
+
+impl MultiInterner {
+    /// Returns a new interner with no tables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the table for values of type `T`, if any values of that type
+    /// have been interned.
+    pub fn get<T>(&self) -> Option<&Interner<T::Owned>>
+    where
+        T: ToOwned + ?Sized + 'static,
+        T::Owned: 'static,
+    {
+        self.interners
+            .get(&TypeId::of::<T>())
+            .map(Table::get::<T>)
+    }
+
+    /// Returns the table for values of type `T`, creating it if it doesn't
+    /// exist.
+    pub fn get_mut<T>(&mut self) -> &mut Interner<T::Owned>
+    where
+        T: ToOwned + ?Sized + 'static,
+        T::Owned: 'static,
+    {
+        self.interners
+            .entry(TypeId::of::<T>())
+            .or_insert_with(Table::new::<T>)
+            .get_mut::<T>()
+    }
+
+    /// Returns the number of values with each reference count, across all
+    /// tables.
+    ///
+    /// See [`Interner::ref_count_histogram`] for details.
+    #[cfg(feature = "statistics")]
+    pub fn ref_count_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for table in self.interners.values() {
+            let table_histogram = (table.ref_count_histogram)(&*table.interner);
+            for (ref_cnt, count) in table_histogram {
+                *histogram.entry(ref_cnt).or_insert(0) += count;
+            }
+        }
+        histogram
+    }
+}
+
+impl<T, E> Interning<T, E> for MultiInterner
+where
+    T::Owned: Hash + Eq + Borrow<T> + 'static,
+    T: Hash + Eq + ToOwned + ?Sized + 'static,
+    E: InterningError,
+{
+    type State<'a> = <Interner<T::Owned> as Interning<T, E>>::State<'a>
+    where
+        T: 'a;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a T,
+    ) -> InterningState<Self::State<'a>> {
+        Interning::<T, E>::start_interning(self.get_mut::<T>(), value)
+    }
+
+    fn start_interning_with_hash<'a>(
+        &mut self,
+        value: &'a T,
+        hash: u64,
+    ) -> InterningState<Self::State<'a>> {
+        Interning::<T, E>::start_interning_with_hash(
+            self.get_mut::<T>(),
+            value,
+            hash,
+        )
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        self.get_mut::<T>().finish_interning(state, pos)
+    }
+
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        Interning::<T, E>::record_hit(self.get_mut::<T>(), value, pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_with, USERS},
+        DerefIntern, Intern, MultiInterner,
+    };
+
+    #[test]
+    fn intern_mixed_types() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Upload {
+            #[rkyv(with = Intern)]
+            user: String,
+            #[rkyv(with = DerefIntern)]
+            owner: String,
+            #[rkyv(with = Intern)]
+            data: Vec<u8>,
+        }
+
+        let value = (0..100)
+            .map(|i| Upload {
+                user: USERS[i % USERS.len()].to_string(),
+                owner: USERS[0].to_string(),
+                data: USERS[i % 2].as_bytes().to_vec(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&value, MultiInterner::new())
+                .unwrap();
+        assert_eq!(interner.get::<String>().unwrap().len(), USERS.len());
+        assert_eq!(interner.get::<str>().unwrap().len(), 1);
+        assert_eq!(interner.get::<Vec<u8>>().unwrap().len(), 2);
+        assert!(interner.get::<[u8]>().is_none());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Upload>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Upload>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "debug-checks")]
+    #[test]
+    #[should_panic = "value of type `str` was routed to the table for `[u8]`"]
+    fn misrouted_value() {
+        use core::any::TypeId;
+
+        use rkyv::rancor::Error;
+
+        use super::Table;
+        use crate::{Interning, InterningState};
+
+        let mut interner = MultiInterner::new();
+        interner
+            .interners
+            .insert(TypeId::of::<str>(), Table::new::<[u8]>());
+        match Interning::<str, Error>::start_interning(&mut interner, "a") {
+            InterningState::Started(_) => (),
+            _ => panic!("value should have started interning"),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use rkyv::{
+    niche::niching::Niching,
+    rc::{ArchivedRc, Flavor},
+    traits::ArchivePointee,
+    with::MapNiche,
+    Place, Portable, RelPtr,
+};
+
+use crate::Intern;
+
+/// A niche for interned values which archives `None` as an invalid pointer.
+///
+/// Interned values are archived as shared pointers, and a valid pointer never
+/// points into itself. This niches `None` into a pointer with an offset of
+/// one, like rkyv's `Null` niche does for boxes, so an optional interned value
+/// is archived in the same space as the pointer alone. Use it with rkyv's
+/// `MapNiche` and any wrapper which archives an `ArchivedRc`, like
+/// `MapNiche<DerefIntern, InternNiche>`, or use [`NicheIntern`] for
+/// [`Intern`].
+///
+/// # Example
+///
+/// ```
+/// use core::mem::size_of;
+///
+/// use rkyv::{with::MapNiche, Archive, Archived};
+/// use rkyv_intern::{DerefIntern, InternNiche};
+///
+/// # #[cfg(feature = "alloc")]
+/// # {
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = MapNiche<DerefIntern, InternNiche>)]
+///     nickname: Option<String>,
+///     #[rkyv(with = DerefIntern)]
+///     name: String,
+/// }
+///
+/// assert_eq!(size_of::<Archived<Example>>(), 16);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct InternNiche;
+
+impl<T, F> Niching<ArchivedRc<T, F>> for InternNiche
+where
+    T: ArchivePointee + Portable + ?Sized,
+    F: Flavor,
+{
+    unsafe fn is_niched(niched: *const ArchivedRc<T, F>) -> bool {
+        // `ArchivedRc` is a transparent wrapper around a `RelPtr`.
+        unsafe { (*niched.cast::<RelPtr<T>>()).is_invalid() }
+    }
+
+    fn resolve_niched(out: Place<ArchivedRc<T, F>>) {
+        let out = unsafe { out.cast_unchecked::<RelPtr<T>>() };
+        RelPtr::emplace_invalid(out);
+    }
+}
+
+/// A wrapper that interns optional values, archiving `None` in the space of
+/// the pointer.
+///
+/// This archives an `Option<T>` in the same space as a field interned with
+/// [`Intern`], instead of adding a discriminant like `Map<Intern>` does. See
+/// [`InternNiche`] for details.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::NicheIntern;
+///
+/// # #[cfg(feature = "alloc")]
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = NicheIntern)]
+///     team: Option<String>,
+/// }
+/// ```
+pub type NicheIntern = MapNiche<Intern, InternNiche>;
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::mem::size_of;
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, with::Map, Archive,
+        Archived, Deserialize, Serialize,
+    };
+
+    use crate::{tests::serialize_interned, Intern, NicheIntern};
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Log {
+        #[rkyv(with = NicheIntern)]
+        team: Option<String>,
+    }
+
+    fn logs() -> Vec<Log> {
+        (0..20)
+            .map(|i| Log {
+                team: ["red", "blue"]
+                    .get(i % 3)
+                    .map(|team| team.to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn niched_option_size() {
+        #[derive(Archive)]
+        struct Tagged {
+            #[rkyv(with = Map<Intern>)]
+            team: Option<String>,
+        }
+
+        #[derive(Archive)]
+        struct Required {
+            #[rkyv(with = Intern)]
+            team: String,
+        }
+
+        assert_eq!(size_of::<Archived<Log>>(), size_of::<Archived<Required>>());
+        assert!(size_of::<Archived<Log>>() < size_of::<Archived<Tagged>>());
+    }
+
+    #[test]
+    fn niched_option_round_trip() {
+        let value = logs();
+        let bytes = serialize_interned::<_, Panic>(&value).unwrap();
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(a.team.as_ref().map(|team| &***team), b.team.as_deref());
+        }
+        assert!(core::ptr::eq(
+            &**archived[0].team.as_ref().unwrap(),
+            &**archived[3].team.as_ref().unwrap(),
+        ));
+
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "bytecheck")]
+    #[test]
+    fn niched_option_access_checked() {
+        use rkyv::{access, rancor::Error};
+
+        let value = logs();
+        let bytes = serialize_interned::<_, Error>(&value).unwrap();
+        let archived = access::<Archived<Vec<Log>>, Error>(&bytes).unwrap();
+        assert!(archived[2].team.is_none());
+        assert_eq!(
+            archived[1].team.as_ref().map(|team| team.as_str()),
+            Some("blue"),
+        );
+    }
+}
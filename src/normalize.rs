@@ -0,0 +1,191 @@
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+};
+use core::marker::PhantomData;
+
+use rkyv::{
+    rancor::{Fallible, Source},
+    rc::{ArchivedRc, RcResolver},
+    ser::Writer,
+    traits::LayoutRaw,
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    ArchiveUnsized, DeserializeUnsized, Place, SerializeUnsized,
+};
+
+use crate::{
+    deserialize_boxed, InternFlavor, Interning, InterningError,
+    InterningExt as _,
+};
+
+/// A normalization from a value to the form which is interned.
+///
+/// This is used by [`NormalizeIntern`] to choose which form of a value is
+/// interned and written.
+pub trait Normalize<T: ?Sized> {
+    /// The type of the normalized value.
+    type Target: ToOwned + ?Sized;
+
+    /// Normalizes the value.
+    ///
+    /// Values which are already normalized can be returned borrowed.
+    fn normalize(value: &T) -> Cow<'_, Self::Target>;
+}
+
+/// A wrapper that interns the normalized form of a value.
+///
+/// The normalized value is used both to look up the value in the interner and
+/// as the value which is written, so values which are equal after
+/// normalization share a single interned value. Only normalized values are
+/// archived: deserializing yields the normalized value, not the original.
+///
+/// # Example
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::{Normalize, NormalizeIntern};
+///
+/// struct Lowercase;
+///
+/// impl Normalize<String> for Lowercase {
+///     type Target = str;
+///
+///     fn normalize(value: &String) -> Cow<'_, str> {
+///         if value.chars().any(char::is_uppercase) {
+///             Cow::Owned(value.to_lowercase())
+///         } else {
+///             Cow::Borrowed(value)
+///         }
+///     }
+/// }
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = NormalizeIntern<Lowercase>)]
+///     name: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct NormalizeIntern<F: ?Sized> {
+    _phantom: PhantomData<F>,
+}
+
+impl<T, F> ArchiveWith<T> for NormalizeIntern<F>
+where
+    F: Normalize<T> + ?Sized,
+    F::Target: ArchiveUnsized,
+{
+    type Archived =
+        ArchivedRc<<F::Target as ArchiveUnsized>::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedRc::resolve_from_ref(&*F::normalize(field), resolver, out);
+    }
+}
+
+impl<T, S, F> SerializeWith<T, S> for NormalizeIntern<F>
+where
+    F: Normalize<T> + ?Sized,
+    F::Target: SerializeUnsized<S>,
+    S: Interning<F::Target> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        serializer.resolve_interned(&*F::normalize(field))
+    }
+}
+
+impl<T, D, F>
+    DeserializeWith<
+        ArchivedRc<<F::Target as ArchiveUnsized>::Archived, InternFlavor>,
+        T,
+        D,
+    > for NormalizeIntern<F>
+where
+    T: From<Box<F::Target>>,
+    F: Normalize<T> + ?Sized,
+    F::Target: ArchiveUnsized + LayoutRaw,
+    <F::Target as ArchiveUnsized>::Archived: DeserializeUnsized<F::Target, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<
+            <F::Target as ArchiveUnsized>::Archived,
+            InternFlavor,
+        >,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        Ok(T::from(deserialize_boxed(field.get(), deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        borrow::Cow,
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{tests::serialize_with_interner, Normalize, NormalizeIntern};
+
+    struct Lowercase;
+
+    impl Normalize<String> for Lowercase {
+        type Target = str;
+
+        fn normalize(value: &String) -> Cow<'_, str> {
+            if value.chars().any(char::is_uppercase) {
+                Cow::Owned(value.to_lowercase())
+            } else {
+                Cow::Borrowed(value)
+            }
+        }
+    }
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Greeting {
+        #[rkyv(with = NormalizeIntern<Lowercase>)]
+        text: String,
+    }
+
+    #[test]
+    fn normalize_intern_strings() {
+        let value = ["Hello", "HELLO", "hello"]
+            .iter()
+            .map(|text| Greeting {
+                text: text.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        assert_eq!(interner.len(), 1);
+        assert!(interner.contains("hello"));
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Greeting>>>(&bytes) };
+        assert!(archived.iter().all(|greeting| &*greeting.text == "hello"));
+        assert!(core::ptr::eq(&*archived[0].text, &*archived[2].text));
+
+        let deserialized =
+            deserialize::<Vec<Greeting>, Panic>(archived).unwrap();
+        assert!(deserialized.iter().all(|greeting| greeting.text == "hello"));
+    }
+}
@@ -0,0 +1,192 @@
+use std::{ffi::OsStr, string::String};
+
+use rkyv::{
+    rancor::Fallible,
+    rc::{ArchivedRc, RcResolver},
+    ser::Writer,
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    Place,
+};
+
+use crate::{
+    error::InvalidUtf8, InternFlavor, Interning, InterningError,
+    InterningExt as _,
+};
+
+/// A wrapper that interns `OsString`s and `PathBuf`s as UTF-8 strings.
+///
+/// Values are interned as `str`s, so they share their interned values with
+/// `DerefIntern` and `BorrowIntern<str>` fields. This lets paths share a
+/// serializer and an `Interner<String>` with string fields.
+///
+/// # Platform caveats
+///
+/// `OsStr` is not guaranteed to be valid UTF-8. On Unix it may contain
+/// arbitrary bytes, and on Windows it may contain unpaired surrogates. Values
+/// which aren't valid UTF-8 fail to serialize with
+/// [`InvalidUtf8`](crate::error::InvalidUtf8).
+///
+/// This deliberately doesn't intern the raw bytes of `OsStr`s and `Path`s
+/// through `BorrowIntern<OsStr>` or `BorrowIntern<Path>`. Their encoding is
+/// platform-specific, so archived bytes couldn't be read back portably, and
+/// they couldn't share interned values with string fields.
+///
+/// Paths are archived exactly as written: separators are not normalized, so a
+/// Windows-style path archived on Unix deserializes as a single path component
+/// on Unix and vice versa.
+///
+/// # Example
+///
+/// ```
+/// use std::{ffi::OsString, path::PathBuf};
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::AsStringIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = AsStringIntern)]
+///     os_string: OsString,
+///     #[rkyv(with = AsStringIntern)]
+///     path: PathBuf,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsStringIntern;
+
+impl<T: AsRef<OsStr>> ArchiveWith<T> for AsStringIntern {
+    type Archived = ArchivedRc<str, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        // It's safe to unwrap here because if the value wasn't valid UTF-8 it
+        // would have failed to serialize
+        ArchivedRc::resolve_from_ref(
+            field.as_ref().to_str().unwrap(),
+            resolver,
+            out,
+        );
+    }
+}
+
+impl<T, S> SerializeWith<T, S> for AsStringIntern
+where
+    T: AsRef<OsStr>,
+    S: Interning<str> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        let value = match field.as_ref().to_str() {
+            Some(value) => value,
+            None => {
+                let value = field.as_ref().to_string_lossy().into_owned();
+                return Err(InterningError::new(InvalidUtf8(value)));
+            }
+        };
+        serializer.resolve_interned(value)
+    }
+}
+
+impl<T, D> DeserializeWith<ArchivedRc<str, InternFlavor>, T, D>
+    for AsStringIntern
+where
+    T: From<String>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<str, InternFlavor>,
+        _: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        Ok(T::from(String::from(field.get())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, path::PathBuf, vec::Vec};
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{tests::serialize_with_interner, AsStringIntern};
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Artifact {
+        #[rkyv(with = AsStringIntern)]
+        path: PathBuf,
+        #[rkyv(with = AsStringIntern)]
+        target: OsString,
+    }
+
+    fn roundtrip_paths(paths: &[&str]) {
+        let value = (0..100)
+            .map(|i| Artifact {
+                path: paths[i % paths.len()].into(),
+                target: paths[(i + 1) % paths.len()].into(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        assert_eq!(interner.len(), paths.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Artifact>>>(&bytes) };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(*a.path, *b.path.to_str().unwrap());
+            assert_eq!(*a.target, *b.target.to_str().unwrap());
+        }
+
+        let deserialized =
+            deserialize::<Vec<Artifact>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn intern_unix_paths() {
+        roundtrip_paths(&[
+            "/usr/lib/libc.so.6",
+            "/usr/lib/libm.so.6",
+            "/home/user/.cache/build/target",
+        ]);
+    }
+
+    #[test]
+    fn intern_windows_paths() {
+        roundtrip_paths(&[
+            r"C:\Windows\System32\kernel32.dll",
+            r"C:\Users\user\AppData\Local\build\target",
+            r"\\server\share\artifact.bin",
+        ]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn invalid_utf8_paths() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt, string::ToString};
+
+        use rkyv::rancor::BoxedError;
+
+        use crate::error::InvalidUtf8;
+
+        let value = Artifact {
+            path: OsStr::from_bytes(b"/tmp/\xff").into(),
+            target: "target".into(),
+        };
+        let error = serialize_with_interner::<_, BoxedError>(&value)
+            .map(|_| ())
+            .unwrap_err();
+        let source = BoxedError::inner(&error);
+        assert!(source.downcast_ref::<InvalidUtf8>().is_some());
+        assert!(error.to_string().contains("/tmp/\u{fffd}"));
+    }
+}
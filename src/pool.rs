@@ -0,0 +1,268 @@
+use alloc::boxed::Box;
+use core::{
+    any::{Any, TypeId},
+    ops::Deref,
+};
+
+use hashbrown::HashMap;
+use rkyv::{
+    de::{ErasedPtr, Pool, Pooling, PoolingState},
+    rancor::{Fallible, Source, Strategy},
+    rc::{ArchivedRc, RcResolver},
+    ser::Writer,
+    traits::LayoutRaw,
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    ArchiveUnsized, DeserializeUnsized, Place, SerializeUnsized,
+};
+
+use crate::{
+    deserialize_boxed, DerefIntern, InternFlavor, Interning, InterningError,
+};
+
+/// A deserializer which can pool deserialized values of type `P`.
+///
+/// This is used by [`PooledDerefIntern`] to share one deserialized value
+/// between every reference to the same interned value.
+pub trait DeserializePooling<P> {
+    /// Returns the value deserialized from the archived value at `address`, if
+    /// it was pooled.
+    fn get_pooled(&self, address: usize) -> Option<P>;
+
+    /// Pools the value deserialized from the archived value at `address`.
+    fn insert_pooled(&mut self, address: usize, value: P);
+}
+
+impl<T: DeserializePooling<P> + ?Sized, P, E> DeserializePooling<P>
+    for Strategy<T, E>
+{
+    fn get_pooled(&self, address: usize) -> Option<P> {
+        T::get_pooled(self, address)
+    }
+
+    fn insert_pooled(&mut self, address: usize, value: P) {
+        T::insert_pooled(self, address, value);
+    }
+}
+
+/// A pool of deserialized values.
+///
+/// Values are pooled by the address of the archived value they were
+/// deserialized from and by their type, so an `Rc<str>` and an `Arc<str>`
+/// deserialized from the same interned value are pooled separately. The pool
+/// also implements rkyv's `Pooling`, so it can deserialize archives which
+/// contain `Rc`s and `Arc`s as well.
+#[derive(Debug, Default)]
+pub struct DeserializePool {
+    values: HashMap<(usize, TypeId), Box<dyn Any>>,
+    pool: Pool,
+}
+
+impl DeserializePool {
+    /// Returns a new, empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of pooled values.
+    ///
+    /// This doesn't include shared pointers pooled through `Pooling`.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the pool contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl<P: Clone + 'static> DeserializePooling<P> for DeserializePool {
+    fn get_pooled(&self, address: usize) -> Option<P> {
+        self.values
+            .get(&(address, TypeId::of::<P>()))
+            .and_then(|value| value.downcast_ref::<P>())
+            .cloned()
+    }
+
+    fn insert_pooled(&mut self, address: usize, value: P) {
+        self.values
+            .insert((address, TypeId::of::<P>()), Box::new(value));
+    }
+}
+
+impl<E: Source> Pooling<E> for DeserializePool {
+    fn start_pooling(&mut self, address: usize) -> PoolingState {
+        Pooling::<E>::start_pooling(&mut self.pool, address)
+    }
+
+    unsafe fn finish_pooling(
+        &mut self,
+        address: usize,
+        ptr: ErasedPtr,
+        drop: unsafe fn(ErasedPtr),
+    ) -> Result<(), E> {
+        // SAFETY: The safety requirements for `finish_pooling` are the same as
+        // the requirements for calling this function.
+        unsafe { self.pool.finish_pooling(address, ptr, drop) }
+    }
+}
+
+/// A wrapper that interns `Deref` values and shares them when deserialized.
+///
+/// Values are archived exactly like they are by [`DerefIntern`]. When
+/// deserializing, the first reference to each interned value is deserialized
+/// into a new value, which is pooled by the deserializer. Every later
+/// reference gets a clone of the pooled value, so fields like `Rc<str>` share
+/// a single allocation. The deserializer must implement
+/// [`DeserializePooling`], for example with a [`DeserializePool`].
+///
+/// # Example
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use rkyv::{
+///     access_unchecked, rancor::{Error, Strategy}, Archive, Archived,
+///     Deserialize, Serialize,
+/// };
+/// use rkyv_intern::{DeserializePool, PooledDerefIntern};
+///
+/// #[derive(Archive, Serialize, Deserialize)]
+/// struct Log {
+///     #[rkyv(with = PooledDerefIntern)]
+///     user: Rc<str>,
+/// }
+///
+/// let value = vec![
+///     Log { user: Rc::from("alice") },
+///     Log { user: Rc::from("alice") },
+/// ];
+/// let bytes = rkyv_intern::to_bytes::<Error>(&value).unwrap();
+/// let archived = unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+///
+/// let mut pool = DeserializePool::new();
+/// let deserialized: Vec<Log> = archived
+///     .deserialize(Strategy::<_, Error>::wrap(&mut pool))
+///     .unwrap();
+/// assert!(Rc::ptr_eq(&deserialized[0].user, &deserialized[1].user));
+/// ```
+#[derive(Debug)]
+pub struct PooledDerefIntern;
+
+impl<T: Deref> ArchiveWith<T> for PooledDerefIntern
+where
+    T::Target: ArchiveUnsized,
+{
+    type Archived =
+        ArchivedRc<<T::Target as ArchiveUnsized>::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        <DerefIntern>::resolve_with(field, resolver, out);
+    }
+}
+
+impl<T, S> SerializeWith<T, S> for PooledDerefIntern
+where
+    T: Deref,
+    T::Target: SerializeUnsized<S>,
+    S: Interning<T::Target> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        <DerefIntern>::serialize_with(field, serializer)
+    }
+}
+
+impl<T, D>
+    DeserializeWith<
+        ArchivedRc<<T::Target as ArchiveUnsized>::Archived, InternFlavor>,
+        T,
+        D,
+    > for PooledDerefIntern
+where
+    T: Deref + From<Box<T::Target>> + Clone,
+    T::Target: ArchiveUnsized + LayoutRaw,
+    <T::Target as ArchiveUnsized>::Archived: DeserializeUnsized<T::Target, D>,
+    D: DeserializePooling<T> + Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<
+            <T::Target as ArchiveUnsized>::Archived,
+            InternFlavor,
+        >,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        let archived = field.get();
+        let address = archived as *const _ as *const u8 as usize;
+        if let Some(value) = deserializer.get_pooled(address) {
+            return Ok(value);
+        }
+        let value = T::from(deserialize_boxed(archived, deserializer)?);
+        deserializer.insert_pooled(address, value.clone());
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{rc::Rc, string::ToString, sync::Arc, vec::Vec};
+
+    use rkyv::{
+        access_unchecked,
+        rancor::{Panic, Strategy},
+        Archive, Archived, Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_interned, USERS},
+        DeserializePool, PooledDerefIntern,
+    };
+
+    #[test]
+    fn pooled_deref_intern_shares_values() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = PooledDerefIntern)]
+            user: Rc<str>,
+            #[rkyv(with = PooledDerefIntern)]
+            team: Arc<str>,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: Rc::from(USERS[i % USERS.len()].to_string()),
+                team: Arc::from(USERS[0]),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = serialize_interned::<_, Panic>(&value).unwrap();
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+
+        let mut pool = DeserializePool::new();
+        let deserialized: Vec<Log> = archived
+            .deserialize(Strategy::<_, Panic>::wrap(&mut pool))
+            .unwrap();
+        assert_eq!(deserialized, value);
+        assert_eq!(pool.len(), USERS.len() + 1);
+
+        let first = &deserialized[0];
+        assert!(deserialized.iter().step_by(USERS.len()).all(|log| {
+            Rc::ptr_eq(&log.user, &first.user)
+                && Arc::ptr_eq(&log.team, &first.team)
+        }));
+        assert!(!Rc::ptr_eq(&first.user, &deserialized[1].user));
+
+        drop(pool);
+        assert_eq!(Rc::strong_count(&first.user), 1000 / USERS.len());
+    }
+}
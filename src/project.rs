@@ -0,0 +1,206 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+
+use rkyv::{
+    rancor::Fallible,
+    rc::{ArchivedRc, RcResolver},
+    ser::Writer,
+    with::{ArchiveWith, SerializeWith},
+    ArchiveUnsized, Place, SerializeUnsized,
+};
+#[cfg(feature = "alloc")]
+use rkyv::{
+    rancor::Source, traits::LayoutRaw, with::DeserializeWith,
+    DeserializeUnsized,
+};
+
+#[cfg(feature = "alloc")]
+use crate::deserialize_boxed;
+use crate::{InternFlavor, Interning, InterningError, InterningExt as _};
+
+/// A projection from a value to a borrowed value which is interned.
+///
+/// This is used by [`ProjectIntern`] to choose which part of a value is
+/// interned.
+pub trait Project<T: ?Sized> {
+    /// The type of the projected value.
+    type Target: ?Sized;
+
+    /// Projects the value.
+    fn project(value: &T) -> &Self::Target;
+}
+
+/// A wrapper that interns a value projected from a field.
+///
+/// This works like [`BorrowIntern`](crate::BorrowIntern), but uses a
+/// [`Project`] implementation instead of `Borrow`. One projection can be
+/// implemented for several types with the same target, which lets fields of
+/// different types share interned values.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::Archive;
+/// use rkyv_intern::{Project, ProjectIntern};
+///
+/// struct Symbol(String);
+///
+/// impl From<Box<str>> for Symbol {
+///     fn from(value: Box<str>) -> Self {
+///         Symbol(value.into())
+///     }
+/// }
+///
+/// struct SymbolName;
+///
+/// impl Project<Symbol> for SymbolName {
+///     type Target = str;
+///
+///     fn project(value: &Symbol) -> &str {
+///         &value.0
+///     }
+/// }
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = ProjectIntern<SymbolName>)]
+///     name: Symbol,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ProjectIntern<P: ?Sized> {
+    _phantom: PhantomData<P>,
+}
+
+impl<T, P> ArchiveWith<T> for ProjectIntern<P>
+where
+    P: Project<T> + ?Sized,
+    P::Target: ArchiveUnsized,
+{
+    type Archived =
+        ArchivedRc<<P::Target as ArchiveUnsized>::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedRc::resolve_from_ref(P::project(field), resolver, out);
+    }
+}
+
+impl<T, S, P> SerializeWith<T, S> for ProjectIntern<P>
+where
+    P: Project<T> + ?Sized,
+    P::Target: SerializeUnsized<S>,
+    S: Interning<P::Target> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        serializer.resolve_interned(P::project(field))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, D, P>
+    DeserializeWith<
+        ArchivedRc<<P::Target as ArchiveUnsized>::Archived, InternFlavor>,
+        T,
+        D,
+    > for ProjectIntern<P>
+where
+    T: From<Box<P::Target>>,
+    P: Project<T> + ?Sized,
+    P::Target: ArchiveUnsized + LayoutRaw,
+    <P::Target as ArchiveUnsized>::Archived: DeserializeUnsized<P::Target, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<
+            <P::Target as ArchiveUnsized>::Archived,
+            InternFlavor,
+        >,
+        deserializer: &mut D,
+    ) -> Result<T, <D as Fallible>::Error> {
+        Ok(T::from(deserialize_boxed(field.get(), deserializer)?))
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::{
+        boxed::Box,
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_with_interner, USERS},
+        Project, ProjectIntern,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Symbol(String);
+
+    impl From<Box<str>> for Symbol {
+        fn from(value: Box<str>) -> Self {
+            Symbol(value.into())
+        }
+    }
+
+    struct AsStr;
+
+    impl Project<String> for AsStr {
+        type Target = str;
+
+        fn project(value: &String) -> &str {
+            value
+        }
+    }
+
+    impl Project<Symbol> for AsStr {
+        type Target = str;
+
+        fn project(value: &Symbol) -> &str {
+            &value.0
+        }
+    }
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    enum Value {
+        Str(#[rkyv(with = ProjectIntern<AsStr>)] String),
+        Sym(#[rkyv(with = ProjectIntern<AsStr>)] Symbol),
+        Int(u32),
+    }
+
+    #[test]
+    fn project_intern_newtypes() {
+        let value = (0..100)
+            .map(|i| match i % 3 {
+                0 => Value::Str(USERS[i % 2].to_string()),
+                1 => Value::Sym(Symbol(USERS[i % 2].to_string())),
+                _ => Value::Int(i as u32),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with_interner::<_, Panic>(&value).unwrap();
+        assert_eq!(interner.len(), 2);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Value>>>(&bytes) };
+        let deserialized = deserialize::<Vec<Value>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+}
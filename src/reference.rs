@@ -0,0 +1,136 @@
+use rkyv::{
+    rancor::Fallible,
+    rc::{ArchivedRc, RcResolver},
+    ser::Writer,
+    with::{ArchiveWith, SerializeWith},
+    ArchiveUnsized, Place, SerializeUnsized,
+};
+
+use crate::{InternFlavor, Interning, InterningError, InterningExt as _};
+
+/// A wrapper that interns the value behind a reference.
+///
+/// This archives a `&T` the same way as [`DerefIntern`](crate::DerefIntern)
+/// archives an owned pointer to `T`, so borrowed fields like `&'a str` and
+/// `&'a [u8]` share their values with each other and with owned fields that
+/// intern the same type. Like rkyv's `InlineAsBox`, it only supports archiving
+/// and serializing, since a reference can't be deserialized without somewhere
+/// to borrow from. Deserialize into an owned type with the same archived
+/// representation instead, like a `String` field wrapped with `DerefIntern`.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{Archive, Serialize};
+/// use rkyv_intern::InternRef;
+///
+/// #[derive(Archive, Serialize)]
+/// struct Example<'a> {
+///     #[rkyv(with = InternRef)]
+///     name: &'a str,
+///     #[rkyv(with = InternRef)]
+///     data: &'a [u8],
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InternRef;
+
+impl<T> ArchiveWith<&T> for InternRef
+where
+    T: ArchiveUnsized + ?Sized,
+{
+    type Archived = ArchivedRc<T::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &&T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedRc::resolve_from_ref(*field, resolver, out);
+    }
+}
+
+impl<T, S> SerializeWith<&T, S> for InternRef
+where
+    T: SerializeUnsized<S> + ?Sized,
+    S: Interning<T> + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &&T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        serializer.resolve_interned(*field)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::{boxed::Box, string::String, vec::Vec};
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_with, USERS},
+        DerefIntern, InternRef, MultiInterner,
+    };
+
+    #[derive(Archive, Serialize)]
+    struct Borrowed<'a> {
+        #[rkyv(with = InternRef)]
+        name: &'a str,
+        #[rkyv(with = InternRef)]
+        data: &'a [u8],
+    }
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Owned {
+        #[rkyv(with = DerefIntern)]
+        name: String,
+        #[rkyv(with = DerefIntern)]
+        data: Box<[u8]>,
+    }
+
+    #[test]
+    fn intern_references() {
+        let borrowed = (0..20)
+            .map(|i| {
+                let name = USERS[i % USERS.len()];
+                Borrowed {
+                    name,
+                    data: &name.as_bytes()[..i % 3],
+                }
+            })
+            .collect::<Vec<_>>();
+        let owned = borrowed
+            .iter()
+            .map(|value| Owned {
+                name: value.name.into(),
+                data: value.data.into(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) =
+            serialize_with::<_, _, Panic>(&borrowed, MultiInterner::new())
+                .unwrap();
+        assert_eq!(interner.get::<str>().unwrap().len(), USERS.len());
+
+        let (owned_bytes, _) =
+            serialize_with::<_, _, Panic>(&owned, MultiInterner::new())
+                .unwrap();
+        assert_eq!(&*bytes, &*owned_bytes);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Owned>>>(&bytes) };
+        assert!(core::ptr::eq(
+            archived[1].name.as_ptr(),
+            archived[5].name.as_ptr(),
+        ));
+        let deserialized = deserialize::<Vec<Owned>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, owned);
+    }
+}
@@ -0,0 +1,468 @@
+use alloc::rc::Rc;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+
+use hashbrown::HashMap;
+use rkyv::{
+    rancor::{Fallible, Source, Strategy},
+    rc::{ArchivedRc, RcResolver},
+    ser::Writer,
+    traits::LayoutRaw,
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    ArchiveUnsized, DeserializeUnsized, Place, SerializeUnsized,
+};
+
+use crate::{
+    deserialize_boxed, InternFlavor, Interning, InterningAdapter,
+    InterningError, InterningExt as _, InterningState,
+};
+
+/// A cache of the positions of values behind shared pointers.
+///
+/// This is used by [`RcIntern`] to skip looking up values which were already
+/// interned through the same shared pointer.
+pub trait PointerCache {
+    /// Returns the position of the value at `address`, if it was cached.
+    fn cached_pos(&self, address: usize) -> Option<usize>;
+
+    /// Caches the position of the value at `address`.
+    fn cache_pos(&mut self, address: usize, pos: usize);
+}
+
+impl<S, I: PointerCache> PointerCache for InterningAdapter<S, I> {
+    fn cached_pos(&self, address: usize) -> Option<usize> {
+        self.interning().cached_pos(address)
+    }
+
+    fn cache_pos(&mut self, address: usize, pos: usize) {
+        self.interning_mut().cache_pos(address, pos);
+    }
+}
+
+impl<S: PointerCache + ?Sized, E> PointerCache for Strategy<S, E> {
+    fn cached_pos(&self, address: usize) -> Option<usize> {
+        S::cached_pos(self, address)
+    }
+
+    fn cache_pos(&mut self, address: usize, pos: usize) {
+        S::cache_pos(self, address, pos);
+    }
+}
+
+/// An interner which remembers the positions of values behind shared pointers.
+///
+/// This wraps another interner and adds a [`PointerCache`] to it. Values
+/// serialized with [`RcIntern`] are first looked up by the address of their
+/// shared pointer, which avoids hashing them again when the same `Rc` or `Arc`
+/// is serialized several times. Values behind different shared pointers are
+/// still deduplicated by the wrapped interner.
+///
+/// Addresses are only meaningful while the shared pointers they came from are
+/// alive. If the interner is reused after the values it serialized have been
+/// dropped, call [`clear_pointers`](Self::clear_pointers) first so that new
+/// shared pointers at the same addresses aren't mistaken for old ones.
+///
+/// # Example
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use rkyv::{
+///     rancor::Error,
+///     ser::{allocator::Arena, Serializer},
+///     util::AlignedVec,
+/// };
+/// use rkyv_intern::{Interner, InterningAdapter, RcIntern, RcInterner};
+///
+/// #[derive(rkyv::Archive, rkyv::Serialize)]
+/// struct Example {
+///     #[rkyv(with = RcIntern)]
+///     id: Rc<str>,
+/// }
+///
+/// let id: Rc<str> = Rc::from("hello");
+/// let value = vec![Example { id: id.clone() }, Example { id }];
+///
+/// let mut arena = Arena::new();
+/// let mut serializer = InterningAdapter::new(
+///     Serializer::new(AlignedVec::<16>::new(), arena.acquire(), ()),
+///     RcInterner::new(Interner::<String>::new()),
+/// );
+/// rkyv::api::serialize_using::<_, Error>(&value, &mut serializer).unwrap();
+/// let (_, interner) = serializer.into_components();
+/// assert_eq!(interner.pointers_len(), 1);
+/// ```
+pub struct RcInterner<I> {
+    interning: I,
+    address_to_pos: HashMap<usize, usize>,
+}
+
+impl<I> RcInterner<I> {
+    /// Returns a new interner which wraps `interning`.
+    pub fn new(interning: I) -> Self {
+        Self {
+            interning,
+            address_to_pos: HashMap::new(),
+        }
+    }
+
+    /// Returns a reference to the wrapped interner.
+    pub fn interning(&self) -> &I {
+        &self.interning
+    }
+
+    /// Returns a mutable reference to the wrapped interner.
+    pub fn interning_mut(&mut self) -> &mut I {
+        &mut self.interning
+    }
+
+    /// Consumes the interner and returns the wrapped interner.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+
+    /// The number of cached shared pointers.
+    pub fn pointers_len(&self) -> usize {
+        self.address_to_pos.len()
+    }
+
+    /// Removes all cached shared pointers.
+    ///
+    /// This doesn't remove any values from the wrapped interner.
+    pub fn clear_pointers(&mut self) {
+        self.address_to_pos.clear();
+    }
+}
+
+impl<I: Default> Default for RcInterner<I> {
+    fn default() -> Self {
+        Self::new(I::default())
+    }
+}
+
+impl<I> PointerCache for RcInterner<I> {
+    fn cached_pos(&self, address: usize) -> Option<usize> {
+        self.address_to_pos.get(&address).copied()
+    }
+
+    fn cache_pos(&mut self, address: usize, pos: usize) {
+        self.address_to_pos.insert(address, pos);
+    }
+}
+
+impl<I, T, E> Interning<T, E> for RcInterner<I>
+where
+    I: Interning<T, E>,
+    T: ?Sized,
+{
+    type State<'a> = I::State<'a> where T: 'a;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a T,
+    ) -> InterningState<Self::State<'a>> {
+        self.interning.start_interning(value)
+    }
+
+    fn start_interning_with_hash<'a>(
+        &mut self,
+        value: &'a T,
+        hash: u64,
+    ) -> InterningState<Self::State<'a>> {
+        self.interning.start_interning_with_hash(value, hash)
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        self.interning.finish_interning(state, pos)
+    }
+
+    fn record_hit(&mut self, value: &T, pos: usize) {
+        self.interning.record_hit(value, pos);
+    }
+}
+
+/// A wrapper that interns the value behind an `Rc` or `Arc`.
+///
+/// Values are archived like they would be by
+/// [`DerefIntern`](crate::DerefIntern), so shared pointers to equal values
+/// share a single archived copy. The serializer must implement
+/// [`PointerCache`], which lets copies of the same shared pointer skip the
+/// interner entirely. [`RcInterner`] adds a pointer cache to any interner.
+///
+/// Deserializing creates a new shared pointer for each field. `Rc` and `Arc`
+/// fields with equal values share an archived value, so the pointers can't be
+/// pooled without mixing the two.
+///
+/// # Example
+///
+/// ```
+/// use std::{rc::Rc, sync::Arc};
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::RcIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = RcIntern)]
+///     name: Rc<str>,
+///     #[rkyv(with = RcIntern)]
+///     data: Arc<[u8]>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RcIntern;
+
+/// A wrapper that interns the value behind an `Arc`.
+///
+/// This is another name for [`RcIntern`], which handles both `Rc` and `Arc`.
+/// Serializers without a [`PointerCache`] can intern shared pointers with
+/// [`DerefIntern`](crate::DerefIntern) instead, which archives them the same
+/// way.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::ArcIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = ArcIntern)]
+///     name: Arc<str>,
+/// }
+/// ```
+pub type ArcIntern = RcIntern;
+
+fn serialize_shared<T, S>(
+    value: &T,
+    serializer: &mut S,
+) -> Result<RcResolver, <S as Fallible>::Error>
+where
+    T: SerializeUnsized<S> + ?Sized,
+    S: Interning<T> + PointerCache + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    let address = value as *const T as *const u8 as usize;
+    let pos = match serializer.cached_pos(address) {
+        Some(pos) => {
+            serializer.record_hit(value, pos);
+            pos
+        }
+        None => {
+            let pos = serializer.serialize_interned(value)?;
+            serializer.cache_pos(address, pos);
+            pos
+        }
+    };
+    Ok(RcResolver::from_pos(pos))
+}
+
+impl<T: ArchiveUnsized + ?Sized> ArchiveWith<Rc<T>> for RcIntern {
+    type Archived = ArchivedRc<T::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &Rc<T>,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedRc::resolve_from_ref(&**field, resolver, out);
+    }
+}
+
+impl<T, S> SerializeWith<Rc<T>, S> for RcIntern
+where
+    T: SerializeUnsized<S> + ?Sized,
+    S: Interning<T> + PointerCache + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &Rc<T>,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        serialize_shared(&**field, serializer)
+    }
+}
+
+impl<T, D> DeserializeWith<ArchivedRc<T::Archived, InternFlavor>, Rc<T>, D>
+    for RcIntern
+where
+    T: ArchiveUnsized + LayoutRaw + ?Sized,
+    T::Archived: DeserializeUnsized<T, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<T::Archived, InternFlavor>,
+        deserializer: &mut D,
+    ) -> Result<Rc<T>, <D as Fallible>::Error> {
+        Ok(Rc::from(deserialize_boxed(field.get(), deserializer)?))
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<T: ArchiveUnsized + ?Sized> ArchiveWith<Arc<T>> for RcIntern {
+    type Archived = ArchivedRc<T::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &Arc<T>,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedRc::resolve_from_ref(&**field, resolver, out);
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<T, S> SerializeWith<Arc<T>, S> for RcIntern
+where
+    T: SerializeUnsized<S> + ?Sized,
+    S: Interning<T> + PointerCache + Writer + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &Arc<T>,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        serialize_shared(&**field, serializer)
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<T, D> DeserializeWith<ArchivedRc<T::Archived, InternFlavor>, Arc<T>, D>
+    for RcIntern
+where
+    T: ArchiveUnsized + LayoutRaw + ?Sized,
+    T::Archived: DeserializeUnsized<T, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<T::Archived, InternFlavor>,
+        deserializer: &mut D,
+    ) -> Result<Arc<T>, <D as Fallible>::Error> {
+        Ok(Arc::from(deserialize_boxed(field.get(), deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        rc::Rc,
+        string::{String, ToString},
+        sync::Arc,
+        vec::Vec,
+    };
+
+    use rkyv::{
+        access_unchecked, deserialize, rancor::Panic, Archive, Archived,
+        Deserialize, Serialize,
+    };
+
+    use crate::{
+        tests::{serialize_with, USERS},
+        Interner, RcIntern, RcInterner,
+    };
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Log {
+        #[rkyv(with = RcIntern)]
+        user: Rc<str>,
+        #[rkyv(with = RcIntern)]
+        team: Arc<str>,
+    }
+
+    #[test]
+    fn rc_intern_shared_pointers() {
+        let users = USERS
+            .iter()
+            .map(|&user| Rc::<str>::from(user))
+            .collect::<Vec<_>>();
+        let team: Arc<str> = Arc::from(USERS[0]);
+        let value = (0..100)
+            .map(|i| Log {
+                user: users[i % users.len()].clone(),
+                team: team.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) = serialize_with::<_, _, Panic>(
+            &value,
+            RcInterner::new(Interner::<String>::new()),
+        )
+        .unwrap();
+        assert_eq!(interner.pointers_len(), USERS.len() + 1);
+        assert_eq!(interner.interning().len(), USERS.len());
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        assert!(core::ptr::eq(&*archived[0].user, &*archived[0].team));
+        assert!(core::ptr::eq(&*archived[1].user, &*archived[5].user));
+
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn rc_intern_counts_cached_pointers() {
+        use alloc::collections::BTreeMap;
+
+        let users = USERS
+            .iter()
+            .map(|&user| Rc::<str>::from(user))
+            .collect::<Vec<_>>();
+        let team: Arc<str> = Arc::from(USERS[0]);
+        let value = (0..100)
+            .map(|i| Log {
+                user: users[i % users.len()].clone(),
+                team: team.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let (_, interner) = serialize_with::<_, _, Panic>(
+            &value,
+            RcInterner::new(Interner::<String>::new()),
+        )
+        .unwrap();
+        assert_eq!(
+            interner.interning().ref_count_histogram(),
+            BTreeMap::from([(25, 3), (125, 1)]),
+        );
+    }
+
+    #[test]
+    fn rc_intern_equal_values() {
+        let value = (0..100)
+            .map(|i| Log {
+                user: Rc::from(USERS[i % 2].to_string()),
+                team: Arc::from(USERS[i % 2].to_string()),
+            })
+            .collect::<Vec<_>>();
+
+        let (bytes, interner) = serialize_with::<_, _, Panic>(
+            &value,
+            RcInterner::new(Interner::<String>::new()),
+        )
+        .unwrap();
+        assert_eq!(interner.interning().len(), 2);
+
+        let archived =
+            unsafe { access_unchecked::<Archived<Vec<Log>>>(&bytes) };
+        for log in archived.iter() {
+            assert!(core::ptr::eq(&*log.user, &*log.team));
+        }
+        assert!(core::ptr::eq(&*archived[0].user, &*archived[2].user));
+        assert!(!core::ptr::eq(&*archived[0].user, &*archived[1].user));
+
+        let deserialized = deserialize::<Vec<Log>, Panic>(archived).unwrap();
+        assert_eq!(deserialized, value);
+    }
+}
@@ -0,0 +1,411 @@
+use alloc::{string::String, vec::Vec};
+use core::{
+    convert::{TryFrom, TryInto},
+    error::Error,
+    fmt, str,
+};
+
+use hashbrown::HashMap;
+use rkyv::{
+    rancor::{Fallible, Strategy},
+    ser::{Writer, WriterExt as _},
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    Archive, Deserialize, Place, Serialize,
+};
+
+use crate::{InterningAdapter, InterningError};
+
+/// A serializer which can add strings to a [`StringPool`].
+///
+/// This is used by [`PoolIntern`] to replace strings with references into the
+/// pool.
+pub trait StringPooling {
+    /// Returns the offset of the string's entry in the pool, adding it if it
+    /// isn't in the pool yet.
+    fn pool_string(&mut self, value: &str) -> usize;
+}
+
+impl<S, I> StringPooling for InterningAdapter<S, I>
+where
+    I: StringPooling,
+{
+    fn pool_string(&mut self, value: &str) -> usize {
+        self.interning_mut().pool_string(value)
+    }
+}
+
+impl<S, E> StringPooling for Strategy<S, E>
+where
+    S: StringPooling + ?Sized,
+{
+    fn pool_string(&mut self, value: &str) -> usize {
+        S::pool_string(self, value)
+    }
+}
+
+/// A deserializer which can look up strings in a string pool.
+///
+/// This is used by [`PoolIntern`] to replace references into the pool with the
+/// strings they refer to.
+pub trait StringPoolLookup {
+    /// Returns the string with the entry at `offset` in the pool, if there is
+    /// one and its length is `len`.
+    fn pooled_string(&self, offset: usize, len: usize) -> Option<&str>;
+}
+
+impl<D, E> StringPoolLookup for Strategy<D, E>
+where
+    D: StringPoolLookup + ?Sized,
+{
+    fn pooled_string(&self, offset: usize, len: usize) -> Option<&str> {
+        D::pooled_string(self, offset, len)
+    }
+}
+
+impl StringPoolLookup for [u8] {
+    fn pooled_string(&self, offset: usize, len: usize) -> Option<&str> {
+        let prefix = self.get(offset..offset.checked_add(4)?)?;
+        let prefix = u32::from_le_bytes(prefix.try_into().ok()?);
+        if prefix as usize != len {
+            return None;
+        }
+        let start = offset + 4;
+        str::from_utf8(self.get(start..start.checked_add(len)?)?).ok()
+    }
+}
+
+/// A deduplicated pool of length-prefixed strings.
+///
+/// Each distinct string is added to the pool once, as an entry with its length
+/// as a little-endian `u32` followed by its UTF-8 bytes. Entries are padded
+/// with zeros to a multiple of four bytes, so every length prefix is aligned
+/// to four bytes relative to the start of the pool. Entries are in the order
+/// their strings were first added, so a reader can walk the pool by its length
+/// prefixes without any other index.
+///
+/// The pool is built while serializing with [`PoolIntern`], and written with
+/// [`write_to`](Self::write_to) once serialization has finished. References
+/// store offsets relative to the start of the pool, so they stay valid
+/// wherever the pool is written and don't have to be patched afterwards.
+#[derive(Debug, Default)]
+pub struct StringPool {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, usize>,
+}
+
+impl StringPool {
+    /// Returns a new, empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of strings in the pool.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns `true` if the pool contains no strings.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Returns the bytes of the pool.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Consumes the pool and returns its bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Returns the offset of the string's entry, if it is in the pool.
+    pub fn offset_of(&self, value: &str) -> Option<usize> {
+        self.offsets.get(value).copied()
+    }
+
+    /// Writes the pool to `writer` contiguously, returning the position it was
+    /// written at.
+    ///
+    /// The writer is first padded to a multiple of four bytes, so the length
+    /// prefixes are aligned in the written pool as well. A reader can find an
+    /// entry by adding its offset to the returned position.
+    pub fn write_to<W, E>(&self, writer: &mut W) -> Result<usize, E>
+    where
+        W: Writer<E> + ?Sized,
+    {
+        writer.align(4)?;
+        let pos = writer.pos();
+        writer.write(&self.bytes)?;
+        Ok(pos)
+    }
+}
+
+impl StringPooling for StringPool {
+    fn pool_string(&mut self, value: &str) -> usize {
+        if let Some(&offset) = self.offsets.get(value) {
+            return offset;
+        }
+        let offset = self.bytes.len();
+        // Lengths which don't fit in a `u32` are rejected by `PoolIntern`
+        // after pooling, so the prefix doesn't need to be correct for them.
+        let len = u32::try_from(value.len()).unwrap_or(u32::MAX);
+        self.bytes.extend_from_slice(&len.to_le_bytes());
+        self.bytes.extend_from_slice(value.as_bytes());
+        let padded = (self.bytes.len() + 3) & !3;
+        self.bytes.resize(padded, 0);
+        self.offsets.insert(value.into(), offset);
+        offset
+    }
+}
+
+impl StringPoolLookup for StringPool {
+    fn pooled_string(&self, offset: usize, len: usize) -> Option<&str> {
+        self.bytes.pooled_string(offset, len)
+    }
+}
+
+/// A reference to a string in a [`StringPool`].
+#[derive(
+    Archive, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+pub struct PoolRef {
+    /// The offset of the string's entry from the start of the pool.
+    pub offset: u32,
+    /// The length of the string in bytes.
+    pub len: u32,
+}
+
+#[derive(Debug)]
+struct PooledStringTooLarge {
+    offset: usize,
+    len: usize,
+}
+
+impl fmt::Display for PooledStringTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pooled string at offset {} with length {} is too large to be \
+             archived with `u32`s",
+            self.offset, self.len,
+        )
+    }
+}
+
+impl Error for PooledStringTooLarge {}
+
+#[derive(Debug)]
+struct MissingPooledString {
+    offset: usize,
+    len: usize,
+}
+
+impl fmt::Display for MissingPooledString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the string pool has no string of length {} at offset {}",
+            self.len, self.offset,
+        )
+    }
+}
+
+impl Error for MissingPooledString {}
+
+/// A wrapper that replaces strings with references into a shared string pool.
+///
+/// Unlike [`DerefIntern`](crate::DerefIntern), strings aren't written among the
+/// rest of the archive. Each distinct string is added to a [`StringPool`] once,
+/// and each field is archived as a [`PoolRef`] with the offset of the string's
+/// entry in the pool and its length. This suits consumers which expect a single
+/// table of length-prefixed strings, like readers of FlatBuffers-style string
+/// tables, instead of relative pointers. The serializer must implement
+/// [`StringPooling`], for example with an adapter over a `StringPool`.
+///
+/// Once serialization has finished, the pool is written with
+/// [`StringPool::write_to`], for example to the end of the archive or to a
+/// separate buffer. To deserialize, the deserializer must implement
+/// [`StringPoolLookup`], for example with the pool or its bytes.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{
+///     access_unchecked, rancor::{Error, Strategy}, Archive, Archived,
+///     Deserialize, Serialize,
+/// };
+/// use rkyv_intern::{PoolIntern, StringPool};
+///
+/// #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+/// struct Log {
+///     #[rkyv(with = PoolIntern)]
+///     user: String,
+/// }
+///
+/// let value = Log { user: "alice".to_string() };
+/// let (bytes, mut pool) =
+///     rkyv_intern::to_bytes_with::<_, Error>(&value, StringPool::new())
+///         .unwrap();
+///
+/// let archived = unsafe { access_unchecked::<Archived<Log>>(&bytes) };
+/// let offset = archived.user.offset.to_native() as usize;
+/// assert_eq!(&pool.as_bytes()[offset + 4..][..5], b"alice");
+///
+/// let deserialized: Log = archived
+///     .deserialize(Strategy::<_, Error>::wrap(&mut pool))
+///     .unwrap();
+/// assert_eq!(deserialized, value);
+/// ```
+#[derive(Debug)]
+pub struct PoolIntern;
+
+impl<T> ArchiveWith<T> for PoolIntern {
+    type Archived = ArchivedPoolRef;
+    type Resolver = PoolRef;
+
+    fn resolve_with(
+        _: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        let fields = PoolRefResolver {
+            offset: (),
+            len: (),
+        };
+        resolver.resolve(fields, out);
+    }
+}
+
+impl<T, S> SerializeWith<T, S> for PoolIntern
+where
+    T: AsRef<str>,
+    S: StringPooling + Fallible + ?Sized,
+    S::Error: InterningError,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, S::Error> {
+        let value = field.as_ref();
+        let offset = serializer.pool_string(value);
+        let len = value.len();
+        match (u32::try_from(offset), u32::try_from(len)) {
+            (Ok(offset), Ok(len)) => Ok(PoolRef { offset, len }),
+            _ => Err(S::Error::new(PooledStringTooLarge { offset, len })),
+        }
+    }
+}
+
+impl<T, D> DeserializeWith<ArchivedPoolRef, T, D> for PoolIntern
+where
+    T: for<'a> From<&'a str>,
+    D: StringPoolLookup + Fallible + ?Sized,
+    D::Error: InterningError,
+{
+    fn deserialize_with(
+        field: &ArchivedPoolRef,
+        deserializer: &mut D,
+    ) -> Result<T, D::Error> {
+        let offset = field.offset.to_native() as usize;
+        let len = field.len.to_native() as usize;
+        deserializer
+            .pooled_string(offset, len)
+            .map(T::from)
+            .ok_or_else(|| D::Error::new(MissingPooledString { offset, len }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        rc::Rc,
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::mem::size_of;
+
+    use rkyv::{
+        api::access_pos_unchecked,
+        rancor::{Error, Panic, Strategy},
+        Archive, Archived, Deserialize, Serialize,
+    };
+
+    use crate::{
+        to_bytes_with, tests::USERS, PoolIntern, StringPool, StringPoolLookup,
+    };
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Message {
+        #[rkyv(with = PoolIntern)]
+        user: String,
+        #[rkyv(with = PoolIntern)]
+        topic: Rc<str>,
+        text: String,
+    }
+
+    fn walk(pool: &[u8]) -> Vec<(usize, &str)> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset < pool.len() {
+            let mut prefix = [0; 4];
+            prefix.copy_from_slice(&pool[offset..offset + 4]);
+            let len = u32::from_le_bytes(prefix) as usize;
+            let bytes = &pool[offset + 4..offset + 4 + len];
+            entries.push((offset, core::str::from_utf8(bytes).unwrap()));
+            offset = (offset + 4 + len + 3) & !3;
+        }
+        entries
+    }
+
+    #[test]
+    fn contiguous_string_pool() {
+        let value = (0..10)
+            .map(|i| Message {
+                user: USERS[i % 3].to_string(),
+                topic: Rc::from(["", "rkyv"][i % 2]),
+                text: i.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let (mut bytes, mut pool) =
+            to_bytes_with::<_, Panic>(&value, StringPool::new()).unwrap();
+        assert_eq!(pool.len(), 5);
+        let root = bytes.len() - size_of::<Archived<Vec<Message>>>();
+        let pool_pos = pool.write_to::<_, Panic>(&mut bytes).unwrap();
+        assert_eq!(pool_pos % 4, 0);
+
+        // The pool is written contiguously and can be walked by its length
+        // prefixes alone.
+        let section = &bytes[pool_pos..];
+        assert_eq!(section, pool.as_bytes());
+        let entries = walk(section);
+        let strings = entries.iter().map(|&(_, s)| s).collect::<Vec<_>>();
+        assert_eq!(strings, [USERS[0], "", USERS[1], "rkyv", USERS[2]]);
+        for &(offset, string) in entries.iter() {
+            assert_eq!(offset % 4, 0);
+            assert_eq!(pool.offset_of(string), Some(offset));
+        }
+
+        let archived = unsafe {
+            access_pos_unchecked::<Archived<Vec<Message>>>(&bytes, root)
+        };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            let offset = a.user.offset.to_native() as usize;
+            let len = a.user.len.to_native() as usize;
+            assert_eq!(section.pooled_string(offset, len), Some(&*b.user));
+            assert_eq!(a.text, b.text);
+        }
+
+        let deserialized: Vec<Message> = archived
+            .deserialize(Strategy::<_, Panic>::wrap(&mut pool))
+            .unwrap();
+        assert_eq!(deserialized, value);
+
+        let mut truncated = StringPool::new();
+        let result =
+            archived.deserialize(Strategy::<_, Error>::wrap(&mut truncated));
+        let error: Error = result.map(|_: Vec<Message>| ()).unwrap_err();
+        assert!(error.to_string().contains("no string of length"));
+    }
+}
@@ -0,0 +1,156 @@
+use alloc::{borrow::ToOwned, vec::Vec};
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+};
+
+use hashbrown::{hash_table, DefaultHashBuilder, HashTable};
+
+/// A symbol returned by a [`SymbolInterner`].
+///
+/// Symbols are numbered densely in the order their values were first interned,
+/// starting from zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(usize);
+
+impl Symbol {
+    /// Returns the index of the symbol.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A general-purpose interner which maps values to [`Symbol`]s.
+///
+/// Unlike the other interners in this crate, this isn't used for serialization.
+/// Interning a value returns a symbol which can be resolved back to the value,
+/// and interning an equal value returns the same symbol.
+///
+/// # Example
+///
+/// ```
+/// use rkyv_intern::SymbolInterner;
+///
+/// let mut interner = SymbolInterner::<String>::new();
+/// let hello = interner.intern("hello");
+/// let world = interner.intern("world");
+/// assert_eq!(interner.intern("hello"), hello);
+/// assert_eq!(interner.resolve(world).map(String::as_str), Some("world"));
+/// ```
+pub struct SymbolInterner<T> {
+    values: Vec<T>,
+    table: HashTable<usize>,
+    hasher: DefaultHashBuilder,
+}
+
+impl<T> SymbolInterner<T> {
+    /// Returns a new, empty interner.
+    pub fn new() -> Self {
+        Self {
+            values: Vec::new(),
+            table: HashTable::new(),
+            hasher: DefaultHashBuilder::default(),
+        }
+    }
+
+    /// The number of interned values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the interner contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Interns a value, returning its symbol.
+    ///
+    /// The value is cloned if it hasn't been interned before.
+    pub fn intern<Q>(&mut self, value: &Q) -> Symbol
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = T> + ?Sized,
+    {
+        let Self {
+            values,
+            table,
+            hasher,
+        } = self;
+        let hash = hasher.hash_one(value);
+        match table.entry(
+            hash,
+            |&index| values[index].borrow() == value,
+            |&index| hasher.hash_one(values[index].borrow()),
+        ) {
+            hash_table::Entry::Occupied(entry) => Symbol(*entry.get()),
+            hash_table::Entry::Vacant(entry) => {
+                let index = values.len();
+                values.push(value.to_owned());
+                entry.insert(index);
+                Symbol(index)
+            }
+        }
+    }
+
+    /// Returns the symbol of a value if it has been interned.
+    pub fn get<Q>(&self, value: &Q) -> Option<Symbol>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hasher.hash_one(value);
+        self.table
+            .find(hash, |&index| self.values[index].borrow() == value)
+            .map(|&index| Symbol(index))
+    }
+
+    /// Returns the value of a symbol, or `None` if the symbol wasn't returned
+    /// by this interner.
+    pub fn resolve(&self, symbol: Symbol) -> Option<&T> {
+        self.values.get(symbol.0)
+    }
+
+    /// Consumes the interner and returns its values, ordered by the indices
+    /// of their symbols.
+    ///
+    /// This can be used as the dictionary for values serialized with
+    /// [`DictIntern`](crate::DictIntern).
+    pub fn into_dictionary(self) -> Vec<T> {
+        self.values
+    }
+}
+
+impl<T> Default for SymbolInterner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use crate::{tests::USERS, SymbolInterner};
+
+    #[test]
+    fn intern_symbols() {
+        let mut interner = SymbolInterner::<String>::new();
+        let symbols = USERS
+            .iter()
+            .map(|user| interner.intern(*user))
+            .collect::<Vec<_>>();
+        assert!(symbols.iter().map(|symbol| symbol.index()).eq(0..4));
+
+        for i in 0..1000 {
+            let user = USERS[i * 7 % USERS.len()];
+            assert_eq!(interner.intern(user), symbols[i * 7 % USERS.len()]);
+        }
+        assert_eq!(interner.len(), USERS.len());
+
+        for (user, symbol) in USERS.iter().zip(symbols.iter()) {
+            assert_eq!(interner.get(*user), Some(*symbol));
+            assert_eq!(interner.resolve(*symbol).unwrap(), user);
+        }
+        assert_eq!(interner.get("Eve"), None);
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use hashbrown::DefaultHashBuilder;
+
+use crate::{Interner, Interning, InterningState};
+
+/// An interner which can be shared between threads.
+///
+/// This wraps an [`Interner`] in a mutex. `Interning` is implemented for
+/// shared references to it, so several threads can each use an
+/// [`InterningAdapter`](crate::InterningAdapter) with a `&SyncInterner` and
+/// deduplicate values across all of them. Starting and finishing a value each
+/// lock the interner once, so exactly one thread starts each new value and
+/// every other thread sees it as pending until it is finished.
+///
+/// Positions are only meaningful in the buffer they were written to. Every
+/// thread which shares a `SyncInterner` must write to the same buffer, for
+/// example by taking turns appending to it. Threads which write to separate
+/// buffers would refer to each other's positions and produce corrupt archives.
+///
+/// A value which another thread is still serializing is reported as pending,
+/// which [`serialize_interned`](crate::InterningExt::serialize_interned)
+/// treats as a cycle. Threads must finish serializing each value before
+/// another thread interns the same value.
+pub struct SyncInterner<T, S = DefaultHashBuilder> {
+    interner: Mutex<Interner<T, S>>,
+}
+
+impl<T> SyncInterner<T> {
+    /// Returns a new, empty interner.
+    pub fn new() -> Self {
+        Self::from_interner(Interner::new())
+    }
+}
+
+impl<T, S> SyncInterner<T, S> {
+    /// Returns an interner which shares the values of `interner`.
+    pub fn from_interner(interner: Interner<T, S>) -> Self {
+        Self {
+            interner: Mutex::new(interner),
+        }
+    }
+
+    /// Locks the interner and returns the underlying interner.
+    ///
+    /// This blocks while any other thread is using the interner.
+    pub fn lock(&self) -> MutexGuard<'_, Interner<T, S>> {
+        self.interner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The number of interned values.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the interner contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Consumes the interner and returns the underlying interner.
+    pub fn into_inner(self) -> Interner<T, S> {
+        self.interner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T, S: Default> Default for SyncInterner<T, S> {
+    fn default() -> Self {
+        Self::from_interner(Interner::default())
+    }
+}
+
+impl<T, S> From<Interner<T, S>> for SyncInterner<T, S> {
+    fn from(interner: Interner<T, S>) -> Self {
+        Self::from_interner(interner)
+    }
+}
+
+impl<T, S, U, E> Interning<U, E> for &SyncInterner<T, S>
+where
+    Interner<T, S>: Interning<U, E>,
+    U: ?Sized,
+{
+    type State<'a> = <Interner<T, S> as Interning<U, E>>::State<'a>
+    where
+        U: 'a;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a U,
+    ) -> InterningState<Self::State<'a>> {
+        self.lock().start_interning(value)
+    }
+
+    fn start_interning_with_hash<'a>(
+        &mut self,
+        value: &'a U,
+        hash: u64,
+    ) -> InterningState<Self::State<'a>> {
+        self.lock().start_interning_with_hash(value, hash)
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        self.lock().finish_interning(state, pos)
+    }
+
+    fn record_hit(&mut self, value: &U, pos: usize) {
+        Interning::<U, E>::record_hit(&mut *self.lock(), value, pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        string::{String, ToString},
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        vec::Vec,
+    };
+
+    use rkyv::rancor::Panic;
+
+    use crate::{
+        testing::check_interning_contract, tests::USERS, Interning,
+        InterningState, SyncInterner,
+    };
+
+    #[test]
+    fn sync_interner_threads() {
+        fn intern(
+            mut interner: &SyncInterner<String>,
+            value: &str,
+            pos: usize,
+            started: &AtomicUsize,
+        ) -> usize {
+            loop {
+                match Interning::<str, Panic>::start_interning(
+                    &mut interner,
+                    value,
+                ) {
+                    InterningState::Started(state) => {
+                        started.fetch_add(1, Ordering::SeqCst);
+                        Interning::<str, Panic>::finish_interning(
+                            &mut interner,
+                            state,
+                            pos,
+                        )
+                        .unwrap();
+                    }
+                    InterningState::Pending => thread::yield_now(),
+                    InterningState::Finished(pos) => return pos,
+                }
+            }
+        }
+
+        let values = (0..100).map(|i| i.to_string()).collect::<Vec<_>>();
+        let started = values
+            .iter()
+            .map(|_| AtomicUsize::new(0))
+            .collect::<Vec<_>>();
+        let interner = SyncInterner::<String>::new();
+
+        thread::scope(|scope| {
+            for offset in 0..8 {
+                let (values, started, interner) =
+                    (&values, &started, &interner);
+                scope.spawn(move || {
+                    for i in 0..values.len() {
+                        let i = (i + offset * 13) % values.len();
+                        let pos =
+                            intern(interner, &values[i], i * 8, &started[i]);
+                        assert_eq!(pos, i * 8);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(interner.len(), values.len());
+        assert!(started.iter().all(|count| count.load(Ordering::SeqCst) == 1));
+    }
+
+    #[test]
+    fn sync_interner_follows_contract() {
+        let interner = SyncInterner::<String>::new();
+        check_interning_contract::<_, Panic>(&mut &interner);
+        assert!(!interner.lock().contains(USERS[0]));
+    }
+}
@@ -0,0 +1,245 @@
+//! Utilities for testing [`Interning`] implementations and interned types.
+
+use core::fmt::Debug;
+
+#[cfg(feature = "testing")]
+use rkyv::{
+    de::Pool,
+    rancor::{Source, Strategy},
+    Archive, Deserialize, Portable, Serialize,
+};
+
+#[cfg(feature = "testing")]
+use crate::{to_bytes, InterningSerializer, MultiInterner};
+use crate::{Interning, InterningState};
+
+/// The values interned by [`check_interning_contract`].
+pub const CONTRACT_VALUES: [&str; 2] = [
+    "rkyv_intern::testing::CONTRACT_VALUES[0]",
+    "rkyv_intern::testing::CONTRACT_VALUES[1]",
+];
+
+/// Checks that an interner follows the contract of [`Interning`].
+///
+/// This interns each of [`CONTRACT_VALUES`], which must not have been interned
+/// already, and checks that:
+///
+/// - Starting a new value returns `Started`, and finishing it succeeds.
+/// - Starting a value again before it is finished returns `Pending`.
+/// - Starting a finished value returns `Finished` with the position it was
+///   finished with.
+///
+/// # Panics
+///
+/// Panics if the interner doesn't follow the contract.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "alloc")]
+/// # {
+/// use rkyv::rancor::Error;
+/// use rkyv_intern::{testing::check_interning_contract, Interner};
+///
+/// check_interning_contract::<_, Error>(&mut Interner::<String>::new());
+/// # }
+/// ```
+pub fn check_interning_contract<I, E>(interner: &mut I)
+where
+    I: Interning<str, E> + ?Sized,
+    E: Debug,
+{
+    for (value, pos) in CONTRACT_VALUES.iter().zip([0, 1024]) {
+        let state = match interner.start_interning(value) {
+            InterningState::Started(state) => state,
+            InterningState::Pending => {
+                panic!("new value {:?} was already pending", value)
+            }
+            InterningState::Finished(pos) => panic!(
+                "new value {:?} was already finished at {}",
+                value, pos,
+            ),
+        };
+
+        for _ in 0..2 {
+            match interner.start_interning(value) {
+                InterningState::Pending => (),
+                InterningState::Started(_) => {
+                    panic!("started value {:?} was started again", value)
+                }
+                InterningState::Finished(pos) => panic!(
+                    "started value {:?} was finished at {} before it was \
+                     finished",
+                    value, pos,
+                ),
+            }
+        }
+
+        if let Err(error) = interner.finish_interning(state, pos) {
+            panic!("failed to finish value {:?}: {:?}", value, error);
+        }
+
+        for _ in 0..2 {
+            match interner.start_interning(value) {
+                InterningState::Finished(actual) => assert_eq!(
+                    actual, pos,
+                    "finished value {:?} has the wrong position",
+                    value,
+                ),
+                InterningState::Started(_) => {
+                    panic!("finished value {:?} was started again", value)
+                }
+                InterningState::Pending => {
+                    panic!("finished value {:?} is still pending", value)
+                }
+            }
+        }
+    }
+}
+
+/// Serializes a value with interning, deserializes it again, and asserts that
+/// the result is equal to the value.
+///
+/// Values are interned with a new [`MultiInterner`], so any mix of interning
+/// wrappers can be used. Returns the length of the serialized bytes, so callers
+/// can also check how well the value was deduplicated.
+///
+/// # Panics
+///
+/// Panics if serializing or deserializing fails, or if the deserialized value
+/// isn't equal to `value`.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{rancor::Error, Archive, Deserialize, Serialize};
+/// use rkyv_intern::{testing::assert_interned_roundtrip, Intern};
+///
+/// #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+/// struct Log {
+///     #[rkyv(with = Intern)]
+///     user: String,
+/// }
+///
+/// let value = vec![
+///     Log { user: "alice".to_string() },
+///     Log { user: "alice".to_string() },
+/// ];
+/// let len = assert_interned_roundtrip::<_, Error>(&value);
+/// assert!(len < 2 * "alice".len() + 2 * 8);
+/// ```
+#[cfg(feature = "testing")]
+pub fn assert_interned_roundtrip<T, E>(value: &T) -> usize
+where
+    T: Archive
+        + for<'a> Serialize<InterningSerializer<'a, MultiInterner, E>>
+        + PartialEq
+        + Debug,
+    T::Archived: Portable + Deserialize<T, Strategy<Pool, E>>,
+    E: Source + Debug,
+{
+    let bytes = match to_bytes::<E>(value) {
+        Ok(bytes) => bytes,
+        Err(error) => panic!("failed to serialize {:?}: {:?}", value, error),
+    };
+    // SAFETY: The bytes were just serialized from a value of type `T`.
+    let archived = unsafe { rkyv::access_unchecked::<T::Archived>(&bytes) };
+    match rkyv::deserialize::<T, E>(archived) {
+        Ok(deserialized) => assert_eq!(
+            &deserialized, value,
+            "deserialized value isn't equal to the serialized value",
+        ),
+        Err(error) => panic!("failed to deserialize {:?}: {:?}", value, error),
+    }
+    bytes.len()
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::string::String;
+
+    use rkyv::rancor::Panic;
+
+    use crate::{
+        testing::check_interning_contract, BorrowedInterner, BoundedInterner,
+        EvictionPolicy, Interner, Interning, InterningState, MultiInterner,
+    };
+
+    #[test]
+    fn interners_follow_contract() {
+        check_interning_contract::<_, Panic>(&mut Interner::<String>::new());
+        check_interning_contract::<_, Panic>(&mut MultiInterner::new());
+        let mut bounded =
+            BoundedInterner::<String>::new(2, EvictionPolicy::Oldest);
+        check_interning_contract::<_, Panic>(&mut bounded);
+        // SAFETY: The contract values are static.
+        let mut borrowed = unsafe { BorrowedInterner::<'static, str>::new() };
+        check_interning_contract::<_, Panic>(&mut borrowed);
+    }
+
+    struct OffByOne(Interner<String>);
+
+    impl Interning<str, Panic> for OffByOne {
+        type State<'a> = <Interner<String> as Interning<str, Panic>>::State<'a>;
+
+        fn start_interning<'a>(
+            &mut self,
+            value: &'a str,
+        ) -> InterningState<Self::State<'a>> {
+            let state =
+                Interning::<str, Panic>::start_interning(&mut self.0, value);
+            match state {
+                InterningState::Finished(pos) => {
+                    InterningState::Finished(pos + 1)
+                }
+                state => state,
+            }
+        }
+
+        fn finish_interning(
+            &mut self,
+            state: Self::State<'_>,
+            pos: usize,
+        ) -> Result<(), Panic> {
+            self.0.finish_interning(state, pos)
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn interned_roundtrip() {
+        use alloc::{string::ToString, vec::Vec};
+
+        use rkyv::{Archive, Deserialize, Serialize};
+
+        use crate::{
+            testing::assert_interned_roundtrip, tests::USERS, DerefIntern,
+            Intern,
+        };
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = DerefIntern)]
+            user: String,
+            #[rkyv(with = Intern)]
+            tags: Vec<u32>,
+        }
+
+        let value = (0..100)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+                tags: (0..i as u32 % 3).collect(),
+            })
+            .collect::<Vec<_>>();
+        let len = assert_interned_roundtrip::<_, Panic>(&value);
+        // Each log is two relative pointers, followed by one copy of each
+        // user and list of tags.
+        assert!(len < value.len() * 16 + 256);
+    }
+
+    #[test]
+    #[should_panic = "has the wrong position"]
+    fn off_by_one_position() {
+        check_interning_contract(&mut OffByOne(Interner::new()));
+    }
+}
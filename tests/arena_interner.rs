@@ -0,0 +1,74 @@
+#![cfg(feature = "alloc")]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use rkyv::rancor::Panic;
+use rkyv_intern::{ArenaInterner, Interner, Interning, InterningState};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// Interns every value twice, and returns the number of allocations made.
+fn intern_all<I>(interner: &mut I, values: &[String]) -> usize
+where
+    I: Interning<str, Panic>,
+{
+    let before = allocations();
+    for (pos, value) in values.iter().enumerate() {
+        match interner.start_interning(value) {
+            InterningState::Started(state) => {
+                interner.finish_interning(state, pos).unwrap()
+            }
+            _ => panic!("value {:?} should be new", value),
+        }
+    }
+    for (pos, value) in values.iter().enumerate() {
+        match interner.start_interning(value) {
+            InterningState::Finished(actual) => assert_eq!(actual, pos),
+            _ => panic!("value {:?} should be finished", value),
+        }
+    }
+    allocations() - before
+}
+
+#[test]
+fn arena_interner_allocates_less() {
+    let values = (0..100_000)
+        .map(|i| format!("{:x}", i))
+        .collect::<Vec<_>>();
+
+    let mut arena = ArenaInterner::new();
+    let arena_allocations = intern_all(&mut arena, &values);
+    assert_eq!(arena.len(), values.len());
+
+    let mut interner = Interner::<String>::new();
+    let interner_allocations = intern_all(&mut interner, &values);
+    assert_eq!(interner.len(), values.len());
+
+    assert!(interner_allocations >= values.len());
+    assert!(arena_allocations < values.len() / 100);
+}
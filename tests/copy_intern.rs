@@ -0,0 +1,93 @@
+#![cfg(feature = "alloc")]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    borrow::Borrow,
+    cell::Cell,
+};
+
+use rkyv::{
+    access_unchecked,
+    api::serialize_using,
+    rancor::{Panic, Strategy},
+    ser::{allocator::Arena, Serializer},
+    util::AlignedVec,
+    Archive, Archived, Deserialize, Serialize,
+};
+use rkyv_intern::{CopyIntern, Interner, InterningAdapter};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Hash([u8; 16]);
+
+impl Borrow<[u8; 16]> for Hash {
+    fn borrow(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl From<[u8; 16]> for Hash {
+    fn from(value: [u8; 16]) -> Self {
+        Hash(value)
+    }
+}
+
+#[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+struct Pair {
+    #[rkyv(with = CopyIntern<[u8; 16]>)]
+    left: Hash,
+    #[rkyv(with = CopyIntern<[u8; 16]>)]
+    right: Hash,
+}
+
+#[test]
+fn copy_intern_deserializes_without_allocating() {
+    let value = Pair {
+        left: Hash([1; 16]),
+        right: Hash([1; 16]),
+    };
+
+    let mut arena = Arena::new();
+    let mut serializer = InterningAdapter::new(
+        Serializer::new(AlignedVec::<16>::new(), arena.acquire(), ()),
+        Interner::<[u8; 16]>::new(),
+    );
+    serialize_using::<_, Panic>(&value, &mut serializer).unwrap();
+    let (serializer, interner) = serializer.into_components();
+    assert_eq!(interner.len(), 1);
+    let bytes = serializer.into_writer();
+
+    let archived = unsafe { access_unchecked::<Archived<Pair>>(&bytes) };
+    assert_eq!(*archived.left, [1; 16]);
+    assert!(core::ptr::eq(&*archived.left, &*archived.right));
+
+    let before = allocations();
+    let deserialized: Pair = archived
+        .deserialize(Strategy::<_, Panic>::wrap(&mut ()))
+        .unwrap();
+    assert_eq!(allocations(), before);
+    assert_eq!(deserialized, value);
+}
@@ -0,0 +1,100 @@
+//! Interning without an allocator, which also builds with
+//! `--no-default-features`.
+
+use rkyv::{
+    access_unchecked,
+    api::serialize_using,
+    rancor::Panic,
+    ser::{writer::Buffer, Serializer},
+    Archive, Archived, Serialize,
+};
+use rkyv_intern::{
+    Intern, Interning, InterningAdapter, InterningError, InterningState,
+};
+
+/// An interner which stores up to `N` values in an array.
+struct ArrayInterner<const N: usize> {
+    entries: [(u32, Option<usize>); N],
+    len: usize,
+}
+
+impl<const N: usize> ArrayInterner<N> {
+    fn new() -> Self {
+        Self {
+            entries: [(0, None); N],
+            len: 0,
+        }
+    }
+}
+
+impl<E: InterningError, const N: usize> Interning<u32, E> for ArrayInterner<N> {
+    type State<'a> = Option<usize>;
+
+    fn start_interning<'a>(
+        &mut self,
+        value: &'a u32,
+    ) -> InterningState<Self::State<'a>> {
+        let entries = &self.entries[..self.len];
+        match entries.iter().position(|(v, _)| v == value) {
+            Some(index) => match entries[index].1 {
+                Some(pos) => InterningState::Finished(pos),
+                None => InterningState::Pending,
+            },
+            // Values which don't fit are written without being interned.
+            None if self.len == N => InterningState::Started(None),
+            None => {
+                self.entries[self.len] = (*value, None);
+                self.len += 1;
+                InterningState::Started(Some(self.len - 1))
+            }
+        }
+    }
+
+    fn finish_interning(
+        &mut self,
+        state: Self::State<'_>,
+        pos: usize,
+    ) -> Result<(), E> {
+        if let Some(index) = state {
+            self.entries[index].1 = Some(pos);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Archive, Serialize)]
+struct Reading {
+    #[rkyv(with = Intern)]
+    sensor: u32,
+    value: u16,
+}
+
+#[repr(align(16))]
+struct Aligned([u8; 256]);
+
+#[test]
+fn intern_without_alloc() {
+    let value: [Reading; 8] = core::array::from_fn(|i| Reading {
+        sensor: i as u32 % 3,
+        value: i as u16,
+    });
+
+    let mut bytes = Aligned([0; 256]);
+    let mut serializer = InterningAdapter::new(
+        Serializer::new(Buffer::from(&mut bytes.0), (), ()),
+        ArrayInterner::<2>::new(),
+    );
+    serialize_using::<_, Panic>(&value, &mut serializer).unwrap();
+    let (serializer, interner) = serializer.into_components();
+    assert_eq!(interner.len, 2);
+    let buffer = serializer.into_writer();
+
+    let archived =
+        unsafe { access_unchecked::<Archived<[Reading; 8]>>(&buffer) };
+    for (a, b) in archived.iter().zip(value.iter()) {
+        assert_eq!(*a.sensor, b.sensor);
+        assert_eq!(a.value, b.value);
+    }
+    assert!(core::ptr::eq(&*archived[0].sensor, &*archived[3].sensor));
+    assert!(!core::ptr::eq(&*archived[2].sensor, &*archived[5].sensor));
+}
@@ -0,0 +1,10 @@
+use rkyv::Archive;
+use rkyv_intern::BorrowIntern;
+
+#[derive(Archive)]
+struct Example {
+    #[rkyv(with = BorrowIntern<str>)]
+    bytes: Vec<u8>,
+}
+
+fn main() {}